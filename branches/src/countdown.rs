@@ -0,0 +1,410 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+// COUNTDOWN TIMER
+
+// This grows the `while value != 0` LIFTOFF loop into a real timer.
+// The loop itself looks the same - count down one step at a time - but
+// each step waits on a "tick source" instead of running instantly.
+
+// A tick source is anything that can wait for one second to pass. The real
+// one sleeps the thread; a fake one (e.g. one that just counts how many times
+// it was called) can be swapped in so the loop can be driven without waiting.
+pub trait Ticker {
+    fn tick(&mut self);
+}
+
+pub struct SleepTicker {
+    pub interval: Duration,
+}
+
+impl Ticker for SleepTicker {
+    fn tick(&mut self) {
+        thread::sleep(self.interval);
+    }
+}
+
+// Messages the keyboard thread sends to the countdown loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Pause,
+    Resume,
+    Quit,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Finished,
+    Quit { remaining: u64 },
+}
+
+// What to do when the timer hits zero.
+pub enum Alarm {
+    Bell,
+    Exec(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseDurationError {
+    Empty,
+    MissingUnit(String),
+    UnknownUnit(char),
+    MissingNumber(char),
+    // "1h1h" - each unit can only be used once.
+    RepeatedUnit(char),
+    // "5s1m" - units go from biggest to smallest.
+    UnitOutOfOrder(char),
+    Overflow,
+}
+
+impl fmt::Display for ParseDurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseDurationError::Empty => write!(f, "duration is empty"),
+            ParseDurationError::MissingUnit(n) => {
+                write!(f, "number {n} is missing a unit (h, m or s)")
+            }
+            ParseDurationError::UnknownUnit(c) => {
+                write!(f, "unknown unit '{c}' (expected h, m or s)")
+            }
+            ParseDurationError::MissingNumber(c) => {
+                write!(f, "unit '{c}' has no number in front of it")
+            }
+            ParseDurationError::RepeatedUnit(c) => write!(f, "unit '{c}' is used more than once"),
+            ParseDurationError::UnitOutOfOrder(c) => {
+                write!(f, "unit '{c}' is out of order (expected h, then m, then s)")
+            }
+            ParseDurationError::Overflow => write!(f, "duration is too large"),
+        }
+    }
+}
+
+// Parses durations like "90s", "5m", "1h30m" or "1h2m3s". A bare number
+// ("45") is treated as seconds, since that's what most people mean. Each
+// unit may appear once, biggest first - "1h1h" and "5s1m" are mistakes.
+pub fn parse_duration(input: &str) -> Result<Duration, ParseDurationError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(ParseDurationError::Empty);
+    }
+    if input.chars().all(|c| c.is_ascii_digit()) {
+        let secs = input
+            .parse::<u64>()
+            .map_err(|_| ParseDurationError::Overflow)?;
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    // the scale of the last unit seen; the next one must be smaller.
+    let mut last_scale = None;
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let scale = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            other => return Err(ParseDurationError::UnknownUnit(other)),
+        };
+        if digits.is_empty() {
+            return Err(ParseDurationError::MissingNumber(c));
+        }
+        match last_scale {
+            Some(last) if last == scale => return Err(ParseDurationError::RepeatedUnit(c)),
+            Some(last) if last < scale => return Err(ParseDurationError::UnitOutOfOrder(c)),
+            _ => last_scale = Some(scale),
+        }
+        let amount = digits
+            .parse::<u64>()
+            .map_err(|_| ParseDurationError::Overflow)?;
+        total = amount
+            .checked_mul(scale)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or(ParseDurationError::Overflow)?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() {
+        return Err(ParseDurationError::MissingUnit(digits));
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+// Formats a number of seconds as h:mm:ss (or m:ss when under an hour).
+pub fn format_remaining(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+// Spawns a thread that turns keyboard input into Control messages.
+// Stdin is line-buffered, so each command is a letter followed by Enter:
+// p = pause, r = resume, q = quit.
+pub fn spawn_keyboard() -> Receiver<Control> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            let control = match line.trim() {
+                "p" => Control::Pause,
+                "r" => Control::Resume,
+                "q" => Control::Quit,
+                _ => continue,
+            };
+            if tx.send(control).is_err() {
+                break; // the countdown is over - nobody is listening.
+            }
+        }
+    });
+    rx
+}
+
+// The countdown loop. Same shape as the LIFTOFF while loop - but between each
+// step we check for keyboard commands and wait for the next tick.
+pub fn run<T: Ticker, W: Write>(
+    total: Duration,
+    ticker: &mut T,
+    controls: &Receiver<Control>,
+    out: &mut W,
+) -> io::Result<Outcome> {
+    let mut remaining = total.as_secs();
+    let mut paused = false;
+
+    while remaining != 0 {
+        // drain any commands that arrived during the last tick.
+        loop {
+            let control = if paused {
+                // while paused there's nothing to do but wait for input, so
+                // we block instead of spinning.
+                match controls.recv() {
+                    Ok(control) => control,
+                    Err(_) => Control::Resume, // keyboard closed - carry on.
+                }
+            } else {
+                match controls.try_recv() {
+                    Ok(control) => control,
+                    Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
+                }
+            };
+            match control {
+                Control::Pause => {
+                    if !paused {
+                        writeln!(out, "paused at {}", format_remaining(remaining))?;
+                    }
+                    paused = true;
+                }
+                Control::Resume => {
+                    if paused {
+                        writeln!(out, "resumed")?;
+                    }
+                    paused = false;
+                }
+                Control::Quit => return Ok(Outcome::Quit { remaining }),
+            }
+        }
+
+        writeln!(out, "{}", format_remaining(remaining))?;
+        ticker.tick();
+        remaining -= 1;
+    }
+
+    Ok(Outcome::Finished)
+}
+
+pub fn ring(alarm: &Alarm) -> io::Result<()> {
+    match alarm {
+        Alarm::Bell => {
+            // \x07 is the ASCII "bell" character - most terminals beep or flash.
+            println!("\x07LIFTOFF!!");
+            io::stdout().flush()
+        }
+        Alarm::Exec(command) => {
+            let status = shell(command).status()?;
+            if !status.success() {
+                eprintln!("alarm command exited with {status}");
+            }
+            Ok(())
+        }
+    }
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+// Entry point for `cargo run -- countdown <duration> [--exec <command>]`.
+pub fn cli(args: &[String]) -> Result<(), String> {
+    let mut duration = None;
+    let mut alarm = Alarm::Bell;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--exec" => {
+                let command = args.next().ok_or("--exec needs a command")?;
+                alarm = Alarm::Exec(command.clone());
+            }
+            other if duration.is_none() => {
+                duration = Some(parse_duration(other).map_err(|e| e.to_string())?);
+            }
+            other => return Err(format!("unexpected argument '{other}'")),
+        }
+    }
+
+    let duration = duration.ok_or("usage: countdown <duration> [--exec <command>]")?;
+
+    println!(
+        "counting down from {} (p = pause, r = resume, q = quit)",
+        format_remaining(duration.as_secs())
+    );
+    let controls = spawn_keyboard();
+    let mut ticker = SleepTicker {
+        interval: Duration::from_secs(1),
+    };
+    let outcome =
+        run(duration, &mut ticker, &controls, &mut io::stdout()).map_err(|e| e.to_string())?;
+
+    match outcome {
+        Outcome::Finished => ring(&alarm).map_err(|e| e.to_string()),
+        Outcome::Quit { remaining } => {
+            println!("stopped with {} left", format_remaining(remaining));
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Sender;
+
+    // A ticker that doesn't wait. Before tick n it sends whatever controls
+    // are scripted for n, as if someone typed them during that second.
+    struct FakeTicker {
+        ticks: usize,
+        script: Vec<(usize, Control)>,
+        controls: Sender<Control>,
+    }
+
+    fn countdown(secs: u64, script: Vec<(usize, Control)>) -> (Outcome, usize, String) {
+        let (tx, rx) = mpsc::channel();
+        let mut ticker = FakeTicker {
+            ticks: 0,
+            script,
+            controls: tx,
+        };
+        let mut out = Vec::new();
+        let outcome = run(Duration::from_secs(secs), &mut ticker, &rx, &mut out).unwrap();
+        (outcome, ticker.ticks, String::from_utf8(out).unwrap())
+    }
+
+    impl Ticker for FakeTicker {
+        fn tick(&mut self) {
+            for &(at, control) in &self.script {
+                if at == self.ticks {
+                    self.controls.send(control).unwrap();
+                }
+            }
+            self.ticks += 1;
+        }
+    }
+
+    #[test]
+    fn counts_down_to_zero() {
+        let (outcome, ticks, out) = countdown(3, vec![]);
+        assert_eq!(outcome, Outcome::Finished);
+        assert_eq!(ticks, 3);
+        assert_eq!(out, "0:03\n0:02\n0:01\n");
+    }
+
+    #[test]
+    fn zero_finishes_straight_away() {
+        assert_eq!(countdown(0, vec![]), (Outcome::Finished, 0, String::new()));
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let script = vec![
+            (0, Control::Pause),
+            (0, Control::Pause),
+            (0, Control::Resume),
+        ];
+        let (outcome, ticks, out) = countdown(3, script);
+        assert_eq!(outcome, Outcome::Finished);
+        assert_eq!(ticks, 3);
+        // the second pause is ignored - it's already paused.
+        assert_eq!(out, "0:03\npaused at 0:02\nresumed\n0:02\n0:01\n");
+    }
+
+    #[test]
+    fn resume_without_pause_does_nothing() {
+        let (_, _, out) = countdown(2, vec![(0, Control::Resume)]);
+        assert_eq!(out, "0:02\n0:01\n");
+    }
+
+    #[test]
+    fn quit_stops_early() {
+        let (outcome, ticks, out) = countdown(10, vec![(1, Control::Quit)]);
+        assert_eq!(outcome, Outcome::Quit { remaining: 8 });
+        assert_eq!(ticks, 2);
+        assert_eq!(out, "0:10\n0:09\n");
+    }
+
+    #[test]
+    fn quit_while_paused() {
+        let script = vec![(0, Control::Pause), (0, Control::Quit)];
+        let (outcome, _, out) = countdown(5, script);
+        assert_eq!(outcome, Outcome::Quit { remaining: 4 });
+        assert_eq!(out, "0:05\npaused at 0:04\n");
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration(" 1h2m3s "), Ok(Duration::from_secs(3723)));
+        assert_eq!(parse_duration("1h3s"), Ok(Duration::from_secs(3603)));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        use ParseDurationError::*;
+        assert_eq!(parse_duration(""), Err(Empty));
+        assert_eq!(parse_duration("1h30"), Err(MissingUnit(String::from("30"))));
+        assert_eq!(parse_duration("5d"), Err(UnknownUnit('d')));
+        assert_eq!(parse_duration("m"), Err(MissingNumber('m')));
+        assert_eq!(parse_duration("1h1h"), Err(RepeatedUnit('h')));
+        assert_eq!(parse_duration("5s1m"), Err(UnitOutOfOrder('m')));
+        assert_eq!(parse_duration("1m2h"), Err(UnitOutOfOrder('h')));
+        assert_eq!(parse_duration("99999999999999999999"), Err(Overflow));
+        assert_eq!(parse_duration("9999999999999999h"), Err(Overflow));
+    }
+
+    #[test]
+    fn formats_remaining_time() {
+        assert_eq!(format_remaining(0), "0:00");
+        assert_eq!(format_remaining(75), "1:15");
+        assert_eq!(format_remaining(3723), "1:02:03");
+    }
+}
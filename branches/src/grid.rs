@@ -0,0 +1,363 @@
+// GRID SEARCH
+
+// The 'counting_up example nests one loop inside another and uses a label to
+// break out of the outer one. That's fine for two loops, but a search over
+// three, four, or N nested loops gets deep fast, and the number of loops is
+// baked into the code.
+
+// A Grid does the nesting for us. Each Axis is one loop level (outermost
+// first), and `search` walks every combination like an odometer - the last
+// axis ticks fastest, and when it runs out the one before it moves on.
+// The closure decides what happens at each point by returning a Flow,
+// which stands in for the loop keywords:
+//
+//   Flow::Continue      -> keep going (the end of the innermost loop body)
+//   Flow::Next(level)   -> `continue 'level` - skip the rest of that loop's
+//                          current pass and move it to its next value
+//   Flow::Break(value)  -> `break 'outermost value` - stop everything and
+//                          hand back a result, like `break counter * 2`
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Axis {
+    pub start: i64,
+    pub end: i64, // inclusive, like start..=end
+    pub step: i64,
+}
+
+impl Axis {
+    pub fn new(start: i64, end: i64) -> Axis {
+        Axis {
+            start,
+            end,
+            step: 1,
+        }
+    }
+
+    // A negative step counts down from start to end, like (end..=start).rev().
+    // A step of 0 would never get anywhere, so it's an error.
+    pub fn step_by(self, step: i64) -> Result<Axis, ZeroStepError> {
+        if step == 0 {
+            return Err(ZeroStepError);
+        }
+        Ok(Axis { step, ..self })
+    }
+
+    fn contains(&self, value: i64) -> bool {
+        if self.step > 0 {
+            value >= self.start && value <= self.end
+        } else {
+            value <= self.start && value >= self.end
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.contains(self.start)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroStepError;
+
+impl fmt::Display for ZeroStepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an Axis step must not be zero")
+    }
+}
+
+impl std::error::Error for ZeroStepError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flow<T> {
+    Continue,
+    Next(usize),
+    Break(T),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Search<T> {
+    pub result: Option<T>,   // None if the grid ran out without a Break.
+    pub visited: u64,        // how many points the closure was called with.
+    pub per_level: Vec<u64>, // how many values each loop level took on.
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    axes: Vec<Axis>,
+}
+
+impl Grid {
+    pub fn new(axes: Vec<Axis>) -> Grid {
+        Grid { axes }
+    }
+
+    // Every axis gets the same bounds - handy for "pick N numbers from 1..=9".
+    pub fn cube(dimensions: usize, axis: Axis) -> Grid {
+        Grid {
+            axes: vec![axis; dimensions],
+        }
+    }
+
+    pub fn search<T, F>(&self, mut visit: F) -> Search<T>
+    where
+        F: FnMut(&[i64]) -> Flow<T>,
+    {
+        let mut search = Search {
+            result: None,
+            visited: 0,
+            per_level: vec![0; self.axes.len()],
+        };
+
+        if self.axes.iter().any(Axis::is_empty) {
+            return search;
+        }
+
+        let mut point: Vec<i64> = self.axes.iter().map(|axis| axis.start).collect();
+        for count in search.per_level.iter_mut() {
+            *count += 1;
+        }
+
+        loop {
+            search.visited += 1;
+            let level = match visit(&point) {
+                Flow::Continue => self.axes.len().saturating_sub(1),
+                Flow::Next(level) => level.min(self.axes.len().saturating_sub(1)),
+                Flow::Break(value) => {
+                    search.result = Some(value);
+                    break;
+                }
+            };
+            if !self.advance(&mut point, level, &mut search.per_level) {
+                break;
+            }
+        }
+
+        search
+    }
+
+    // Moves loop `level` on to its next value, resetting every loop inside
+    // it. If that loop is finished, the one outside it moves instead.
+    // Returns false once the outermost loop is finished too.
+    fn advance(&self, point: &mut [i64], level: usize, per_level: &mut [u64]) -> bool {
+        for l in (0..point.len().min(level + 1)).rev() {
+            let axis = &self.axes[l];
+            let next = point[l].checked_add(axis.step);
+            if let Some(next) = next.filter(|n| axis.contains(*n)) {
+                point[l] = next;
+                per_level[l] += 1;
+                for inner in l + 1..point.len() {
+                    point[inner] = self.axes[inner].start;
+                    per_level[inner] += 1;
+                }
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// EXAMPLE PUZZLES
+
+// n * n, in a type big enough that it can't overflow: an i64 squared is
+// under 2^126, so even two of them added together fit in a u128.
+fn square(n: i64) -> u128 {
+    let n = u128::from(n.unsigned_abs());
+    n * n
+}
+
+// All Pythagorean triples a < b < c with c <= limit. The innermost loop
+// (c) bails out early with Next(1) once c*c passes a*a + b*b, since larger
+// c can't work either - that's `continue 'b_loop`.
+pub fn pythagorean_triples(limit: i64) -> (Vec<(i64, i64, i64)>, Search<()>) {
+    let grid = Grid::cube(3, Axis::new(1, limit));
+    let mut triples = Vec::new();
+
+    let search = grid.search(|p| {
+        let (a, b, c) = (p[0], p[1], p[2]);
+        if b <= a {
+            return Flow::Next(1);
+        }
+        if c <= b {
+            return Flow::Continue;
+        }
+        let (sum, hypotenuse) = (square(a) + square(b), square(c));
+        if hypotenuse == sum {
+            triples.push((a, b, c));
+        }
+        if hypotenuse >= sum {
+            return Flow::Next(1);
+        }
+        Flow::Continue
+    });
+
+    (triples, search)
+}
+
+// The first Pythagorean triple whose sides add up to `perimeter` - the
+// search stops as soon as it's found, and hands the triple back through
+// Break, just like `break counter * 2` hands back a value from `loop`.
+pub fn triple_with_perimeter(perimeter: i64) -> Search<(i64, i64, i64)> {
+    let grid = Grid::cube(2, Axis::new(1, perimeter / 2));
+
+    grid.search(|p| {
+        let (a, b) = (p[0], p[1]);
+        if b <= a {
+            return Flow::Continue;
+        }
+        let c = perimeter - a - b;
+        if c <= b {
+            return Flow::Next(0); // b only gets bigger, so move a on.
+        }
+        if square(a) + square(b) == square(c) {
+            Flow::Break((a, b, c))
+        } else {
+            Flow::Continue
+        }
+    })
+}
+
+// A 3x3 magic square uses 1..=9 once each, and every row, column, and
+// diagonal adds up to 15. Picking the top-left, top-middle, middle-left and
+// center cells fixes all the others, so the grid is only 4 levels deep.
+pub fn magic_squares() -> (Vec<[[i64; 3]; 3]>, Search<()>) {
+    const SUM: i64 = 15;
+    let grid = Grid::cube(4, Axis::new(1, 9));
+    let mut squares = Vec::new();
+
+    let search = grid.search(|p| {
+        let (a, b, d, e) = (p[0], p[1], p[2], p[3]);
+        if b == a {
+            return Flow::Next(1);
+        }
+        if d == a || d == b {
+            return Flow::Next(2);
+        }
+
+        let c = SUM - a - b;
+        let g = SUM - a - d;
+        let f = SUM - d - e;
+        let h = SUM - b - e;
+        let i = SUM - c - f;
+        let square = [[a, b, c], [d, e, f], [g, h, i]];
+
+        if is_magic(&square, SUM) {
+            squares.push(square);
+        }
+        Flow::Continue
+    });
+
+    (squares, search)
+}
+
+fn is_magic(square: &[[i64; 3]; 3], sum: i64) -> bool {
+    let mut seen = [false; 10];
+    for value in square.iter().flatten() {
+        if !(1..=9).contains(value) || seen[*value as usize] {
+            return false;
+        }
+        seen[*value as usize] = true;
+    }
+
+    let rows = square.iter().all(|row| row.iter().sum::<i64>() == sum);
+    let columns = (0..3).all(|c| (0..3).map(|r| square[r][c]).sum::<i64>() == sum);
+    let diagonals = (0..3).map(|i| square[i][i]).sum::<i64>() == sum
+        && (0..3).map(|i| square[i][2 - i]).sum::<i64>() == sum;
+
+    rows && columns && diagonals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_step_is_an_error() {
+        assert_eq!(Axis::new(0, 5).step_by(0), Err(ZeroStepError));
+        assert_eq!(Axis::new(5, 0).step_by(-2).map(|axis| axis.step), Ok(-2));
+    }
+
+    #[test]
+    fn walks_like_nested_loops() {
+        let grid = Grid::new(vec![Axis::new(0, 1), Axis::new(3, 1).step_by(-2).unwrap()]);
+        let mut points = Vec::new();
+        let search: Search<()> = grid.search(|p| {
+            points.push(p.to_vec());
+            Flow::Continue
+        });
+        assert_eq!(points, [[0, 3], [0, 1], [1, 3], [1, 1]]);
+        assert_eq!(search.visited, 4);
+        assert_eq!(search.per_level, [2, 4]);
+    }
+
+    #[test]
+    fn next_skips_the_rest_of_a_level_and_break_stops() {
+        let grid = Grid::cube(2, Axis::new(1, 3));
+        let mut points = Vec::new();
+        let search = grid.search(|p| {
+            points.push(p.to_vec());
+            match (p[0], p[1]) {
+                (1, 2) => Flow::Next(0), // continue 'outer
+                (3, 2) => Flow::Break(p[0] * 10 + p[1]),
+                _ => Flow::Continue,
+            }
+        });
+        assert_eq!(
+            points,
+            [[1, 1], [1, 2], [2, 1], [2, 2], [2, 3], [3, 1], [3, 2]]
+        );
+        assert_eq!(search.result, Some(32));
+    }
+
+    #[test]
+    fn empty_axis_visits_nothing() {
+        let search: Search<()> =
+            Grid::new(vec![Axis::new(1, 3), Axis::new(2, 1)]).search(|_| Flow::Continue);
+        assert_eq!(search.visited, 0);
+    }
+
+    #[test]
+    fn finds_the_known_triples() {
+        let (triples, _) = pythagorean_triples(20);
+        assert_eq!(
+            triples,
+            [
+                (3, 4, 5),
+                (5, 12, 13),
+                (6, 8, 10),
+                (8, 15, 17),
+                (9, 12, 15),
+                (12, 16, 20)
+            ]
+        );
+    }
+
+    #[test]
+    fn triples_near_the_limit_of_i64_do_not_overflow() {
+        let big = i64::MAX - 1;
+        let search = Grid::new(vec![Axis::new(big, big + 1); 3]).search(|p| {
+            let sum = square(p[0]) + square(p[1]);
+            Flow::Break(sum == square(p[2]))
+        });
+        assert_eq!(search.result, Some(false));
+    }
+
+    #[test]
+    fn finds_the_triple_with_perimeter_1000() {
+        assert_eq!(triple_with_perimeter(1000).result, Some((200, 375, 425)));
+        assert_eq!(triple_with_perimeter(12).result, Some((3, 4, 5)));
+        assert_eq!(triple_with_perimeter(11).result, None);
+    }
+
+    #[test]
+    fn finds_all_eight_magic_squares() {
+        let (squares, _) = magic_squares();
+        assert_eq!(squares.len(), 8);
+        assert!(squares.iter().all(|square| is_magic(square, 15)));
+        assert!(squares.contains(&[[2, 7, 6], [9, 5, 1], [4, 3, 8]]));
+        let mut distinct = squares.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 8);
+    }
+}
@@ -1,4 +1,30 @@
+use std::env;
+use std::process;
+
+mod countdown;
+mod grid;
+mod sequence;
+mod song;
+mod walk;
+
 fn main() {
+    // running with arguments (e.g. `cargo run -- countdown 90s`) runs one of
+    // the utilities instead of the loop examples below.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(command) = args.first() {
+        let result = match command.as_str() {
+            "countdown" => countdown::cli(&args[1..]),
+            "song" => song::cli(&args[1..]),
+            "seq" => sequence::cli(&args[1..]),
+            other => Err(format!("unknown command '{other}'")),
+        };
+        if let Err(message) = result {
+            eprintln!("{message}");
+            process::exit(1);
+        }
+        return;
+    }
+
     let number = 7;
 
     if number < 5 {
@@ -87,6 +113,45 @@ fn main() {
     }
     println!("End new_count = {new_count}");
 
+    // grid.rs turns this nesting into a helper that can go any number of
+    // levels deep - Flow::Next(level) and Flow::Break(value) play the part of
+    // `continue 'label` and `break 'label value`. Here's the loop above again:
+
+    let counting = grid::Grid::new(vec![
+        grid::Axis::new(0, 2), // 'counting_up: new_count
+        grid::Axis::new(10, 9).step_by(-1).expect("-1 isn't 0"), // remaining, counting down
+    ]);
+    let search = counting.search(|point| {
+        if point[0] == 2 {
+            return grid::Flow::Break(point[0]); // break 'counting_up
+        }
+        grid::Flow::Continue
+    });
+    println!(
+        "grid stopped at new_count = {:?} after {} points",
+        search.result, search.visited
+    );
+
+    let (triples, search) = grid::pythagorean_triples(20);
+    println!(
+        "triples up to 20: {triples:?} ({} points visited)",
+        search.visited
+    );
+
+    let search = grid::triple_with_perimeter(1000);
+    println!(
+        "triple with perimeter 1000: {:?} ({} points visited)",
+        search.result, search.visited
+    );
+
+    let (squares, search) = grid::magic_squares();
+    println!(
+        "{} magic squares, first is {:?} (visited per level: {:?})",
+        squares.len(),
+        squares[0],
+        search.per_level
+    );
+
     // CONDITIONAL LOOPS - WHILE:
 
     // rust while loops work very similarly to while
@@ -120,6 +185,31 @@ fn main() {
     // It also requires an extra step of a conditional check for
     // each iteration through the loop.
 
+    // walk.rs has safer versions of the usual index tricks - none of them
+    // can panic, whatever stride or size they're given:
+
+    let every_other: Vec<_> = walk::strided(&a, 0, 2).collect();
+    let backwards: Vec<_> = walk::reversed(&a, 1).collect();
+    let pairs: Vec<_> = walk::windowed(&a, 2).collect();
+    let halves: Vec<_> = walk::chunked(&a, 3).collect();
+    println!("{every_other:?} {backwards:?} {pairs:?} {halves:?}");
+
+    let labels = ["ten", "twenty", "thirty"];
+    for (value, label) in walk::zipped(&a, &labels) {
+        println!("{label} is {value}");
+    }
+    for (value, label) in walk::zipped_longest(&a, &labels) {
+        println!("{value:?} {label:?}");
+    }
+
+    println!(
+        "{:?} {:?} {:?}",
+        walk::at(&a, -1),
+        walk::at(&a, 10),
+        walk::at_many(&a, [0, -1])
+    );
+    // a[10] would panic here - at(&a, 10) just says there's nothing there.
+
     // FOR LOOPS
 
     // Rust has a for loop very similar to JavaScript
@@ -147,12 +237,27 @@ fn main() {
     for num in 1..=12 {
         gen_fib(num);
     }
+
+    // sequence.rs has the same idea as an iterator (plus primes, collatz,
+    // factorials...), which stops with an error instead of overflowing:
+    // cargo run -- seq fibonacci --count 100
+    let fibs: Vec<u64> = sequence::fibonacci()
+        .take(12)
+        .map_while(Result::ok)
+        .collect();
+    println!("{fibs:?}");
+
+    // the third exercise - the twelve days of christmas - lives in song.rs,
+    // with the lyrics in songs/twelve-days.song:
+    // cargo run -- song songs/twelve-days.song
 }
 
 fn fahr_to_cel(num: i32) -> i32 {
     (num - 32) * 5 / 9
 }
 
+// kept as written in the exercise, explicit returns and all.
+#[allow(clippy::needless_return)]
 fn gen_fib(num: i32) -> i32 {
     if num <= 1 {
         return 0;
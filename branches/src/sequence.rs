@@ -0,0 +1,599 @@
+use std::fmt;
+
+// NUMBER SEQUENCES
+
+// gen_fib computes one Fibonacci number with a for loop. The sequences here
+// are iterators instead - each call to `next` runs one pass of the loop - so
+// callers can take as many terms as they like with `.take(n)`.
+
+// Every term is checked: instead of panicking (debug builds) or silently
+// wrapping around (release builds) when a term doesn't fit in a u64, the
+// iterator yields Err(Overflow) once and then stops.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overflow {
+    pub sequence: &'static str,
+    pub index: u64, // index of the first term that didn't fit.
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} term {} overflows a u64 (max {})",
+            self.sequence,
+            self.index,
+            u64::MAX
+        )
+    }
+}
+
+pub type Term = Result<u64, Overflow>;
+
+// FIBONACCI
+
+pub struct Fibonacci {
+    current: Option<u64>,
+    next: Option<u64>,
+    index: u64,
+    done: bool,
+}
+
+pub fn fibonacci() -> Fibonacci {
+    Fibonacci {
+        current: Some(0),
+        next: Some(1),
+        index: 0,
+        done: false,
+    }
+}
+
+impl Iterator for Fibonacci {
+    type Item = Term;
+
+    fn next(&mut self) -> Option<Term> {
+        if self.done {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+
+        let Some(current) = self.current else {
+            self.done = true;
+            return Some(Err(Overflow {
+                sequence: "fibonacci",
+                index,
+            }));
+        };
+        // the term after `next` may not fit - that's only an error once
+        // someone asks for it, so carry it along as an Option.
+        let after = match (self.current, self.next) {
+            (Some(a), Some(b)) => a.checked_add(b),
+            _ => None,
+        };
+        self.current = self.next;
+        self.next = after;
+        Some(Ok(current))
+    }
+}
+
+// PRIMES - SEGMENTED SIEVE
+
+// The sieve of Eratosthenes crosses out every multiple of each prime, and
+// whatever's left is prime. A plain sieve needs one bool per number up to
+// the limit, and we don't know the limit ahead of time. A segmented sieve
+// works through one fixed-size window at a time instead: to sieve the
+// window [low, high) it only needs the "base" primes up to sqrt(high).
+
+const SEGMENT: u64 = 1 << 15;
+
+pub struct Primes {
+    base: Vec<u64>, // primes up to base_limit, used to sieve each window.
+    base_limit: u64,
+    found: Vec<u64>, // primes in the current window, in order.
+    position: usize,
+    low: u64, // start of the next window to sieve.
+    index: u64,
+    done: bool,
+}
+
+pub fn primes() -> Primes {
+    primes_from(2)
+}
+
+// Every prime >= `start`. The first window starts at `start` itself, so
+// nothing below it is sieved - only the base primes up to its square root
+// are needed. Overflow's index counts from the first prime handed out here.
+//
+// Those base primes still come from a plain sieve with one bool per number,
+// so a start near u64::MAX would need one up to 2^32 - gigabytes. The seq
+// command doesn't let --from go past MAX_PRIMES_FROM, which keeps the base
+// sieve to a few tens of megabytes.
+pub const MAX_PRIMES_FROM: u64 = 1 << 48;
+
+pub fn primes_from(start: u64) -> Primes {
+    Primes {
+        base: Vec::new(),
+        base_limit: 0,
+        found: Vec::new(),
+        position: 0,
+        low: start.max(2),
+        index: 0,
+        done: false,
+    }
+}
+
+impl Primes {
+    fn sieve_next_window(&mut self) -> bool {
+        if self.low == u64::MAX {
+            return false;
+        }
+        let high = self.low.saturating_add(SEGMENT);
+
+        // make sure we have every base prime up to sqrt(high).
+        let needed = high.isqrt() + 1;
+        if needed > self.base_limit {
+            // grow in big steps so we don't redo the small sieve every window.
+            self.base_limit = needed.max(self.base_limit * 2);
+            self.base = simple_sieve(self.base_limit);
+        }
+
+        let mut composite = vec![false; (high - self.low) as usize];
+        for &p in &self.base {
+            let square = match p.checked_mul(p) {
+                Some(square) if square < high => square,
+                _ => break,
+            };
+            // first multiple of p inside the window (but never p itself).
+            let Some(first) = self.low.div_ceil(p).checked_mul(p) else {
+                continue;
+            };
+            let mut multiple = square.max(first);
+            while multiple < high {
+                composite[(multiple - self.low) as usize] = true;
+                match multiple.checked_add(p) {
+                    Some(next) => multiple = next,
+                    None => break,
+                }
+            }
+        }
+
+        self.found.clear();
+        self.position = 0;
+        for (offset, &crossed_out) in composite.iter().enumerate() {
+            if !crossed_out {
+                self.found.push(self.low + offset as u64);
+            }
+        }
+        self.low = high;
+        true
+    }
+}
+
+// A plain sieve of Eratosthenes: every prime below `limit`.
+fn simple_sieve(limit: u64) -> Vec<u64> {
+    let limit = limit as usize;
+    let mut composite = vec![false; limit.max(2)];
+    let mut primes = Vec::new();
+    for n in 2..limit {
+        if composite[n] {
+            continue;
+        }
+        primes.push(n as u64);
+        let mut multiple = n * n;
+        while multiple < limit {
+            composite[multiple] = true;
+            multiple += n;
+        }
+    }
+    primes
+}
+
+impl Iterator for Primes {
+    type Item = Term;
+
+    fn next(&mut self) -> Option<Term> {
+        if self.done {
+            return None;
+        }
+        while self.position == self.found.len() {
+            if !self.sieve_next_window() {
+                // every prime below u64::MAX has been handed out.
+                self.done = true;
+                return Some(Err(Overflow {
+                    sequence: "primes",
+                    index: self.index,
+                }));
+            }
+        }
+        let prime = self.found[self.position];
+        self.position += 1;
+        self.index += 1;
+        Some(Ok(prime))
+    }
+}
+
+// STEPPED SEQUENCES
+
+// Most of the other sequences only need the previous term (and its index)
+// to work out the next one, so they share one iterator. `step` returns the
+// term after `current`, and the iterator keeps track of the rest.
+
+pub enum Next {
+    Term(u64),
+    Overflow, // the next term doesn't fit in a u64.
+    End,      // the sequence is finished (e.g. Collatz reaching 1).
+}
+
+enum State {
+    Term(u64),
+    Overflowed,
+    Done,
+}
+
+pub struct Stepped {
+    name: &'static str,
+    index: u64,
+    state: State,
+    step: fn(u64, u64) -> Next, // (index of current, current) -> next
+}
+
+impl Iterator for Stepped {
+    type Item = Term;
+
+    fn next(&mut self) -> Option<Term> {
+        match self.state {
+            State::Term(current) => {
+                self.state = match (self.step)(self.index, current) {
+                    Next::Term(next) => State::Term(next),
+                    Next::Overflow => State::Overflowed,
+                    Next::End => State::Done,
+                };
+                self.index += 1;
+                Some(Ok(current))
+            }
+            State::Overflowed => {
+                self.state = State::Done;
+                Some(Err(Overflow {
+                    sequence: self.name,
+                    index: self.index,
+                }))
+            }
+            State::Done => None,
+        }
+    }
+}
+
+fn checked(next: Option<u64>) -> Next {
+    match next {
+        Some(term) => Next::Term(term),
+        None => Next::Overflow,
+    }
+}
+
+// COLLATZ
+
+// Start anywhere: halve even numbers, turn odd n into 3n + 1, and stop at 1.
+// Nobody has proven every start reaches 1, but every one ever tried has.
+pub fn collatz(start: u64) -> Stepped {
+    Stepped {
+        name: "collatz",
+        index: 0,
+        // 0 would halve to 0 forever, so it has no trajectory.
+        state: if start == 0 {
+            State::Done
+        } else {
+            State::Term(start)
+        },
+        step: |_, n| {
+            if n == 1 {
+                Next::End
+            } else if n % 2 == 0 {
+                Next::Term(n / 2)
+            } else {
+                checked(n.checked_mul(3).and_then(|t| t.checked_add(1)))
+            }
+        },
+    }
+}
+
+// FIGURATE NUMBERS
+
+// Triangular, square and pentagonal numbers count the dots in ever bigger
+// triangles, squares and pentagons. Each has a closed formula for the nth
+// term, so the step ignores the previous term and just uses the index.
+// All three start at n = 1: 1, 3, 6, ... / 1, 4, 9, ... / 1, 5, 12, ...
+// As with catalan below, the formulas multiply before they halve, so that
+// happens in u128 - otherwise n(n + 1) would overflow long before the
+// triangular number itself does.
+
+fn figurate(name: &'static str, nth: fn(u64, u64) -> Next) -> Stepped {
+    Stepped {
+        name,
+        index: 0,
+        state: State::Term(1),
+        step: nth,
+    }
+}
+
+pub fn triangular() -> Stepped {
+    // n(n + 1) / 2, with index i being term n = i + 1, so the next is n + 1.
+    figurate("triangular", |i, _| {
+        let n = i as u128 + 2;
+        checked(u64::try_from(n * (n + 1) / 2).ok())
+    })
+}
+
+pub fn square() -> Stepped {
+    figurate("square", |i, _| {
+        let n = i + 2;
+        checked(n.checked_mul(n))
+    })
+}
+
+pub fn pentagonal() -> Stepped {
+    // n(3n - 1) / 2
+    figurate("pentagonal", |i, _| {
+        let n = i as u128 + 2;
+        checked(u64::try_from(n * (3 * n - 1) / 2).ok())
+    })
+}
+
+// FACTORIALS
+
+// 0! = 1, and n! = n * (n - 1)!. These grow fast - 21! is already too big.
+pub fn factorials() -> Stepped {
+    Stepped {
+        name: "factorial",
+        index: 0,
+        state: State::Term(1),
+        step: |i, current| checked(current.checked_mul(i + 1)),
+    }
+}
+
+// CATALAN NUMBERS
+
+// C(0) = 1, and C(n + 1) = C(n) * 2(2n + 1) / (n + 2). They count things
+// like balanced bracket strings and binary tree shapes. The multiplication
+// happens before the division, so it's done in u128 to keep the middle
+// step from overflowing when the final answer would still fit.
+pub fn catalan() -> Stepped {
+    Stepped {
+        name: "catalan",
+        index: 0,
+        state: State::Term(1),
+        step: |n, current| {
+            let wide = current as u128 * (2 * (2 * n as u128 + 1)) / (n as u128 + 2);
+            checked(u64::try_from(wide).ok())
+        },
+    }
+}
+
+// SEQ CLI
+
+pub const NAMES: [&str; 8] = [
+    "fibonacci",
+    "primes",
+    "collatz",
+    "triangular",
+    "square",
+    "pentagonal",
+    "factorial",
+    "catalan",
+];
+
+// Entry point for `cargo run -- seq <name> [--count N] [--from K]`.
+// --from skips terms smaller than K (for collatz it's the starting number,
+// and primes start sieving at K rather than skipping the ones below it).
+pub fn cli(args: &[String]) -> Result<(), String> {
+    let usage = format!(
+        "usage: seq <name> [--count N] [--from K]\nnames: {}",
+        NAMES.join(", ")
+    );
+    let mut name = None;
+    let mut count: u64 = 10;
+    let mut from: Option<u64> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => count = number_arg(args.next(), "--count")?,
+            "--from" => from = Some(number_arg(args.next(), "--from")?),
+            other if name.is_none() => name = Some(other.to_string()),
+            other => return Err(format!("unexpected argument '{other}'")),
+        }
+    }
+
+    let name = name.ok_or(usage.clone())?;
+    let terms: Box<dyn Iterator<Item = Term>> = match name.as_str() {
+        "fibonacci" | "fib" => Box::new(fibonacci()),
+        "primes" => match from.take() {
+            Some(start) if start > MAX_PRIMES_FROM => {
+                return Err(format!(
+                    "primes --from can be at most {MAX_PRIMES_FROM} (2^48)"
+                ))
+            }
+            start => Box::new(start.map_or_else(primes, primes_from)),
+        },
+        "collatz" => Box::new(collatz(from.take().unwrap_or(27))),
+        "triangular" => Box::new(triangular()),
+        "square" => Box::new(square()),
+        "pentagonal" => Box::new(pentagonal()),
+        "factorial" | "factorials" => Box::new(factorials()),
+        "catalan" => Box::new(catalan()),
+        "help" => {
+            println!("{usage}");
+            return Ok(());
+        }
+        other => return Err(format!("unknown sequence '{other}'\n{usage}")),
+    };
+
+    let from = from.unwrap_or(0);
+    let terms = terms
+        .filter(|term| term.as_ref().map_or(true, |&value| value >= from))
+        .take(count as usize);
+
+    // print as we go, so long runs stream instead of showing up all at once.
+    for term in terms {
+        match term {
+            Ok(value) => println!("{value}"),
+            Err(overflow) => return Err(overflow.to_string()),
+        }
+    }
+    Ok(())
+}
+
+fn number_arg(value: Option<&String>, flag: &str) -> Result<u64, String> {
+    let value = value.ok_or(format!("{flag} needs a number"))?;
+    value
+        .parse()
+        .map_err(|_| format!("{flag} expects a whole number, got '{value}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(terms: impl Iterator<Item = Term>) -> Vec<u64> {
+        terms.map(|term| term.unwrap()).collect()
+    }
+
+    // The term after `current` and then the overflow - so the tests don't
+    // have to count up through billions of terms to get there.
+    fn last_two(mut sequence: Stepped, index: u64, current: u64) -> Vec<Term> {
+        sequence.index = index;
+        sequence.state = State::Term(current);
+        sequence.collect()
+    }
+
+    fn overflow(sequence: &'static str, index: u64) -> Term {
+        Err(Overflow { sequence, index })
+    }
+
+    #[test]
+    fn fibonacci_overflows_at_term_94() {
+        let terms: Vec<Term> = fibonacci().collect();
+        assert_eq!(terms.len(), 95);
+        assert_eq!(
+            values(terms[..10].iter().cloned()),
+            [0, 1, 1, 2, 3, 5, 8, 13, 21, 34]
+        );
+        assert_eq!(terms[93], Ok(12_200_160_415_121_876_738));
+        assert_eq!(terms[94], overflow("fibonacci", 94));
+    }
+
+    #[test]
+    fn factorial_overflows_at_21() {
+        let terms: Vec<Term> = factorials().collect();
+        assert_eq!(terms.len(), 22);
+        assert_eq!(values(terms[..6].iter().cloned()), [1, 1, 2, 6, 24, 120]);
+        assert_eq!(terms[20], Ok(2_432_902_008_176_640_000));
+        assert_eq!(terms[21], overflow("factorial", 21));
+    }
+
+    #[test]
+    fn catalan_overflows_at_37() {
+        let terms: Vec<Term> = catalan().collect();
+        assert_eq!(terms.len(), 38);
+        assert_eq!(
+            values(terms[..8].iter().cloned()),
+            [1, 1, 2, 5, 14, 42, 132, 429]
+        );
+        // C(36) is past u64::MAX / 4, so the next step's multiply only
+        // fits because it's done in u128.
+        assert_eq!(terms[36], Ok(11_959_798_385_860_453_492));
+        assert_eq!(terms[37], overflow("catalan", 37));
+    }
+
+    #[test]
+    fn collatz_stops_at_one() {
+        assert_eq!(values(collatz(6)), [6, 3, 10, 5, 16, 8, 4, 2, 1]);
+        assert_eq!(values(collatz(1)), [1]);
+        assert_eq!(collatz(0).count(), 0);
+        let terms = values(collatz(27));
+        assert_eq!(terms.len(), 112);
+        assert_eq!(terms.iter().max(), Some(&9232));
+    }
+
+    #[test]
+    fn collatz_overflows_on_a_big_odd_start() {
+        let terms: Vec<Term> = collatz(u64::MAX).collect();
+        assert_eq!(terms, [Ok(u64::MAX), overflow("collatz", 1)]);
+    }
+
+    #[test]
+    fn figurate_numbers() {
+        assert_eq!(values(triangular().take(6)), [1, 3, 6, 10, 15, 21]);
+        assert_eq!(values(square().take(6)), [1, 4, 9, 16, 25, 36]);
+        assert_eq!(values(pentagonal().take(6)), [1, 5, 12, 22, 35, 51]);
+    }
+
+    #[test]
+    fn figurate_numbers_overflow_at_the_right_term() {
+        // the last term that fits is at index n - 1.
+        let n: u64 = 6_074_000_999;
+        assert_eq!(
+            last_two(triangular(), n - 1, n.div_ceil(2) * n),
+            [Ok(18_446_744_070_963_499_500), overflow("triangular", n)]
+        );
+        let n: u64 = (1 << 32) - 1;
+        assert_eq!(
+            last_two(square(), n - 1, n * n),
+            [Ok(n * n), overflow("square", n)]
+        );
+        let n: u64 = 3_506_826_112;
+        assert_eq!(
+            last_two(pentagonal(), n - 1, n / 2 * (3 * n - 1)),
+            [Ok(18_446_744_067_954_141_760), overflow("pentagonal", n)]
+        );
+    }
+
+    #[test]
+    fn overflow_messages() {
+        assert_eq!(
+            overflow("factorial", 21).unwrap_err().to_string(),
+            "factorial term 21 overflows a u64 (max 18446744073709551615)"
+        );
+    }
+
+    #[test]
+    fn cli_caps_primes_from() {
+        let args =
+            |from: u64| ["primes", "--count", "1", "--from", &from.to_string()].map(String::from);
+        assert!(cli(&args(MAX_PRIMES_FROM + 1))
+            .unwrap_err()
+            .contains("at most"));
+        assert_eq!(cli(&args(MAX_PRIMES_FROM)), Ok(()));
+    }
+
+    #[test]
+    fn primes_start_at_two() {
+        assert_eq!(
+            values(primes().take(10)),
+            [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]
+        );
+        assert_eq!(values(primes_from(0).take(3)), [2, 3, 5]);
+        assert_eq!(values(primes_from(1).take(3)), [2, 3, 5]);
+    }
+
+    #[test]
+    fn primes_from_matches_filtering_primes() {
+        for start in [2, 3, 4, 10, 97, 100, SEGMENT - 1, SEGMENT, SEGMENT + 1] {
+            let skipped: Vec<u64> = values(primes().take(20_000))
+                .into_iter()
+                .filter(|&p| p >= start)
+                .take(25)
+                .collect();
+            assert_eq!(values(primes_from(start).take(25)), skipped, "from {start}");
+        }
+    }
+
+    #[test]
+    fn primes_from_a_large_start() {
+        // sieving everything below 10^13 would take far too long for a test.
+        assert_eq!(
+            values(primes_from(10_000_000_000_000).take(3)),
+            [10_000_000_000_037, 10_000_000_000_051, 10_000_000_000_099]
+        );
+    }
+}
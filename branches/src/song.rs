@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+// CUMULATIVE SONGS
+
+// The third loop exercise from the book: print the lyrics to "The Twelve
+// Days of Christmas", taking advantage of the repetition in the song.
+// Songs like this are "cumulative" - each verse adds one new item and then
+// repeats every earlier item, newest first. That's a loop inside a loop:
+// the outer one walks the verses, the inner one walks back down the items.
+
+// Rather than hard-coding one song, the words live in a small data file
+// (see the `songs/` folder). A file has a few templates at the top and then
+// one [item] section per verse:
+//
+//     title = The Twelve Days of Christmas
+//     intro = On the {ordinal} day of Christmas my true love sent to me
+//     chain = {line},
+//
+//     [item]
+//     line = A partridge in a pear tree
+//
+// Templates:
+//   intro     - first line of every verse, filled in with the newest item.
+//   chain     - one line per item, newest to oldest, except the oldest.
+//               {prev.key} reads from the next-older item.
+//   chain-end - line for the oldest item (left out if missing).
+//   only      - line for the oldest item in verse one, when it's the only
+//               item (falls back to chain-end).
+//   refrain   - last line of every verse (left out if missing).
+//
+// Item keys are free-form - any `key = value` can be used as {key} in a
+// template - except two with special meaning:
+//   remark    - a line printed right after the intro of that item's verse.
+//   stop      - `true` ends the verse (and the song) after the remark.
+//
+// {n} and {ordinal} are the verse number ("3" and "third").
+
+#[derive(Debug)]
+pub struct Song {
+    pub title: String,
+    templates: HashMap<String, String>,
+    items: Vec<HashMap<String, String>>,
+}
+
+#[derive(Debug)]
+pub struct ParseSongError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseSongError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+const TEMPLATE_KEYS: [&str; 6] = ["title", "intro", "chain", "chain-end", "only", "refrain"];
+
+impl FromStr for Song {
+    type Err = ParseSongError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut templates = HashMap::new();
+        let mut items: Vec<HashMap<String, String>> = Vec::new();
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            let error = |message: String| ParseSongError {
+                line: index + 1,
+                message,
+            };
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "[item]" {
+                items.push(HashMap::new());
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(error(format!("unknown section {line}")));
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(error(format!("expected `key = value`, got `{line}`")));
+            };
+            let (key, value) = (key.trim().to_string(), value.trim().to_string());
+
+            match items.last_mut() {
+                Some(item) => {
+                    item.insert(key, value);
+                }
+                None if TEMPLATE_KEYS.contains(&key.as_str()) => {
+                    templates.insert(key, value);
+                }
+                None => return Err(error(format!("unknown setting `{key}`"))),
+            }
+        }
+
+        let Some(title) = templates.remove("title") else {
+            return Err(ParseSongError {
+                line: 1,
+                message: String::from("song has no title"),
+            });
+        };
+        if items.is_empty() {
+            return Err(ParseSongError {
+                line: text.lines().count(),
+                message: String::from("song has no [item] sections"),
+            });
+        }
+
+        Ok(Song {
+            title,
+            templates,
+            items,
+        })
+    }
+}
+
+impl Song {
+    pub fn load(path: &str) -> Result<Song, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        text.parse().map_err(|e| format!("{path}: {e}"))
+    }
+
+    // Builds every verse as a list of lines.
+    pub fn verses(&self) -> Vec<Vec<String>> {
+        let mut verses = Vec::new();
+
+        for n in 1..=self.items.len() {
+            let newest = &self.items[n - 1];
+            let mut lines = Vec::new();
+
+            self.push(&mut lines, "intro", n, newest, None);
+            if let Some(remark) = newest.get("remark") {
+                lines.push(remark.clone());
+            }
+            if newest.get("stop").is_some_and(|s| s == "true") {
+                verses.push(lines);
+                break;
+            }
+
+            // walk back down through the items, newest first.
+            for i in (0..n).rev() {
+                let item = &self.items[i];
+                if i > 0 {
+                    self.push(&mut lines, "chain", n, item, Some(&self.items[i - 1]));
+                } else if n == 1 && self.templates.contains_key("only") {
+                    self.push(&mut lines, "only", n, item, None);
+                } else {
+                    self.push(&mut lines, "chain-end", n, item, None);
+                }
+            }
+
+            self.push(&mut lines, "refrain", n, newest, None);
+            verses.push(lines);
+        }
+
+        verses
+    }
+
+    // Fills in a template and adds it to the verse - missing or empty
+    // templates add nothing.
+    fn push(
+        &self,
+        lines: &mut Vec<String>,
+        template: &str,
+        n: usize,
+        item: &HashMap<String, String>,
+        prev: Option<&HashMap<String, String>>,
+    ) {
+        let Some(template) = self.templates.get(template) else {
+            return;
+        };
+        let line = fill(template, n, item, prev);
+        if !line.trim().is_empty() {
+            lines.push(line);
+        }
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        let verses = self.verses();
+        let mut out = String::new();
+
+        match format {
+            Format::Text => {
+                out.push_str(&self.title);
+                out.push('\n');
+                for verse in &verses {
+                    out.push('\n');
+                    for line in verse {
+                        out.push_str(line);
+                        out.push('\n');
+                    }
+                }
+            }
+            Format::Markdown => {
+                out.push_str(&format!("# {}\n", escape_markdown(&self.title)));
+                for verse in &verses {
+                    out.push('\n');
+                    let lines: Vec<String> = verse.iter().map(|l| escape_markdown(l)).collect();
+                    // two trailing spaces are a line break in Markdown.
+                    out.push_str(&lines.join("  \n"));
+                    out.push('\n');
+                }
+            }
+            Format::Html => {
+                out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+                out.push_str(&format!("<title>{}</title>\n", escape_html(&self.title)));
+                out.push_str("</head>\n<body>\n");
+                out.push_str(&format!("<h1>{}</h1>\n", escape_html(&self.title)));
+                for verse in &verses {
+                    let lines: Vec<String> = verse.iter().map(|l| escape_html(l)).collect();
+                    out.push_str(&format!("<p>\n{}\n</p>\n", lines.join("<br>\n")));
+                }
+                out.push_str("</body>\n</html>\n");
+            }
+        }
+
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Markdown,
+    Html,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" | "txt" => Ok(Format::Text),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            other => Err(format!(
+                "unknown format '{other}' (expected text, markdown or html)"
+            )),
+        }
+    }
+}
+
+// Replaces each {placeholder} in a template. Unknown placeholders are left
+// as they are so typos show up in the output instead of vanishing.
+fn fill(
+    template: &str,
+    n: usize,
+    item: &HashMap<String, String>,
+    prev: Option<&HashMap<String, String>>,
+) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        // a { that's never closed is just text, like the rest of the line.
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let key = &rest[start + 1..start + len];
+
+        let value = match key {
+            "n" => Some(n.to_string()),
+            "ordinal" => Some(ordinal(n)),
+            // `again` is how an item is sung on repeats - it falls back to
+            // the plain line.
+            "again" => item.get("again").or_else(|| item.get("line")).cloned(),
+            _ => match key.strip_prefix("prev.") {
+                Some(prev_key) => prev.and_then(|p| p.get(prev_key)).cloned(),
+                None => item.get(key).cloned(),
+            },
+        };
+
+        match value {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+pub fn ordinal(n: usize) -> String {
+    let words = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth", "eleventh", "twelfth",
+    ];
+    if (1..=words.len()).contains(&n) {
+        return words[n - 1].to_string();
+    }
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// A backslash in front of punctuation makes Markdown treat it as plain
+// text. *, _, [ and friends mean something anywhere in a line; #, +, -, >
+// and "1." only at the start, where they'd make a heading or a list.
+fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let indent = s.len() - s.trim_start().len();
+    let digits = s[indent..].chars().take_while(char::is_ascii_digit).count();
+    for (i, c) in s.char_indices() {
+        let starts_block = (i == indent && "#+->".contains(c))
+            || (digits > 0 && i == indent + digits && ".)".contains(c));
+        if starts_block || "\\`*_[]<>|~".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Entry point for `cargo run -- song <file> [--format text|markdown|html]`.
+pub fn cli(args: &[String]) -> Result<(), String> {
+    let mut path = None;
+    let mut format = Format::Text;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let value = args.next().ok_or("--format needs a value")?;
+                format = value.parse()?;
+            }
+            other if path.is_none() => path = Some(other.to_string()),
+            other => return Err(format!("unexpected argument '{other}'")),
+        }
+    }
+
+    let path = path.ok_or("usage: song <file> [--format text|markdown|html]")?;
+    let song = Song::load(&path)?;
+    print!("{}", song.render(format));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SONG: &str =
+        "title = *Stars* & _bars_\nintro = # {line}\n[item]\nline = 1. a <b> [c](d)\n";
+
+    #[test]
+    fn markdown_escapes_lyrics() {
+        let song: Song = SONG.parse().unwrap();
+        assert_eq!(
+            song.render(Format::Markdown),
+            "# \\*Stars\\* & \\_bars\\_\n\n\\# 1. a \\<b\\> \\[c\\](d)\n"
+        );
+    }
+
+    #[test]
+    fn markdown_escapes_list_markers_only_at_the_start() {
+        assert_eq!(
+            escape_markdown("12. drummers - drumming"),
+            "12\\. drummers - drumming"
+        );
+        assert_eq!(escape_markdown("  - a list? + no"), "  \\- a list? + no");
+        assert_eq!(
+            escape_markdown("A partridge in a pear tree."),
+            "A partridge in a pear tree."
+        );
+    }
+
+    #[test]
+    fn html_escapes_lyrics() {
+        let song: Song = SONG.parse().unwrap();
+        let html = song.render(Format::Html);
+        assert!(html.contains("<h1>*Stars* &amp; _bars_</h1>"));
+        assert!(html.contains("# 1. a &lt;b&gt; [c](d)"));
+    }
+
+    fn item(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn unclosed_braces_are_kept_once() {
+        let line = item(&[("line", "x")]);
+        assert_eq!(fill("a {b", 1, &line, None), "a {b");
+        assert_eq!(fill("{line} {b", 1, &line, None), "x {b");
+        assert_eq!(fill("{", 1, &line, None), "{");
+        assert_eq!(fill("a } {line}", 1, &line, None), "a } x");
+    }
+
+    #[test]
+    fn unknown_placeholders_are_left_in() {
+        let line = item(&[("line", "x")]);
+        assert_eq!(
+            fill("{n} {ordinal} {typo}", 3, &line, None),
+            "3 third {typo}"
+        );
+        assert_eq!(fill("{prev.line}", 1, &line, None), "{prev.line}");
+    }
+
+    const COUNTING: &str = "title = Counting
+intro = Verse {n}:
+chain = {again}, then {prev.line}
+chain-end = {line} at last
+only = just {line}
+refrain = (end of {ordinal})
+
+[item]
+line = one
+
+[item]
+line = two
+again = TWO
+
+[item]
+line = three
+";
+
+    #[test]
+    fn verses_repeat_every_item_newest_first() {
+        let song: Song = COUNTING.parse().unwrap();
+        assert_eq!(
+            song.verses(),
+            [
+                vec!["Verse 1:", "just one", "(end of first)"],
+                vec![
+                    "Verse 2:",
+                    "TWO, then one",
+                    "one at last",
+                    "(end of second)"
+                ],
+                vec![
+                    "Verse 3:",
+                    "three, then two",
+                    "TWO, then one",
+                    "one at last",
+                    "(end of third)"
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn stop_ends_the_song_after_the_remark() {
+        let text = format!("{COUNTING}remark = wait\nstop = true\n\n[item]\nline = four\n");
+        let song: Song = text.parse().unwrap();
+        let verses = song.verses();
+        assert_eq!(verses.len(), 3);
+        assert_eq!(verses[2], ["Verse 3:", "wait"]);
+        // anything but `true` doesn't stop it.
+        let text = format!("{COUNTING}stop = yes\n");
+        assert_eq!(text.parse::<Song>().unwrap().verses()[2].len(), 5);
+    }
+
+    #[test]
+    fn text_is_left_alone() {
+        let song: Song = SONG.parse().unwrap();
+        assert_eq!(
+            song.render(Format::Text),
+            "*Stars* & _bars_\n\n# 1. a <b> [c](d)\n"
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::iter;
+use std::slice;
+
+// WALKING A COLLECTION SAFELY
+
+// The `while index < a.len() { a[index] }` loop works, but every a[index]
+// is a chance to panic if the condition or the step is wrong. The helpers
+// here cover the usual reasons for reaching for an index - skipping ahead,
+// looking at neighbours, going backwards - without ever indexing directly.
+// None of them panic, whatever length, stride or size they're given: a
+// stride or size of 0 just gives back nothing, and any step that would run
+// past the end stops the walk instead.
+
+// STRIDED
+
+// Every `stride`th element starting at `start` - the while loop with
+// `index += stride` instead of `index += 1`.
+pub struct Strided<'a, T> {
+    slice: &'a [T],
+    next: Option<usize>,
+    stride: usize,
+}
+
+pub fn strided<T>(slice: &[T], start: usize, stride: usize) -> Strided<'_, T> {
+    Strided {
+        slice,
+        next: if stride == 0 { None } else { Some(start) },
+        stride,
+    }
+}
+
+impl<'a, T> Iterator for Strided<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let index = self.next?;
+        let item = self.slice.get(index);
+        // checked_add so that a huge stride ends the walk instead of wrapping
+        // back around to the start.
+        self.next = if item.is_some() {
+            index.checked_add(self.stride)
+        } else {
+            None
+        };
+        item
+    }
+}
+
+// REVERSE
+
+// Walks backwards from the last element, `stride` at a time. Counting down
+// with a usize index is where the classic `index -= 1` underflow bug lives,
+// so the index is an Option that becomes None instead of going below zero.
+pub struct Reverse<'a, T> {
+    slice: &'a [T],
+    next: Option<usize>,
+    stride: usize,
+}
+
+pub fn reversed<T>(slice: &[T], stride: usize) -> Reverse<'_, T> {
+    Reverse {
+        slice,
+        next: if stride == 0 {
+            None
+        } else {
+            slice.len().checked_sub(1)
+        },
+        stride,
+    }
+}
+
+impl<'a, T> Iterator for Reverse<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let index = self.next?;
+        self.next = index.checked_sub(self.stride);
+        self.slice.get(index)
+    }
+}
+
+// WINDOWS AND CHUNKS
+
+// The standard library already has these (`a.windows(2)`, `a.chunks(2)`),
+// but both panic when given a size of 0. These wrappers treat 0 as "no
+// windows" instead.
+
+// Every run of `size` neighbours: [1, 2, 3] with size 2 -> [1, 2], [2, 3].
+pub fn windowed<T>(slice: &[T], size: usize) -> slice::Windows<'_, T> {
+    match size {
+        0 => slice[..0].windows(1),
+        size => slice.windows(size),
+    }
+}
+
+// Non-overlapping pieces of `size` - the last one may be shorter:
+// [1, 2, 3] with size 2 -> [1, 2], [3].
+pub fn chunked<T>(slice: &[T], size: usize) -> slice::Chunks<'_, T> {
+    match size {
+        0 => slice[..0].chunks(1),
+        size => slice.chunks(size),
+    }
+}
+
+// ZIPPED
+
+// Pairs up two slices element by element, stopping at the shorter one.
+pub fn zipped<'a, A, B>(
+    a: &'a [A],
+    b: &'a [B],
+) -> iter::Zip<slice::Iter<'a, A>, slice::Iter<'a, B>> {
+    a.iter().zip(b.iter())
+}
+
+// Like zipped, but keeps going to the end of the longer slice - the side
+// that ran out shows up as None.
+pub fn zipped_longest<'a, A, B>(
+    a: &'a [A],
+    b: &'a [B],
+) -> impl Iterator<Item = (Option<&'a A>, Option<&'a B>)> {
+    (0..a.len().max(b.len())).map(move |i| (a.get(i), b.get(i)))
+}
+
+// RANDOM ACCESS
+
+// a[index] with the bounds check turned into an Option. Negative indexes
+// count from the end, so at(&a, -1) is the last element.
+pub fn at<T>(slice: &[T], index: isize) -> Option<&T> {
+    let index = if index < 0 {
+        slice.len().checked_sub(index.unsigned_abs())?
+    } else {
+        index as usize
+    };
+    slice.get(index)
+}
+
+// Several lookups at once - all of them or nothing, so callers don't have
+// to unwrap each one.
+pub fn at_many<T, const N: usize>(slice: &[T], indexes: [isize; N]) -> Option<[&T; N]> {
+    let found: Vec<&T> = indexes
+        .iter()
+        .map(|&index| at(slice, index))
+        .collect::<Option<_>>()?;
+    found.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Lengths, starts and strides at both ends of usize. A slice of ()
+    // takes no memory, so even one usize::MAX long is free to make.
+    const EDGES: [usize; 6] = [0, 1, 2, usize::MAX / 2, usize::MAX - 1, usize::MAX];
+
+    #[test]
+    fn strided_never_panics() {
+        let big = [(); usize::MAX];
+        for slice in [&[(); 0][..], &[(); 1][..], &big[..usize::MAX - 1], &big[..]] {
+            for start in EDGES {
+                for stride in EDGES {
+                    // how many steps fit between start and the end, worked
+                    // out in u128 so it can't overflow itself. take(3) so
+                    // the tiny strides don't walk usize::MAX elements.
+                    let walked = strided(slice, start, stride).take(3).count();
+                    let expected = if stride == 0 || start >= slice.len() {
+                        0
+                    } else {
+                        (slice.len() - 1 - start) as u128 / stride as u128 + 1
+                    };
+                    assert_eq!(
+                        walked as u128,
+                        expected.min(3),
+                        "len {} start {start} stride {stride}",
+                        slice.len()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn strided_stops_instead_of_wrapping() {
+        let big = [(); usize::MAX];
+        assert_eq!(strided(&big, usize::MAX - 1, 1).count(), 1);
+        assert_eq!(strided(&big, 1, usize::MAX).count(), 1);
+        assert_eq!(strided(&big, 0, usize::MAX / 2 + 1).count(), 2);
+        assert_eq!(strided(&[1, 2, 3], 0, 0).count(), 0);
+        assert_eq!(strided(&[1, 2, 3], 5, 1).count(), 0);
+    }
+
+    #[test]
+    fn reversed_never_panics() {
+        let big = [(); usize::MAX];
+        for slice in [&[(); 0][..], &[(); 1][..], &big[..]] {
+            for stride in EDGES {
+                let walked = reversed(slice, stride).take(3).count();
+                let expected = if stride == 0 || slice.is_empty() {
+                    0
+                } else {
+                    (slice.len() - 1) / stride + 1
+                };
+                assert_eq!(
+                    walked,
+                    expected.min(3),
+                    "len {} stride {stride}",
+                    slice.len()
+                );
+            }
+        }
+        assert_eq!(reversed(&big, usize::MAX).count(), 1);
+        assert_eq!(reversed(&big, usize::MAX - 1).count(), 2);
+        assert_eq!(reversed(&[1, 2, 3, 4], 3).collect::<Vec<_>>(), [&4, &1]);
+    }
+
+    #[test]
+    fn windows_and_chunks_never_panic() {
+        let big = [(); usize::MAX];
+        for slice in [&[(); 0][..], &[(); 1][..], &big[..]] {
+            for size in EDGES {
+                let len = slice.len();
+                let windows = if size == 0 || size > len {
+                    0
+                } else {
+                    len - size + 1
+                };
+                assert_eq!(windowed(slice, size).len(), windows);
+                let chunks = if size == 0 { 0 } else { len.div_ceil(size) };
+                assert_eq!(chunked(slice, size).len(), chunks);
+            }
+        }
+    }
+
+    #[test]
+    fn zipped_with_empty_slices() {
+        let empty: [i32; 0] = [];
+        assert_eq!(zipped(&empty, &[1]).count(), 0);
+        assert_eq!(
+            zipped_longest(&empty, &[1]).collect::<Vec<_>>(),
+            [(None, Some(&1))]
+        );
+        assert_eq!(zipped_longest(&empty, &empty).count(), 0);
+    }
+
+    #[test]
+    fn at_with_negative_and_extreme_indexes() {
+        let a = [10, 20, 30];
+        assert_eq!(at(&a, 0), Some(&10));
+        assert_eq!(at(&a, -1), Some(&30));
+        assert_eq!(at(&a, -3), Some(&10));
+        assert_eq!(at(&a, -4), None);
+        assert_eq!(at(&a, 3), None);
+        assert_eq!(at(&a, isize::MAX), None);
+        assert_eq!(at(&a, isize::MIN), None);
+        assert_eq!(at(&[0; 0], -1), None);
+        assert_eq!(at(&[0; 0], 0), None);
+
+        let big = [(); usize::MAX];
+        assert_eq!(at(&big, isize::MIN), Some(&()));
+        assert_eq!(at(&big, isize::MAX), Some(&()));
+
+        assert_eq!(at_many(&a, [0, -1, 1]), Some([&10, &30, &20]));
+        assert_eq!(at_many(&a, [0, isize::MIN]), None);
+        assert_eq!(at_many(&a, []), Some([]));
+    }
+
+    // PROPERTIES
+
+    // The same walks done the obvious way, on a slice whose elements are
+    // their own indexes, so what was walked says exactly which indexes were
+    // visited. The arithmetic is in i128, where nothing here can overflow.
+
+    fn indexes(len: usize) -> Vec<usize> {
+        (0..len).collect()
+    }
+
+    fn naive_strided(len: usize, start: usize, stride: usize) -> Vec<usize> {
+        let mut visited = Vec::new();
+        let mut index = start as i128;
+        while stride > 0 && index < len as i128 {
+            visited.push(index as usize);
+            index += stride as i128;
+        }
+        visited
+    }
+
+    fn naive_reversed(len: usize, stride: usize) -> Vec<usize> {
+        let mut visited = Vec::new();
+        let mut index = len as i128 - 1;
+        while stride > 0 && index >= 0 {
+            visited.push(index as usize);
+            index -= stride as i128;
+        }
+        visited
+    }
+
+    fn naive_at(len: usize, index: isize) -> Option<usize> {
+        let index = if index < 0 {
+            len as i128 + index as i128
+        } else {
+            index as i128
+        };
+        (0..len as i128).contains(&index).then_some(index as usize)
+    }
+
+    // Mostly small numbers, which make interesting walks over a short
+    // slice, but the far end of the range too.
+    fn number() -> impl Strategy<Value = usize> {
+        prop_oneof![
+            4 => 0..40usize,
+            1 => usize::MAX - 2..=usize::MAX,
+            1 => any::<usize>(),
+        ]
+    }
+
+    fn index() -> impl Strategy<Value = isize> {
+        prop_oneof![
+            4 => -40..40isize,
+            1 => Just(isize::MIN),
+            1 => Just(isize::MAX),
+            1 => any::<isize>(),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn strided_visits_what_a_naive_loop_does(
+            len in 0..40usize,
+            start in number(),
+            stride in number(),
+        ) {
+            let slice = indexes(len);
+            let walked: Vec<usize> = strided(&slice, start, stride).copied().collect();
+            prop_assert_eq!(walked, naive_strided(len, start, stride));
+        }
+
+        #[test]
+        fn reversed_visits_what_a_naive_loop_does(len in 0..40usize, stride in number()) {
+            let slice = indexes(len);
+            let walked: Vec<usize> = reversed(&slice, stride).copied().collect();
+            prop_assert_eq!(walked, naive_reversed(len, stride));
+        }
+
+        #[test]
+        fn windows_and_chunks_cover_the_slice(len in 0..40usize, size in number()) {
+            let slice = indexes(len);
+            let windows: Vec<Vec<usize>> = windowed(&slice, size).map(<[_]>::to_vec).collect();
+            let expected: Vec<Vec<usize>> = if size == 0 || size > len {
+                vec![]
+            } else {
+                (0..=len - size).map(|start| (start..start + size).collect()).collect()
+            };
+            prop_assert_eq!(windows, expected);
+
+            let chunks: Vec<Vec<usize>> = chunked(&slice, size).map(<[_]>::to_vec).collect();
+            let expected: Vec<Vec<usize>> = if size == 0 {
+                vec![]
+            } else {
+                (0..len)
+                    .step_by(size)
+                    .map(|start| (start..len.min(start.saturating_add(size))).collect())
+                    .collect()
+            };
+            prop_assert_eq!(chunks, expected);
+        }
+
+        #[test]
+        fn zipped_pairs_up_matching_indexes(a in 0..40usize, b in 0..40usize) {
+            let (left, right) = (indexes(a), indexes(b));
+            let pairs: Vec<(usize, usize)> = zipped(&left, &right).map(|(x, y)| (*x, *y)).collect();
+            prop_assert_eq!(pairs, (0..a.min(b)).map(|i| (i, i)).collect::<Vec<_>>());
+
+            let longest: Vec<_> = zipped_longest(&left, &right)
+                .map(|(x, y)| (x.copied(), y.copied()))
+                .collect();
+            let expected: Vec<_> = (0..a.max(b))
+                .map(|i| ((i < a).then_some(i), (i < b).then_some(i)))
+                .collect();
+            prop_assert_eq!(longest, expected);
+        }
+
+        #[test]
+        fn at_finds_what_a_naive_lookup_does(
+            len in 0..40usize,
+            first in index(),
+            second in index(),
+        ) {
+            let slice = indexes(len);
+            prop_assert_eq!(at(&slice, first).copied(), naive_at(len, first));
+            let both = at_many(&slice, [first, second]).map(|[x, y]| [*x, *y]);
+            let expected = naive_at(len, first).zip(naive_at(len, second)).map(|(x, y)| [x, y]);
+            prop_assert_eq!(both, expected);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use methods::rect::{Point, Rect};
+use methods::spatial::{distance_to, QuadTree};
+
+// How much quicker is the quadtree than checking every rect? Scatter 5,000
+// rects over a 10,000 x 10,000 world and ask both the same questions about
+// 1,000 points: which rects contain it, which overlap the 300 x 300 square
+// below and right of it, and which is nearest. Run with `cargo bench`.
+// (The tests in spatial.rs check that the two give the same answers.)
+
+fn next_random(seed: &mut u64) -> u64 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    *seed
+}
+
+fn scattered(count: usize, seed: &mut u64) -> Vec<Rect> {
+    (0..count)
+        .map(|_| {
+            let x = (next_random(seed) % 10_000) as u32;
+            let y = (next_random(seed) % 10_000) as u32;
+            let width = 1 + (next_random(seed) % 200) as u32;
+            let height = 1 + (next_random(seed) % 200) as u32;
+            Rect::new(x, y, width, height)
+        })
+        .collect()
+}
+
+fn region(point: Point) -> Rect {
+    Rect::new(point.x, point.y, 300, 300)
+}
+
+fn with_tree(tree: &QuadTree, points: &[Point]) -> (usize, f64) {
+    let mut found = 0;
+    let mut distances = 0.0;
+    for &point in points {
+        found += tree.containing(point).len() + tree.intersecting(&region(point)).len();
+        distances += tree.nearest(point).map_or(0.0, |(_, distance)| distance);
+    }
+    (found, distances)
+}
+
+fn with_scan(rects: &[Rect], points: &[Point]) -> (usize, f64) {
+    let mut found = 0;
+    let mut distances = 0.0;
+    for &point in points {
+        found += rects
+            .iter()
+            .filter(|rect| rect.contains_point(point))
+            .count();
+        found += rects
+            .iter()
+            .filter(|rect| rect.intersects(&region(point)))
+            .count();
+        distances += rects
+            .iter()
+            .map(|rect| distance_to(rect, point))
+            .reduce(f64::min)
+            .unwrap_or(0.0);
+    }
+    (found, distances)
+}
+
+fn querying(c: &mut Criterion) {
+    let mut seed = 42;
+    let rects = scattered(5_000, &mut seed);
+    let points: Vec<Point> = (0..1_000)
+        .map(|_| {
+            let x = (next_random(&mut seed) % 10_000) as u32;
+            let y = (next_random(&mut seed) % 10_000) as u32;
+            Point::new(x, y)
+        })
+        .collect();
+    let mut tree = QuadTree::new(Rect::new(0, 0, 10_000, 10_000));
+    for &rect in &rects {
+        tree.insert(rect);
+    }
+    assert_eq!(with_tree(&tree, &points), with_scan(&rects, &points));
+
+    let mut group = c.benchmark_group("1,000 queries over 5,000 rects");
+    group.sample_size(20); // the scan is slow
+    group.bench_function("quadtree", |b| {
+        b.iter(|| with_tree(black_box(&tree), black_box(&points)))
+    });
+    group.bench_function("scan", |b| {
+        b.iter(|| with_scan(black_box(&rects), black_box(&points)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, querying);
+criterion_main!(benches);
@@ -0,0 +1,348 @@
+use num_traits::{CheckedAdd, Num, ToPrimitive};
+
+use crate::rect::{Point, Rect};
+use crate::transform::{to_f64, FromF64, Rounding};
+
+// LAYOUT
+
+// Cutting a Rect into smaller ones - a basic layout engine, for things like
+// dashboard panels:
+//
+//     grid(3, 2)                  split_columns(&[1.0, 2.0])
+//     +-----+-----+-----+         +-----+-----------+
+//     |  0  |  1  |  2  |         |     |           |
+//     +-----+-----+-----+         |  1  |     2     |
+//     |  3  |  4  |  5  |         |     |           |
+//     +-----+-----+-----+         +-----+-----------+
+//
+// Weights say how big each piece is compared to the others: [1.0, 2.0]
+// makes the second column twice as wide as the first. Weights that are 0,
+// negative or NaN give an empty piece; if no weight is above 0 the pieces
+// are all the same size.
+//
+// The pieces always cover the whole rect exactly, with no gaps and no
+// overlaps, even when it doesn't divide evenly: 10 split three ways gives
+// 3, 4 and 3 (each edge goes to the nearest whole number), never 3, 3 and 3.
+//
+// For anything more complicated than rows or columns, a Cut describes how
+// to keep cutting the pieces, and bounding() goes the other way - from a
+// set of rects back to the one rect around them all.
+//
+// A rect whose right or bottom edge is past what its number type can hold
+// (see LIMITS in rect.rs) can't be cut up, so cutting it gives None.
+
+// Where each piece starts along one side, plus where the last one ends -
+// None if that's past what T can hold.
+fn edges<T>(start: T, length: T, weights: &[f64]) -> Option<Vec<T>>
+where
+    T: Num + Copy + PartialOrd + ToPrimitive + FromF64 + CheckedAdd,
+{
+    let end = start.checked_add(&length)?;
+    let weights: Vec<f64> = weights
+        .iter()
+        .map(|&w| if w > 0.0 { w } else { 0.0 })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    let weights = if total > 0.0 && total.is_finite() {
+        weights
+    } else {
+        vec![1.0; weights.len()]
+    };
+    let total: f64 = weights.iter().sum();
+
+    // An f64 only has 53 bits, so for u64s and i64s far from 0 the edges
+    // worked out in f64 can land a little past `end`, or before the edge
+    // in front of them. Each one is kept between the previous edge and
+    // `end`, so no piece can come out with a negative size.
+    let mut edges = vec![start];
+    let mut sum = 0.0;
+    for (i, weight) in weights.iter().enumerate() {
+        sum += weight;
+        let previous = edges[edges.len() - 1];
+        let edge = if i + 1 == weights.len() {
+            end // exactly, whatever the f64 maths says
+        } else {
+            T::from_f64(
+                to_f64(start) + to_f64(length) * sum / total,
+                Rounding::Nearest,
+            )
+        };
+        edges.push(if edge < previous {
+            previous
+        } else if edge > end {
+            end
+        } else {
+            edge
+        });
+    }
+    Some(edges)
+}
+
+impl<T: Num + Copy + PartialOrd + ToPrimitive + FromF64 + CheckedAdd> Rect<T> {
+    // Side by side, left to right.
+    pub fn split_columns(&self, weights: &[f64]) -> Option<Vec<Rect<T>>> {
+        let bottom = self.checked_bottom()?;
+        let edges = edges(self.left(), self.size.width, weights)?;
+        let columns = edges.windows(2).map(|edge| {
+            Rect::from_corners(Point::new(edge[0], self.top()), Point::new(edge[1], bottom))
+        });
+        Some(columns.collect())
+    }
+
+    // One above another, top to bottom.
+    pub fn split_rows(&self, weights: &[f64]) -> Option<Vec<Rect<T>>> {
+        let right = self.checked_right()?;
+        let edges = edges(self.top(), self.size.height, weights)?;
+        let rows = edges.windows(2).map(|edge| {
+            Rect::from_corners(Point::new(self.left(), edge[0]), Point::new(right, edge[1]))
+        });
+        Some(rows.collect())
+    }
+
+    // `columns` x `rows` equal cells, a row at a time, left to right.
+    pub fn grid(&self, columns: usize, rows: usize) -> Option<Vec<Rect<T>>> {
+        let mut cells = Vec::new();
+        for row in self.split_rows(&vec![1.0; rows])? {
+            cells.extend(row.split_columns(&vec![1.0; columns])?);
+        }
+        Some(cells)
+    }
+}
+
+impl<T: Num + Copy + PartialOrd> Rect<T> {
+    // The smallest rect around all of `rects`. Empty rects are ignored (as in
+    // union_bounds), so this is None if there are no rects with any area.
+    pub fn bounding(rects: impl IntoIterator<Item = Rect<T>>) -> Option<Rect<T>> {
+        rects
+            .into_iter()
+            .filter(|rect| !rect.is_empty())
+            .reduce(|bounds, rect| bounds.union_bounds(&rect))
+    }
+}
+
+// A guillotine cut tree: each cut goes right across the rect it's given
+// (like a guillotine through paper), splitting it into rows or columns by
+// weight, and each piece is either a finished panel or cut again.
+//
+//     Cut::Rows(vec![
+//         (1.0, Cut::Panel("header")),
+//         (4.0, Cut::Columns(vec![(1.0, Cut::Panel("menu")), (3.0, Cut::Panel("main"))])),
+//     ])
+//
+//     +----------------------+
+//     |        header        |
+//     +------+---------------+
+//     | menu |     main      |
+//     |      |               |
+//     +------+---------------+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cut<N> {
+    Panel(N),
+    Rows(Vec<(f64, Cut<N>)>),
+    Columns(Vec<(f64, Cut<N>)>),
+}
+
+impl<N> Cut<N> {
+    // Every panel and where it ends up inside `bounds`, in the order
+    // they're written.
+    pub fn layout<T>(&self, bounds: Rect<T>) -> Option<Vec<(&N, Rect<T>)>>
+    where
+        T: Num + Copy + PartialOrd + ToPrimitive + FromF64 + CheckedAdd,
+    {
+        let mut panels = Vec::new();
+        self.layout_into(bounds, &mut panels)?;
+        Some(panels)
+    }
+
+    fn layout_into<'a, T>(
+        &'a self,
+        bounds: Rect<T>,
+        panels: &mut Vec<(&'a N, Rect<T>)>,
+    ) -> Option<()>
+    where
+        T: Num + Copy + PartialOrd + ToPrimitive + FromF64 + CheckedAdd,
+    {
+        let (pieces, children) = match self {
+            Cut::Panel(name) => {
+                panels.push((name, bounds));
+                return Some(());
+            }
+            Cut::Rows(children) => (bounds.split_rows(&weights(children))?, children),
+            Cut::Columns(children) => (bounds.split_columns(&weights(children))?, children),
+        };
+        for (piece, (_, child)) in pieces.into_iter().zip(children) {
+            child.layout_into(piece, panels)?;
+        }
+        Some(())
+    }
+}
+
+fn weights<N>(children: &[(f64, Cut<N>)]) -> Vec<f64> {
+    children.iter().map(|(weight, _)| *weight).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // The pieces fill `rect` exactly: their areas add up to its area, the
+    // box around them is `rect`, and no two overlap.
+    fn check_cover<T>(rect: Rect<T>, pieces: &[Rect<T>])
+    where
+        T: Num + Copy + PartialOrd + ToPrimitive + std::fmt::Debug,
+    {
+        // in i128, where even u64 areas fit (as long as they're not huge in
+        // both directions).
+        let area = |rect: &Rect<T>| {
+            rect.size.width.to_i128().unwrap() * rect.size.height.to_i128().unwrap()
+        };
+        let total: i128 = pieces.iter().map(area).sum();
+        assert_eq!(total, area(&rect), "{rect:?} -> {pieces:?}");
+        if total > 0 {
+            assert_eq!(Rect::bounding(pieces.iter().copied()), Some(rect));
+        }
+        for (i, a) in pieces.iter().enumerate() {
+            assert!(rect.can_hold(a), "{a:?} is outside {rect:?}");
+            assert!(
+                pieces[i + 1..].iter().all(|b| !a.intersects(b)),
+                "{a:?} overlaps another piece"
+            );
+        }
+    }
+
+    #[test]
+    fn uneven_splits_still_cover_everything() {
+        let rect = Rect::new(0u32, 0, 10, 1);
+        let columns = rect.split_columns(&[1.0, 1.0, 1.0]).unwrap();
+        let widths: Vec<u32> = columns.iter().map(|r| r.size.width).collect();
+        assert_eq!(widths, [3, 4, 3]);
+        check_cover(rect, &columns);
+    }
+
+    #[test]
+    fn the_dashboard_fills_the_screen() {
+        let dashboard = Cut::Rows(vec![
+            (1.0, Cut::Panel("header")),
+            (
+                5.0,
+                Cut::Columns(vec![
+                    (1.0, Cut::Panel("menu")),
+                    (
+                        4.0,
+                        Cut::Rows(vec![(1.0, Cut::Panel("chart")), (1.0, Cut::Panel("table"))]),
+                    ),
+                ]),
+            ),
+        ]);
+        let screen = Rect::new(7u32, 3, 121, 61);
+        let panels = dashboard.layout(screen).unwrap();
+        let names: Vec<&str> = panels.iter().map(|(name, _)| **name).collect();
+        assert_eq!(names, ["header", "menu", "chart", "table"]);
+        let rects: Vec<Rect<u32>> = panels.iter().map(|(_, rect)| *rect).collect();
+        check_cover(screen, &rects);
+    }
+
+    #[test]
+    fn edges_of_the_range() {
+        let rect = Rect::new(200u8, 250, 55, 5);
+        assert_eq!(rect.grid(2, 2).map(|cells| cells.len()), Some(4));
+        let columns = rect.split_columns(&[1.0, 1.0]).unwrap();
+        assert_eq!(columns[1].checked_right(), Some(255));
+        assert_eq!(Rect::new(200u8, 0, 56, 1).split_columns(&[1.0]), None);
+        assert_eq!(Rect::new(0u8, 200, 1, 56).split_rows(&[1.0]), None);
+        // the other side has to fit too.
+        assert_eq!(Rect::new(0u8, 200, 1, 56).split_columns(&[1.0]), None);
+        assert_eq!(Rect::new(0u8, 200, 1, 56).grid(1, 1), None);
+        assert_eq!(
+            Cut::Panel(())
+                .layout(Rect::new(0u8, 200, 1, 56))
+                .map(|p| p.len()),
+            Some(1)
+        );
+        assert_eq!(
+            Cut::Rows(vec![(1.0, Cut::Panel(()))]).layout(Rect::new(0u8, 200, 1, 56)),
+            None
+        );
+
+        let wide = Rect::new(0u32, 0, u32::MAX, 1);
+        check_cover(wide, &wide.split_columns(&[1.0, 2.0, 3.0]).unwrap());
+        let wide = Rect::new(i32::MIN, 0, i32::MAX, 1);
+        assert_eq!(wide.split_columns(&[1.0, 1.0]).unwrap()[1].right(), -1);
+    }
+
+    #[test]
+    fn edges_past_what_f64_can_hold_exactly() {
+        // the middle edge comes out as 2^64 in f64, past the end.
+        let rect = Rect::new(10u64, 0, u64::MAX - 20, 1);
+        let columns = rect.split_columns(&[1.0, 1e-17]).unwrap();
+        assert_eq!(columns[1].size.width, 0);
+        check_cover(rect, &columns);
+
+        let rect = Rect::new(0i64, i64::MAX - 1_000, 1, 1_000);
+        let rows = rect.split_rows(&[1.0; 7]).unwrap();
+        check_cover(rect, &rows);
+        let rect = Rect::new(i64::MIN, 0, i64::MAX, 1);
+        check_cover(rect, &rect.split_columns(&[1e-300, 1.0, 1e-300]).unwrap());
+    }
+
+    fn weights() -> impl Strategy<Value = Vec<f64>> {
+        let weight = prop_oneof![
+            4 => 0.0..100.0,
+            1 => Just(0.0),
+            1 => Just(-1.0),
+            1 => Just(f64::NAN),
+            1 => Just(f64::INFINITY),
+        ];
+        prop::collection::vec(weight, 1..12)
+    }
+
+    proptest! {
+        #[test]
+        fn splits_cover_the_rect(
+            (x, y) in (0..1_000u32, 0..1_000u32),
+            (width, height) in (0..5_000u32, 0..5_000u32),
+            weights in weights(),
+        ) {
+            let rect = Rect::new(x, y, width, height);
+            let columns = rect.split_columns(&weights).unwrap();
+            prop_assert_eq!(columns.len(), weights.len());
+            check_cover(rect, &columns);
+            let rows = rect.split_rows(&weights).unwrap();
+            prop_assert_eq!(rows.len(), weights.len());
+            check_cover(rect, &rows);
+        }
+
+        #[test]
+        fn splits_cover_big_u64_rects(
+            x in prop_oneof![0..1_000u64, u64::MAX / 2..u64::MAX],
+            width in prop_oneof![0..1_000u64, u64::MAX / 4..=u64::MAX],
+            weights in weights(),
+        ) {
+            let rect = Rect::new(x, 0, width.min(u64::MAX - x), 1);
+            let columns = rect.split_columns(&weights).unwrap();
+            prop_assert_eq!(columns.len(), weights.len());
+            check_cover(rect, &columns);
+        }
+
+        #[test]
+        fn splits_cover_big_i64_rects(
+            y in i64::MIN / 2..i64::MAX / 2,
+            height in prop_oneof![0..1_000i64, i64::MAX / 4..i64::MAX / 2],
+            weights in weights(),
+        ) {
+            let rect = Rect::new(0, y, 1, height);
+            let rows = rect.split_rows(&weights).unwrap();
+            prop_assert_eq!(rows.len(), weights.len());
+            check_cover(rect, &rows);
+        }
+
+        #[test]
+        fn grids_cover_the_rect(width in 0..500u32, height in 0..500u32, columns in 1..20usize, rows in 1..20usize) {
+            let rect = Rect::new(3, 4, width, height);
+            let cells = rect.grid(columns, rows).unwrap();
+            prop_assert_eq!(cells.len(), columns * rows);
+            check_cover(rect, &cells);
+        }
+    }
+}
@@ -0,0 +1,12 @@
+// The modules live in a library as well as the binary, so the benches in
+// benches/ can use them the same way main.rs does.
+
+pub mod layout;
+pub mod packing;
+pub mod parse;
+pub mod rect;
+pub mod rectangle;
+pub mod render;
+pub mod shape;
+pub mod spatial;
+pub mod transform;
@@ -1,41 +1,20 @@
+use methods::{layout, packing, rect, rectangle, render, shape, spatial, transform};
+
+use layout::Cut;
+use packing::{pack, Heuristic, PackOptions};
+use rect::{Point, Rect};
+use rectangle::Rectangle;
+use render::{Drawing, Style};
+use shape::{Circle, Polygon, Shape, Triangle};
+use spatial::{ItemId, QuadTree};
+use transform::Rounding;
+
 fn main() {
     println!("Hello, world!");
 
     // Methods are declared similarly to functions, but are defined
-    // within the context of a struct, and their first parameter is always
-    // "self", which represents the instance of the struct the method is being
-    // called on.
-
-    // We're going to update the previous "area" function we built in our 
-    // "playing-with-structs" exercise to be a method:
-    #[derive(Debug)]
-    struct Rectangle {
-        width:u32,
-        height: u32,
-    }
-
-    impl Rectangle { // declaring a method on rectangle. Everything within the impl block will be associated with Rectangle.
-        fn area(&self) -> u32{ // &self is short for self: &Self. Within an impl block, Self is an alias for the type that the impl block is for.
-            // because we always need to declare self as the first parameter, Rust allows us to just write &self for convenience.
-            // Because we're using a reference, this method just borrows Self. Methods can take ownership of self and borrow self mutably as well, just as they can with any other parameter.
-            // We will occasionally create a method that takes ownership of self, but such occurrences are rare, and are only used if we want to transform our instance into a new value and prevent our user from using the old value.
-            self.width * self.height
-        }
-        fn print_width(&self, message: &str) { // can write multiple methods in here.
-            println!("{}{}",message, self.width);
-        }
-        fn change_height(&mut self, num: u32){ // note that in order to borrow self mutably, we first have to declare self as a mutable instance. (line 31).
-            self.height *= num;
-        }
-        fn reassign(mut self, width: u32, height: u32) -> Rectangle{
-            self.width = width;
-            self.height = height;
-            self
-        }
-        fn greater_volume(&self, rect: &Rectangle) -> bool{
-            self.area() > rect.area()
-         }
-    }
+    // within the context of a struct. Rectangle and its methods live in
+    // rectangle.rs - have a look there first.
 
     // Rust does something called automatic referencing and dereferencing,
     // which allows you to call a method directly on an instance of your struct
@@ -64,6 +43,104 @@ fn main() {
     };
     println!("{}",rect1.greater_volume(&rect2));
     println!("{}",rect1.greater_volume(&rect3));
+
+    // greater_volume only compares sizes, and can_hold asks whether one
+    // size fits inside another:
+    println!("{} {}", rect3.can_hold(&rect2), rect2.can_hold(&rect3)); // true false
+
+    // To ask where rectangles are, they need a position. rect.rs has Rect,
+    // a Rectangle placed at a point (see there for how edges work):
+    let window = rect3.at(0, 0); // 60 x 45 at (0, 0)
+    let dialog = Rect::new(20, 10, 30, 20);
+    let sidebar = Rect::new(60, 0, 15, 45); // touches window's right edge
+    println!(
+        "{} {}",
+        window.can_hold(&dialog),
+        window.contains_point(Point::new(60, 0))
+    ); // true false
+    println!(
+        "{} {:?}",
+        window.intersects(&sidebar),
+        window.intersection(&sidebar)
+    ); // false None - touching isn't overlapping
+    println!("{:?}", dialog.intersection(&Rect::new(40, 25, 100, 100))); // 10 x 5 at (40, 25)
+    println!("{:?}", window.union_bounds(&sidebar)); // 75 x 45 at (0, 0)
+    for piece in window.difference(&dialog) {
+        println!(
+            "  {} x {} at {}, from ({}, {}) to ({}, {})",
+            piece.size.width,
+            piece.size.height,
+            piece.origin,
+            piece.left(),
+            piece.top(),
+            piece.right(),
+            piece.bottom()
+        );
+    }
+    let pieces: i32 = window.difference(&dialog).iter().map(|r| r.area()).sum();
+    println!("{} + {} = {}", pieces, dialog.area(), window.area()); // the pieces and the hole add back up
+    println!("{:?}", dialog.difference(&window)); // [] - all of dialog is in window
+    println!("{}", Rect::new(5, 5, 0, 10).is_empty());
+    println!(
+        "{:?} {:?}",
+        Rect::new(200u8, 0, 55, 1).checked_right(),
+        Rect::new(0u8, 200, 1, 56).checked_bottom()
+    ); // Some(255) None - past u8::MAX
+
+    // Rectangle isn't the only shape. shape.rs has a Shape trait, and
+    // anything that implements it can be passed around as a &dyn Shape:
+    let shapes: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::new(4u32, 3)),
+        Box::new(dialog),
+        Box::new(Circle::new(Point::new(0.0, 0.0), 1.0).unwrap()),
+        Box::new(Triangle::new(
+            Point::new(0.0, 0.0),
+            Point::new(4.0, 0.0),
+            Point::new(0.0, 3.0),
+        )),
+        // an L shape - not convex, but still simple (no edges cross).
+        Box::new(
+            Polygon::new(vec![
+                Point::new(0.0, 0.0),
+                Point::new(2.0, 0.0),
+                Point::new(2.0, 1.0),
+                Point::new(1.0, 1.0),
+                Point::new(1.0, 2.0),
+                Point::new(0.0, 2.0),
+            ])
+            .unwrap(),
+        ),
+    ];
+    for shape in &shapes {
+        describe(shape.as_ref());
+    }
+    let total: f64 = shapes.iter().map(|shape| shape.area()).sum();
+    println!("total area {total:.2}");
+    let probe = Point::new(1.5, 1.5);
+    let hits: Vec<&str> = shapes
+        .iter()
+        .filter(|s| s.contains_point(probe))
+        .map(|s| s.name())
+        .collect();
+    println!("{probe} is inside: {hits:?}"); // not the L - (1.5, 1.5) is in the notch
+    let bowtie = Polygon::new(vec![
+        Point::new(0.0, 0.0),
+        Point::new(2.0, 2.0),
+        Point::new(2.0, 0.0),
+        Point::new(0.0, 2.0),
+    ]);
+    println!("{}", bowtie.unwrap_err()); // the edges cross
+    println!(
+        "{:?}",
+        Polygon::new(vec![Point::new(0.0, 0.0), Point::new(1.0, 1.0)]).map(|p| p.points().len())
+    );
+    println!("{}", Circle::new(Point::new(0.0, 0.0), -1.0).unwrap_err()); // no negative radii
+    let flat = Triangle::new(
+        Point::new(0.0, 0.0),
+        Point::new(1.0, 1.0),
+        Point::new(2.0, 2.0),
+    );
+    println!("{}", flat.contains_point(Point::new(1.0, 1.0))); // false - a triangle in a line has no inside
     
 
     // ASSOCIATED FUNCTIONS
@@ -72,26 +149,429 @@ fn main() {
     // We invoke these associated functions by writing the struct name along with :: syntax (which is also used for namespacing created by modules, which will be discussed later).
     // Associated functions are often used as constructor functions for structs. Many are called "new", but "new" isn't a special name within rust.
 
-    impl Rectangle{
-        fn square(size: u32) -> Self { // associated function. Also a constructor function.
-            Self {
-                width: size,
-                height: size,
-            }
-        }
-        
-        fn width(&self) {
-            println!("called the method width, which prints the property width: self.width == {}", self.width);
-        }
-    }
+    // (square is declared in rectangle.rs.)
 
-     // As shown above, we can also have multiple impl blocks. There's no particular reason to in this example, but they are useful when we use generic types and traits.
+    // As shown in rectangle.rs, we can also have multiple impl blocks. They are useful when we use generic types and traits.
 
     let square = Rectangle::square(10);
     dbg!(&square);
     dbg!(square.width()); // note that when we use the parentheses, Rust knows that we're referencing the method on the struct, rather than the property "width" that the struct has.
-    println!(square.width) // The opposite is true for reading the property - absence of parentheses means Rust knows to look for the property.
+    println!("{}", square.width); // The opposite is true for reading the property - absence of parentheses means Rust knows to look for the property.
+
+    // OVERFLOW
+
+    // area() is just width * height, so a big enough rectangle overflows
+    // its number type. The checked_, saturating_ and wide_ methods don't:
+
+    let huge = Rectangle::new(70_000u32, 70_000);
+    // huge.area(); // panics in a debug build: attempt to multiply with overflow
+    println!("{:?}", huge.checked_area()); // Err(OverflowError { operation: "area" })
+    println!("{}", huge.saturating_area()); // 4294967295, i.e. u32::MAX
+    println!("{}", huge.wide_area()); // 4900000000, as a u64
+    let long = Rectangle::new(u32::MAX, 1);
+    println!("{} {}", long.saturating_perimeter(), long.wide_perimeter()); // 4294967295 8589934592
+    if let Err(e) = Rectangle::new(u8::MAX, 1).checked_perimeter() {
+        println!("{e}");
+    }
+
+    let mut tall = Rectangle::new(3u16, 1000);
+    println!(
+        "{:?}, still {}",
+        tall.checked_change_height(100),
+        tall.height
+    ); // too big for a u16
+    tall.saturating_change_height(100);
+    println!("{}", tall.height); // 65535
+    tall.change_height(0);
+    println!("{:?} {}", tall, tall.perimeter());
+
+    // and the same Rectangle works with other number types:
+    let field = Rectangle::new(2.5, 4.0);
+    println!(
+        "{} {} {}",
+        field.area(),
+        field.perimeter(),
+        field.greater_volume(&Rectangle::square(3.0))
+    );
+    println!("{}", Rectangle::new(-3i64, 4).wide_area()); // -12, as an i128
+
+    // (the tests in rectangle.rs check that checked_, saturating_ and wide_
+    // agree for every u8 and i8 rectangle, and for random bigger ones.)
+
+    // SPATIAL INDEX
+
+    // spatial.rs has a QuadTree for finding rects quickly. Scatter 5,000
+    // rects over a 10,000 x 10,000 world and ask it some questions. (How
+    // much quicker it is than checking every rect is in benches/spatial.rs
+    // - run `cargo bench` - and the tests in spatial.rs check that the two
+    // always agree.)
+    let world = Rect::new(0u32, 0, 10_000, 10_000);
+    let mut tree = QuadTree::new(world);
+    let mut all = Vec::new();
+    let mut seed = 42u64;
+    for _ in 0..5_000 {
+        let x = (next_random(&mut seed) % 10_000) as u32;
+        let y = (next_random(&mut seed) % 10_000) as u32;
+        let width = 1 + (next_random(&mut seed) % 200) as u32;
+        let height = 1 + (next_random(&mut seed) % 200) as u32;
+        let rect = Rect::new(x, y, width, height);
+        all.push((tree.insert(rect), rect));
+    }
+    println!("{} rects, {} levels deep", tree.len(), tree.depth());
+
+    let point = Point::new(5_000, 5_000);
+    let mut here: Vec<ItemId> = tree.containing(point);
+    here.sort();
+    let near = tree.intersecting(&Rect::new(4_900, 4_900, 200, 200));
+    println!(
+        "containing {point}: {here:?}, {} more or less nearby",
+        near.len()
+    );
+    let nearest = tree
+        .nearest(point)
+        .map(|(id, d)| (tree.get(id).copied(), d));
+    println!("{nearest:?}");
+
+    let (first, rect) = all[0];
+    println!(
+        "{:?} {:?}",
+        tree.remove(first) == Some(rect),
+        tree.get(first)
+    );
+    println!("{}", tree.containing(rect.origin).contains(&first)); // false - it's gone
+    let outside = tree.insert(Rect::new(20_000, 20_000, 5, 5)); // outside the world, still found
+    println!(
+        "{:?}",
+        tree.nearest(Point::new(20_010, 20_000))
+            .map(|(id, d)| (id == outside, d))
+    ); // Some((true, 5.0))
+    println!("{}", QuadTree::<u32>::new(world).is_empty());
 
-   
+    // PACKING
+
+    // packing.rs fits a list of Rectangles into a container, like sprites
+    // into a texture atlas. Try each heuristic, with and without turning
+    // rectangles round.
+    let sprites: Vec<Rectangle> = (0..45)
+        .map(|_| {
+            Rectangle::new(
+                4 + (next_random(&mut seed) % 28) as u32,
+                4 + (next_random(&mut seed) % 20) as u32,
+            )
+        })
+        .collect();
+    let atlas = Rectangle::new(128, 64);
+    for heuristic in Heuristic::ALL {
+        for allow_rotation in [false, true] {
+            let packing = pack(
+                &sprites,
+                atlas,
+                PackOptions {
+                    heuristic,
+                    allow_rotation,
+                },
+            );
+            let turned = packing.placements.iter().filter(|p| p.rotated).count();
+            println!("{heuristic:?}, rotation {allow_rotation}: {packing} ({turned} turned, unplaced {:?})", packing.unplaced);
+        }
+    }
+
+    let packing = pack(
+        &sprites,
+        atlas,
+        PackOptions {
+            heuristic: Heuristic::MaxRects,
+            allow_rotation: true,
+        },
+    );
+    for p in packing.placements.iter().take(3) {
+        println!(
+            "sprite {} at {} is {:?}",
+            p.index, p.rect.origin, p.rect.size
+        );
+    }
+    print!("{}", packing.drawing().to_ascii(64));
+    let svg = packing.drawing().to_svg();
+    println!(
+        "{} ({} lines of SVG)",
+        svg.lines().next().unwrap_or(""),
+        svg.lines().count()
+    );
+    let flat = [Rectangle::new(10, 10), Rectangle::new(0, 10)];
+    let options = PackOptions {
+        heuristic: Heuristic::Skyline,
+        allow_rotation: false,
+    };
+    let packing = pack(&flat, atlas, options);
+    println!("{packing}, zero-sized {:?}", packing.zero_sized); // 1 of 2 placed, ..., zero-sized [1]
+
+    // RENDERING
+
+    // render.rs draws shapes, so we can see them rather than reading
+    // dbg! output. ASCII art for the terminal:
+    let frame = Rect::new(0.0, 0.0, 60.0, 30.0);
+    let sun = Circle::new(Point::new(45.0, 10.0), 6.0).unwrap();
+    let roof = Triangle::new(
+        Point::new(5.0, 14.0),
+        Point::new(17.0, 4.0),
+        Point::new(29.0, 14.0),
+    );
+    let house = Rectangle::new(16u32, 13).at(8, 14); // whole numbers and f64 can share a drawing
+    let hill = Polygon::new(vec![
+        Point::new(30.0, 30.0),
+        Point::new(45.0, 20.0),
+        Point::new(60.0, 24.0),
+        Point::new(60.0, 30.0),
+    ])
+    .unwrap();
+
+    let mut drawing = Drawing::new();
+    drawing
+        .add(
+            &frame,
+            Style {
+                ascii: '.',
+                ..Style::filled("skyblue")
+            },
+            None,
+        )
+        .add(
+            &sun,
+            Style {
+                ascii: 'o',
+                ..Style::filled("gold")
+            },
+            Some("sun"),
+        )
+        .add(
+            &hill,
+            Style {
+                ascii: '~',
+                ..Style::filled("olivedrab")
+            },
+            None,
+        )
+        .add(
+            &house,
+            Style {
+                ascii: 'H',
+                ..Style::filled("#c0a080")
+            },
+            Some("house"),
+        )
+        .add(
+            &roof,
+            Style {
+                ascii: '^',
+                ..Style::filled("firebrick")
+            },
+            Some("roof & all"),
+        );
+    print!("{}", drawing.to_ascii(60));
+
+    // and a standalone SVG document - save it as a .svg file and open it
+    // in a browser.
+    print!("{}", drawing.to_svg());
+    println!("{:?}", drawing.bounds());
+    println!("{:?}", Drawing::new().to_ascii(10)); // "" - nothing to draw
+
+    // TRANSFORMATIONS
+
+    // transform.rs can scale, move, turn and fit rectangles. Each operation
+    // changes the rectangle in place (&mut self), or takes it and returns
+    // a new one (self), like change_height and reassign above.
+    let mut photo = Rectangle::new(30u32, 20);
+    photo.scale(0.5, 0.5, Rounding::Down);
+    println!("{:?}", photo); // 15 x 10
+    println!("{:?}", photo.scaled(0.3, 0.3, Rounding::Down)); // 4.5 x 3 -> 4 x 3
+    println!("{:?}", photo.scaled(0.3, 0.3, Rounding::Up)); // 5 x 3
+    println!("{:?}", photo.scaled(0.3, 0.3, Rounding::Nearest)); // 5 x 3 - halves go up
+    println!("{:?}", photo.scaled(1e12, 1.0, Rounding::Nearest)); // width stops at u32::MAX
+    println!("{}", Rounding::Nearest.apply(-1.5)); // -2
+
+    photo.rotate_quarters(1);
+    println!("{:?} {:?}", photo, photo.rotated_quarters(-3)); // 10 x 15, and back again
+    photo.rotate(45.0, Rounding::Up);
+    println!("{:?}", photo); // the box around a turned 10 x 15
+    println!(
+        "{:?}",
+        Rectangle::new(3.0, 4.0).rotated(90.0, Rounding::Nearest)
+    ); // ~4 x 3 - floats aren't rounded
+
+    // fitting keeps the shape: a 16:9 video in a 4:3 screen gets bars
+    // above and below when fitted, and loses its sides when filling.
+    let screen = Rectangle::new(640u32, 480);
+    let mut video = Rectangle::new(1920u32, 1080);
+    println!("{:?}", video.fitted_within(screen, Rounding::Down)); // 640 x 360
+    video.fill(screen, Rounding::Down);
+    println!("{:?}", video); // 853 x 480
+    video.fit_within(screen, Rounding::Down);
+    println!("{:?}", video);
+    println!(
+        "{:?}",
+        Rectangle::new(0u32, 10).filled(screen, Rounding::Down)
+    ); // empty - left alone
+
+    // Rects also have a position, so they turn and scale about an anchor.
+    // A corner can end up somewhere the number type can't go (left of 0 for
+    // a u32), so these give back a Result.
+    let mut window = Rect::new(30u32, 30, 40, 20);
+    println!("{:?}", window.translate(5, 5)); // Ok(())
+    println!("{:?}", window.translated(1, 2).map(|moved| moved.origin));
+    println!("{:?}", window.translated(u32::MAX - 60, 0)); // Err(OutOfRange) - the right edge won't fit
+    let middle = window.center();
+    println!("{}", middle); // (55, 45)
+    println!(
+        "{:?}",
+        window.scaled_about(middle, 2.0, 2.0, Rounding::Nearest)
+    ); // grows in every direction
+       // shrinks towards its top-left corner
+    let corner = Point::new(35.0, 35.0);
+    window
+        .scale_about(corner, 0.5, 0.5, Rounding::Nearest)
+        .unwrap();
+    println!("{:?}", window);
+    println!("{:?}", window.rotated_quarters(1, corner)); // swings down, to the left of the anchor
+    let mut turned = window;
+    turned.rotate_quarters(4, window.center()).unwrap();
+    println!("{}", turned == window); // four quarters is all the way round
+    turned.rotate(30.0, window.center(), Rounding::Up).unwrap();
+    println!("{:?}", turned);
+    println!("{:?}", window.rotated(-30.0, window.center(), Rounding::Up)); // same box, turned the other way
+    let turned = Rect::new(0u32, 0, 10, 5).rotated_quarters(1, Point::new(0.0, 0.0));
+    println!("{turned:?}"); // Err - it would swing to the left of x = 0
+    let turned = Rect::new(0i32, 0, 10, 5).rotated_quarters(1, Point::new(0.0, 0.0));
+    println!("{turned:?}"); // Ok: x = -5, 5 wide and 10 high
+
+    let frame = Rect::new(0u32, 0, 100, 50);
+    let poster = Rect::new(0u32, 0, 30, 40);
+    println!("{:?}", poster.fitted_within(&frame, Rounding::Down)); // 37 x 50, centred
+    println!("{:?}", poster.filled(&frame, Rounding::Down)); // Err - it'd hang over the top, above 0
+    let frame = Rect::new(0i32, 0, 100, 50);
+    let mut poster = Rect::new(0i32, 0, 30, 40);
+    poster.fill(&frame, Rounding::Down).unwrap();
+    println!("{:?}", poster); // 100 x 133, hanging over the top and bottom
+    println!("{:?}", poster.filled(&frame, Rounding::Down) == Ok(poster));
+    poster.fit_within(&frame, Rounding::Down).unwrap();
+    println!("{:?}", poster);
+
+    // PARSING
+
+    // parse.rs reads rectangles from text, and Display writes them back.
+    // (Its tests throw lots of random text at it, too.)
+    let from_config: Rectangle = "30x50".parse().unwrap();
+    let spelled_out = "{ height: 50, width: 30 }".parse::<Rectangle>();
+    println!("{from_config} {spelled_out:?}");
+    let placed: Rect = "30 x 50 @ (10,20)".parse().unwrap();
+    let fields = "{ x: 10, y: 20, width: 30, height: 50, }".parse::<Rect>();
+    println!("{placed} {}", fields == Ok(placed));
+    println!("{}", "-1.5 X 2e3".parse::<Rectangle<f64>>().unwrap());
+
+    // mistakes say which column they're in.
+    for text in [
+        "30 x 5O",
+        "30 50",
+        "30x50 @ (10, 20",
+        "{ width: 30, depth: 5 }",
+        "{ width: 30, width: 5 }",
+        "{ height: 1 }",
+        "30x50 @ (1, 2)",
+        "30x50 junk",
+        "",
+        "99999999999x1",
+    ] {
+        match text.parse::<Rectangle>() {
+            Ok(rect) => println!("{rect}"),
+            Err(error) => println!("{}\n", error.show(text)),
+        }
+    }
+
+    // LAYOUT
+
+    // layout.rs cuts a rect into smaller ones. A 3 x 3 grid of a square
+    // gives nine squares:
+    let board = Rectangle::square(90u32).at(0, 0);
+    let cells = board.grid(3, 3).unwrap();
+    println!("{} cells, the middle one is {}", cells.len(), cells[4]);
+    // and bounding() puts them back together.
+    println!("{:?}", Rect::bounding(cells.iter().copied()) == Some(board));
+    println!("{:?}", Rect::<u32>::bounding([])); // None - nothing to go round
+
+    // weights that don't divide evenly still cover every unit.
+    let columns = Rect::new(0u32, 0, 10, 1)
+        .split_columns(&[1.0, 1.0, 1.0])
+        .unwrap();
+    let widths: Vec<u32> = columns.iter().map(|r| r.size.width).collect();
+    println!("{widths:?}"); // [3, 4, 3]
+    let rows = Rect::new(0u32, 0, 10, 10).split_rows(&[0.0, -1.0, f64::NAN]);
+    println!("{rows:?}"); // no weight above 0 - equal rows
+                          // a rect whose right edge is past u8::MAX can't be cut up.
+    println!(
+        "{:?}",
+        Rect::new(200u8, 0, 100, 10).split_columns(&[1.0, 1.0])
+    ); // None
+
+    // a dashboard: a header, then a menu beside a main area that has two
+    // charts above a table.
+    let dashboard = Cut::Rows(vec![
+        (1.0, Cut::Panel(('H', "header"))),
+        (
+            5.0,
+            Cut::Columns(vec![
+                (1.0, Cut::Panel(('M', "menu"))),
+                (
+                    4.0,
+                    Cut::Rows(vec![
+                        (
+                            1.0,
+                            Cut::Columns(vec![
+                                (1.0, Cut::Panel(('a', "chart a"))),
+                                (1.0, Cut::Panel(('b', "chart b"))),
+                            ]),
+                        ),
+                        (1.0, Cut::Panel(('T', "table"))),
+                    ]),
+                ),
+            ]),
+        ),
+    ]);
+    let screen = Rect::new(0u32, 0, 120, 60);
+    let panels = dashboard.layout(screen).unwrap();
+    let mut drawing = Drawing::new();
+    for ((letter, name), rect) in &panels {
+        println!("{name:>8}: {rect}");
+        drawing.add(
+            rect,
+            Style {
+                ascii: *letter,
+                ..Style::default()
+            },
+            Some(name),
+        );
+    }
+    print!("{}", drawing.to_ascii(60));
+}
+
+// xorshift: a tiny random number generator, plenty for examples.
+fn next_random(seed: &mut u64) -> u64 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    *seed
+}
 
+fn describe(shape: &dyn Shape) {
+    let bounds = shape.bounding_box();
+    let centre = shape.centroid();
+    println!(
+        "{}: area {:.2}, perimeter {:.2}, {} x {} box at {}, centroid ({:.2}, {:.2})",
+        shape.name(),
+        shape.area(),
+        shape.perimeter(),
+        bounds.size.width,
+        bounds.size.height,
+        bounds.origin,
+        centre.x,
+        centre.y,
+    );
 }
@@ -0,0 +1,577 @@
+use std::fmt;
+
+use crate::rect::{Point, Rect};
+use crate::rectangle::Rectangle;
+use crate::render::{Drawing, Style};
+
+// BIN PACKING
+
+// Fit a list of Rectangles into a container without any of them
+// overlapping - like packing sprites into one texture atlas, or panels into
+// a window. Finding the very best packing is far too slow for more than a
+// few rectangles, so these are heuristics: quick rules that usually pack
+// well.
+//
+//   Guillotine  keeps a list of free rects. Placing a rectangle in a free
+//               rect cuts what's left of it in two, like a guillotine
+//               cutting paper - straight across, edge to edge.
+//   MaxRects    also keeps free rects, but each one is as big as it can
+//               be, so they overlap. Slower, but usually packs tightest.
+//   Skyline     only remembers the outline of the top of what's been
+//               placed so far, like a city skyline, and drops each
+//               rectangle onto it as low as it'll go.
+//
+// Rectangles are packed biggest first (which helps all three), but
+// placements still say which input each one was. Anything that doesn't fit
+// is listed in `unplaced`, and anything with a width or height of 0 - which
+// has no area to put anywhere - in `zero_sized`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    Guillotine,
+    MaxRects,
+    Skyline,
+}
+
+impl Heuristic {
+    pub const ALL: [Heuristic; 3] = [
+        Heuristic::Guillotine,
+        Heuristic::MaxRects,
+        Heuristic::Skyline,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackOptions {
+    pub heuristic: Heuristic,
+    // whether a rectangle may be turned 90 degrees to make it fit.
+    pub allow_rotation: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    // which of the input rectangles this is.
+    pub index: usize,
+    // where it went, and its size after any rotation.
+    pub rect: Rect,
+    pub rotated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packing {
+    pub container: Rectangle,
+    pub placements: Vec<Placement>,
+    pub unplaced: Vec<usize>,
+    pub zero_sized: Vec<usize>,
+}
+
+impl Packing {
+    // How much of the container is covered, from 0 to 100.
+    pub fn utilisation(&self) -> f64 {
+        let used: u64 = self
+            .placements
+            .iter()
+            .map(|p| p.rect.size.wide_area())
+            .sum();
+        match self.container.wide_area() {
+            0 => 0.0,
+            total => 100.0 * used as f64 / total as f64,
+        }
+    }
+
+    // The layout as a Drawing (see render.rs): the container in grey with
+    // each placement on top in its own colour, labelled with its own
+    // letter. Empty space is '.' in ASCII art.
+    pub fn drawing(&self) -> Drawing<'_> {
+        let mut drawing = Drawing::new();
+        let background = Style {
+            stroke: String::from("none"),
+            ascii: '.',
+            ..Style::filled("#eeeeee")
+        };
+        drawing.add(&self.container, background, None);
+        for p in &self.placements {
+            let label = label(p.index);
+            let style = Style {
+                ascii: label,
+                ..Style::filled(&format!("hsl({}, 60%, 70%)", p.index * 47 % 360))
+            };
+            drawing.add(&p.rect, style, Some(&label.to_string()));
+        }
+        drawing
+    }
+}
+
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} placed, {:.1}% used",
+            self.placements.len(),
+            self.placements.len() + self.unplaced.len() + self.zero_sized.len(),
+            self.utilisation()
+        )
+    }
+}
+
+// A, B, ... Z, a ... z, 0 ... 9, then round again.
+fn label(index: usize) -> char {
+    const LABELS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    LABELS[index % LABELS.len()] as char
+}
+
+pub fn pack(items: &[Rectangle], container: Rectangle, options: PackOptions) -> Packing {
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    // biggest first; ties broken by the longer side.
+    order.sort_by_key(|&i| {
+        let item = items[i];
+        std::cmp::Reverse((item.wide_area(), item.width.max(item.height)))
+    });
+
+    let mut packer: Box<dyn Packer> = match options.heuristic {
+        Heuristic::Guillotine => Box::new(Guillotine::new(container)),
+        Heuristic::MaxRects => Box::new(MaxRects::new(container)),
+        Heuristic::Skyline => Box::new(Skyline::new(container)),
+    };
+
+    let mut packing = Packing {
+        container,
+        placements: Vec::new(),
+        unplaced: Vec::new(),
+        zero_sized: Vec::new(),
+    };
+    for index in order {
+        let item = items[index];
+        if item.width == 0 || item.height == 0 {
+            packing.zero_sized.push(index);
+            continue;
+        }
+        let mut sizes = vec![(item, false)];
+        if options.allow_rotation && item.width != item.height {
+            sizes.push((Rectangle::new(item.height, item.width), true));
+        }
+        // each packer scores where a size would go (lower is better); try
+        // each way round and keep the better one.
+        let best = sizes
+            .into_iter()
+            .filter_map(|(size, rotated)| Some((packer.find(size)?, size, rotated)))
+            .min_by_key(|&((_, score), _, _)| score);
+        match best {
+            Some(((origin, _), size, rotated)) => {
+                let rect = size.at(origin.x, origin.y);
+                packer.place(rect);
+                packing.placements.push(Placement {
+                    index,
+                    rect,
+                    rotated,
+                });
+            }
+            None => packing.unplaced.push(index),
+        }
+    }
+    packing.placements.sort_by_key(|p| p.index);
+    packing.unplaced.sort();
+    packing.zero_sized.sort();
+    packing
+}
+
+// Each heuristic is a Packer: find() suggests where a size could go (and a
+// score - lower is better), place() commits to it. Scores are u64 so that
+// areas of u32-sized rects fit in them.
+trait Packer {
+    fn find(&self, size: Rectangle) -> Option<(Point, (u64, u64))>;
+    fn place(&mut self, rect: Rect);
+}
+
+// How much room is left around `size` in `free`, short side first -
+// "best short side fit".
+fn leftover(free: &Rect, size: Rectangle) -> (u64, u64) {
+    let across = free.size.width - size.width;
+    let down = free.size.height - size.height;
+    (across.min(down).into(), across.max(down).into())
+}
+
+// GUILLOTINE
+
+struct Guillotine {
+    free: Vec<Rect>,
+}
+
+impl Guillotine {
+    fn new(container: Rectangle) -> Guillotine {
+        Guillotine {
+            free: vec![container.at(0, 0)],
+        }
+    }
+}
+
+impl Packer for Guillotine {
+    fn find(&self, size: Rectangle) -> Option<(Point, (u64, u64))> {
+        self.free
+            .iter()
+            .filter(|free| free.size.can_hold(&size))
+            // best area fit: the free rect with the least area left over.
+            .map(|free| {
+                (
+                    free.origin,
+                    (
+                        free.size.wide_area() - size.wide_area(),
+                        leftover(free, size).0,
+                    ),
+                )
+            })
+            .min_by_key(|&(_, score)| score)
+    }
+
+    fn place(&mut self, rect: Rect) {
+        let Some(i) = self
+            .free
+            .iter()
+            .position(|free| free.origin == rect.origin && free.size.can_hold(&rect.size))
+        else {
+            return;
+        };
+        let free = self.free.swap_remove(i);
+        let across = free.size.width - rect.size.width;
+        let down = free.size.height - rect.size.height;
+        // cut along the shorter leftover side, so the bigger piece stays
+        // as big as possible.
+        let (right, below) = if across < down {
+            (
+                Rect::new(rect.right(), free.top(), across, rect.size.height),
+                Rect::new(free.left(), rect.bottom(), free.size.width, down),
+            )
+        } else {
+            (
+                Rect::new(rect.right(), free.top(), across, free.size.height),
+                Rect::new(free.left(), rect.bottom(), rect.size.width, down),
+            )
+        };
+        self.free
+            .extend([right, below].into_iter().filter(|r| !r.is_empty()));
+    }
+}
+
+// MAXRECTS
+
+struct MaxRects {
+    free: Vec<Rect>,
+}
+
+impl MaxRects {
+    fn new(container: Rectangle) -> MaxRects {
+        MaxRects {
+            free: vec![container.at(0, 0)],
+        }
+    }
+}
+
+impl Packer for MaxRects {
+    fn find(&self, size: Rectangle) -> Option<(Point, (u64, u64))> {
+        self.free
+            .iter()
+            .filter(|free| free.size.can_hold(&size))
+            .map(|free| (free.origin, leftover(free, size)))
+            .min_by_key(|&(_, score)| score)
+    }
+
+    fn place(&mut self, rect: Rect) {
+        let mut free = Vec::new();
+        for f in self.free.drain(..) {
+            if !f.intersects(&rect) {
+                free.push(f);
+                continue;
+            }
+            // the biggest rects left on each side of `rect` - they overlap
+            // each other at the corners, which is the point.
+            if rect.left() > f.left() {
+                free.push(Rect::from_corners(
+                    f.origin,
+                    Point::new(rect.left(), f.bottom()),
+                ));
+            }
+            if rect.right() < f.right() {
+                free.push(Rect::from_corners(
+                    Point::new(rect.right(), f.top()),
+                    Point::new(f.right(), f.bottom()),
+                ));
+            }
+            if rect.top() > f.top() {
+                free.push(Rect::from_corners(
+                    f.origin,
+                    Point::new(f.right(), rect.top()),
+                ));
+            }
+            if rect.bottom() < f.bottom() {
+                free.push(Rect::from_corners(
+                    Point::new(f.left(), rect.bottom()),
+                    Point::new(f.right(), f.bottom()),
+                ));
+            }
+        }
+        // drop free rects that are inside other free rects.
+        let mut kept: Vec<Rect> = Vec::new();
+        for (i, f) in free.iter().enumerate() {
+            let covered = free
+                .iter()
+                .enumerate()
+                .any(|(j, other)| i != j && other.can_hold(f) && (other != f || j < i));
+            if !covered {
+                kept.push(*f);
+            }
+        }
+        self.free = kept;
+    }
+}
+
+// SKYLINE
+
+// The skyline is a list of flat segments, left to right, each at the
+// height (y) where the next rectangle dropped there would rest.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+struct Skyline {
+    container: Rectangle,
+    segments: Vec<Segment>,
+}
+
+impl Skyline {
+    fn new(container: Rectangle) -> Skyline {
+        Skyline {
+            container,
+            segments: vec![Segment {
+                x: 0,
+                y: 0,
+                width: container.width,
+            }],
+        }
+    }
+
+    // Where a rectangle `width` wide would rest if its left edge was at
+    // segment i: on the highest segment underneath it.
+    fn rest_on(&self, i: usize, width: u32) -> Option<u32> {
+        let start = self.segments[i].x;
+        // too wide to fit at all if the right edge is past what a u32 can
+        // even hold.
+        let end = start.checked_add(width)?;
+        if end > self.container.width {
+            return None;
+        }
+        let mut y = 0;
+        for segment in &self.segments[i..] {
+            if segment.x >= end {
+                break;
+            }
+            y = y.max(segment.y);
+        }
+        Some(y)
+    }
+}
+
+impl Packer for Skyline {
+    fn find(&self, size: Rectangle) -> Option<(Point, (u64, u64))> {
+        (0..self.segments.len())
+            .filter_map(|i| {
+                let y = self.rest_on(i, size.width)?;
+                let bottom = y.checked_add(size.height)?;
+                (bottom <= self.container.height)
+                    // bottom-left: keep the top edge as low as possible.
+                    .then(|| {
+                        (
+                            Point::new(self.segments[i].x, y),
+                            (bottom.into(), self.segments[i].x.into()),
+                        )
+                    })
+            })
+            .min_by_key(|&(_, score)| score)
+    }
+
+    fn place(&mut self, rect: Rect) {
+        let new = Segment {
+            x: rect.left(),
+            y: rect.bottom(),
+            width: rect.size.width,
+        };
+        let mut segments = Vec::new();
+        for s in &self.segments {
+            let end = s.x + s.width;
+            // keep the parts of old segments not under the new one.
+            if s.x < new.x {
+                segments.push(Segment {
+                    width: end.min(new.x) - s.x,
+                    ..*s
+                });
+            }
+            if end > new.x + new.width {
+                let x = s.x.max(new.x + new.width);
+                segments.push(Segment {
+                    x,
+                    width: end - x,
+                    ..*s
+                });
+            }
+        }
+        segments.push(new);
+        segments.sort_by_key(|s| s.x);
+        // join neighbours at the same height.
+        let mut merged: Vec<Segment> = Vec::new();
+        for s in segments {
+            match merged.last_mut() {
+                Some(last) if last.y == s.y => last.width += s.width,
+                _ => merged.push(s),
+            }
+        }
+        self.segments = merged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn options(heuristic: Heuristic) -> PackOptions {
+        PackOptions {
+            heuristic,
+            allow_rotation: true,
+        }
+    }
+
+    #[test]
+    fn zero_sized_items_are_reported_separately() {
+        let items = [
+            Rectangle::new(0, 5),
+            Rectangle::new(4, 4),
+            Rectangle::new(5, 0),
+            Rectangle::new(50, 50),
+        ];
+        for heuristic in Heuristic::ALL {
+            let packing = pack(&items, Rectangle::new(10, 10), options(heuristic));
+            assert_eq!(packing.zero_sized, [0, 2], "{heuristic:?}");
+            assert_eq!(packing.unplaced, [3], "{heuristic:?}");
+            assert_eq!(packing.placements.len(), 1, "{heuristic:?}");
+            assert_eq!(packing.to_string(), "1 of 4 placed, 16.0% used");
+        }
+    }
+
+    #[test]
+    fn huge_containers_and_items() {
+        // areas and edges past u32::MAX used to overflow.
+        let container = Rectangle::new(u32::MAX, u32::MAX);
+        let items = [
+            Rectangle::new(u32::MAX, 1),
+            Rectangle::new(u32::MAX, u32::MAX - 1),
+            Rectangle::new(70_000, 70_000),
+            Rectangle::new(1, 1),
+        ];
+        for heuristic in Heuristic::ALL {
+            let packing = pack(&items, container, options(heuristic));
+            assert_eq!(packing.placements.len(), 2, "{heuristic:?}");
+            assert_eq!(packing.unplaced, [2, 3], "{heuristic:?}");
+            for (i, a) in packing.placements.iter().enumerate() {
+                assert!(a.rect.checked_right().is_some());
+                assert!(a.rect.checked_bottom().is_some());
+                assert!(packing.placements[i + 1..]
+                    .iter()
+                    .all(|b| !a.rect.intersects(&b.rect)));
+            }
+        }
+    }
+
+    #[test]
+    fn skyline_item_wider_than_the_space_left() {
+        // the first item leaves a 5-wide gap on the right. The second won't
+        // go on top of the first, and its right edge would be past u32::MAX
+        // if it started in the gap.
+        let container = Rectangle::new(u32::MAX, 10);
+        let items = [
+            Rectangle::new(u32::MAX - 5, 10),
+            Rectangle::new(u32::MAX - 6, 10),
+        ];
+        let packing = pack(&items, container, options(Heuristic::Skyline));
+        assert_eq!(packing.unplaced, [1]);
+    }
+
+    #[test]
+    fn rotation_turns_items_that_only_fit_sideways() {
+        let items = [Rectangle::new(10, 2)];
+        let container = Rectangle::new(2, 10);
+        for heuristic in Heuristic::ALL {
+            let packing = pack(&items, container, options(heuristic));
+            assert_eq!(packing.placements.len(), 1, "{heuristic:?}");
+            let placement = packing.placements[0];
+            assert!(placement.rotated);
+            assert_eq!(placement.rect, Rect::new(0, 0, 2, 10));
+
+            let upright = PackOptions {
+                heuristic,
+                allow_rotation: false,
+            };
+            assert_eq!(pack(&items, container, upright).unplaced, [0]);
+        }
+    }
+
+    // What every packing has to get right, whatever the heuristic.
+    fn check(items: &[Rectangle], container: Rectangle, options: PackOptions) {
+        let packing = pack(items, container, options);
+
+        // every item ends up in exactly one of the three lists.
+        let mut seen: Vec<usize> = packing
+            .placements
+            .iter()
+            .map(|p| p.index)
+            .chain(packing.unplaced.iter().copied())
+            .chain(packing.zero_sized.iter().copied())
+            .collect();
+        seen.sort();
+        assert_eq!(seen, (0..items.len()).collect::<Vec<_>>(), "{options:?}");
+
+        for (i, a) in packing.placements.iter().enumerate() {
+            assert!(container.at(0, 0).can_hold(&a.rect), "{options:?} {a:?}");
+            for b in &packing.placements[i + 1..] {
+                assert!(!a.rect.intersects(&b.rect), "{options:?} {a:?} {b:?}");
+            }
+            // a turned item has its sides swapped; anything else keeps its
+            // size.
+            let item = items[a.index];
+            if a.rotated {
+                assert!(options.allow_rotation, "{options:?} {a:?}");
+                assert_eq!(a.rect.size, Rectangle::new(item.height, item.width));
+            } else {
+                assert_eq!(a.rect.size, item, "{options:?}");
+            }
+        }
+    }
+
+    fn items() -> impl Strategy<Value = Vec<Rectangle>> {
+        prop::collection::vec((0..40u32, 0..30u32), 0..40).prop_map(|sizes| {
+            sizes
+                .into_iter()
+                .map(|(w, h)| Rectangle::new(w, h))
+                .collect()
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn placements_fit_and_never_overlap(
+            items in items(),
+            width in 1..150u32,
+            height in 1..100u32,
+        ) {
+            for heuristic in Heuristic::ALL {
+                for allow_rotation in [false, true] {
+                    let options = PackOptions { heuristic, allow_rotation };
+                    check(&items, Rectangle::new(width, height), options);
+                }
+            }
+        }
+    }
+}
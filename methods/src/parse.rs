@@ -0,0 +1,528 @@
+use std::fmt;
+use std::str::FromStr;
+
+use num_traits::Num;
+
+use crate::rect::{Point, Rect};
+use crate::rectangle::Rectangle;
+
+// PARSING
+
+// Reading rectangles from text - config files, command-line arguments -
+// with "30x50".parse::<Rectangle>(). Three forms are understood:
+//
+//     30x50                          width x height ("X" works too)
+//     30 x 50 @ (10, 20)             and where its top-left corner is
+//     { width: 30, height: 50 }      the fields spelled out, in any order
+//
+// Spaces are allowed between any two parts. A Rectangle has no position, so
+// it doesn't take "@ (10, 20)" or x and y fields. A Rect does, but they're
+// optional - without them it sits at (0, 0).
+//
+// Display writes the first two forms ("30x50" and "30x50 @ (10, 20)"), and
+// parsing what Display wrote always gives back the same rectangle.
+//
+// Mistakes are reported with the column they're in (counting characters
+// from 1), so a long config line can point at the exact problem:
+//
+//     30 x 5O
+//          ^ column 6: "5O" isn't a valid number (invalid digit found in string)
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRectError {
+    pub column: usize,
+    pub kind: ParseRectErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRectErrorKind {
+    // what should have been there, and what was (None at the end of the text).
+    Expected {
+        expected: &'static str,
+        found: Option<char>,
+    },
+    // the text, and why the number type wouldn't take it.
+    InvalidNumber {
+        text: String,
+        reason: String,
+    },
+    UnknownField(String),
+    DuplicateField(String),
+    MissingField(&'static str),
+    // "@ (x, y)" or x and y fields, when parsing a Rectangle.
+    UnexpectedPosition,
+}
+
+impl fmt::Display for ParseRectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: ", self.column)?;
+        match &self.kind {
+            ParseRectErrorKind::Expected {
+                expected,
+                found: Some(found),
+            } => {
+                write!(f, "expected {expected} but found {found:?}")
+            }
+            ParseRectErrorKind::Expected {
+                expected,
+                found: None,
+            } => {
+                write!(f, "expected {expected} but the text ended")
+            }
+            ParseRectErrorKind::InvalidNumber { text, reason } => {
+                write!(f, "{text:?} isn't a valid number ({reason})")
+            }
+            ParseRectErrorKind::UnknownField(name) => write!(f, "there's no field called {name:?}"),
+            ParseRectErrorKind::DuplicateField(name) => write!(f, "{name:?} is given twice"),
+            ParseRectErrorKind::MissingField(name) => write!(f, "{name:?} is missing"),
+            ParseRectErrorKind::UnexpectedPosition => {
+                write!(
+                    f,
+                    "a Rectangle has no position - parse it as a Rect instead"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseRectError {}
+
+impl ParseRectError {
+    // The input with a ^ under the problem, then the message - for showing
+    // to whoever typed it.
+    pub fn show(&self, input: &str) -> String {
+        format!("{input}\n{:>width$} {self}", "^", width = self.column)
+    }
+}
+
+// Walks through the text one character at a time.
+struct Parser {
+    chars: Vec<char>,
+    // index of the next character; its column is this + 1.
+    at: usize,
+}
+
+// What was read, before it's checked against Rectangle or Rect. The
+// position remembers its column, in case a Rectangle has to refuse it.
+struct Parsed<T> {
+    width: T,
+    height: T,
+    position: Option<(usize, Point<T>)>,
+}
+
+impl Parser {
+    fn new(text: &str) -> Parser {
+        Parser {
+            chars: text.chars().collect(),
+            at: 0,
+        }
+    }
+
+    fn column(&self) -> usize {
+        self.at + 1
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn error<V>(&self, kind: ParseRectErrorKind) -> Result<V, ParseRectError> {
+        Err(ParseRectError {
+            column: self.column(),
+            kind,
+        })
+    }
+
+    fn expected<V>(&self, expected: &'static str) -> Result<V, ParseRectError> {
+        self.error(ParseRectErrorKind::Expected {
+            expected,
+            found: self.peek(),
+        })
+    }
+
+    // Skips spaces, then takes `wanted` if it's next.
+    fn eat(&mut self, wanted: &[char]) -> bool {
+        self.skip_spaces();
+        if self.peek().is_some_and(|c| wanted.contains(&c)) {
+            self.at += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, wanted: char, expected: &'static str) -> Result<(), ParseRectError> {
+        if self.eat(&[wanted]) {
+            Ok(())
+        } else {
+            self.expected(expected)
+        }
+    }
+
+    // Letters, digits and anything else a number might contain ("-1.5e3",
+    // "inf"), stopping at the x between width and height.
+    fn word(&mut self) -> String {
+        self.skip_spaces();
+        let start = self.at;
+        while self
+            .peek()
+            .is_some_and(|c| (c.is_alphanumeric() || "+-._".contains(c)) && c != 'x' && c != 'X')
+        {
+            self.at += 1;
+        }
+        self.chars[start..self.at].iter().collect()
+    }
+
+    fn field_name(&mut self) -> String {
+        let start = self.at;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.at += 1;
+        }
+        self.chars[start..self.at].iter().collect()
+    }
+
+    fn number<T>(&mut self) -> Result<T, ParseRectError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.skip_spaces();
+        let column = self.column();
+        let text = self.word();
+        if text.is_empty() {
+            return self.expected("a number");
+        }
+        text.parse().map_err(|reason: T::Err| ParseRectError {
+            column,
+            kind: ParseRectErrorKind::InvalidNumber {
+                text,
+                reason: reason.to_string(),
+            },
+        })
+    }
+
+    fn parse<T>(&mut self) -> Result<Parsed<T>, ParseRectError>
+    where
+        T: Num + Copy + FromStr,
+        T::Err: fmt::Display,
+    {
+        self.skip_spaces();
+        let parsed = if self.peek() == Some('{') {
+            self.fields()?
+        } else {
+            self.size()?
+        };
+        self.skip_spaces();
+        if self.peek().is_some() {
+            return self.expected("the end of the text");
+        }
+        Ok(parsed)
+    }
+
+    // 30 x 50 @ (10, 20)
+    fn size<T>(&mut self) -> Result<Parsed<T>, ParseRectError>
+    where
+        T: Num + Copy + FromStr,
+        T::Err: fmt::Display,
+    {
+        let width = self.number()?;
+        self.expect_x()?;
+        let height = self.number()?;
+        let mut position = None;
+        self.skip_spaces();
+        let column = self.column();
+        if self.eat(&['@']) {
+            self.expect('(', "'(' after '@'")?;
+            let x = self.number()?;
+            self.expect(',', "',' between x and y")?;
+            let y = self.number()?;
+            self.expect(')', "')' after x and y")?;
+            position = Some((column, Point::new(x, y)));
+        }
+        Ok(Parsed {
+            width,
+            height,
+            position,
+        })
+    }
+
+    fn expect_x(&mut self) -> Result<(), ParseRectError> {
+        if self.eat(&['x', 'X']) {
+            Ok(())
+        } else {
+            self.expected("'x' between width and height")
+        }
+    }
+
+    // { width: 30, height: 50, x: 10, y: 20 } - a comma after the last
+    // field is fine too.
+    fn fields<T>(&mut self) -> Result<Parsed<T>, ParseRectError>
+    where
+        T: Num + Copy + FromStr,
+        T::Err: fmt::Display,
+    {
+        const NAMES: [&str; 4] = ["width", "height", "x", "y"];
+        let mut values: [Option<(usize, T)>; 4] = [None; 4];
+        self.expect('{', "'{'")?;
+        while !self.eat(&['}']) {
+            self.skip_spaces();
+            let column = self.column();
+            let name = self.field_name();
+            if name.is_empty() {
+                return self.expected("a field name or '}'");
+            }
+            let Some(field) = NAMES.iter().position(|&known| known == name) else {
+                return Err(ParseRectError {
+                    column,
+                    kind: ParseRectErrorKind::UnknownField(name),
+                });
+            };
+            if values[field].is_some() {
+                return Err(ParseRectError {
+                    column,
+                    kind: ParseRectErrorKind::DuplicateField(name),
+                });
+            }
+            self.expect(':', "':' after the field name")?;
+            values[field] = Some((column, self.number()?));
+            if !self.eat(&[',']) {
+                self.expect('}', "',' or '}'")?;
+                break;
+            }
+        }
+
+        let [width, height, x, y] = values;
+        let (Some((_, width)), Some((_, height))) = (width, height) else {
+            let missing = if width.is_none() { "width" } else { "height" };
+            return self.error(ParseRectErrorKind::MissingField(missing));
+        };
+        let column = x.or(y).map(|(column, _)| column);
+        let position = column.map(|column| {
+            let value = |field: Option<(usize, T)>| field.map_or(T::zero(), |(_, value)| value);
+            (column, Point::new(value(x), value(y)))
+        });
+        Ok(Parsed {
+            width,
+            height,
+            position,
+        })
+    }
+}
+
+impl<T> FromStr for Rectangle<T>
+where
+    T: Num + Copy + FromStr,
+    T::Err: fmt::Display,
+{
+    type Err = ParseRectError;
+
+    fn from_str(text: &str) -> Result<Rectangle<T>, ParseRectError> {
+        let parsed = Parser::new(text).parse()?;
+        if let Some((column, _)) = parsed.position {
+            return Err(ParseRectError {
+                column,
+                kind: ParseRectErrorKind::UnexpectedPosition,
+            });
+        }
+        Ok(Rectangle::new(parsed.width, parsed.height))
+    }
+}
+
+impl<T> FromStr for Rect<T>
+where
+    T: Num + Copy + FromStr,
+    T::Err: fmt::Display,
+{
+    type Err = ParseRectError;
+
+    fn from_str(text: &str) -> Result<Rect<T>, ParseRectError> {
+        let parsed: Parsed<T> = Parser::new(text).parse()?;
+        let origin = parsed
+            .position
+            .map_or(Point::new(T::zero(), T::zero()), |(_, p)| p);
+        Ok(Rectangle::new(parsed.width, parsed.height).at(origin.x, origin.y))
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Rectangle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Rect<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} @ {}", self.size, self.origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn rectangle_error(text: &str) -> ParseRectError {
+        text.parse::<Rectangle>().unwrap_err()
+    }
+
+    fn expected(expected: &'static str, found: Option<char>) -> ParseRectErrorKind {
+        ParseRectErrorKind::Expected { expected, found }
+    }
+
+    #[test]
+    fn columns_of_each_kind_of_error() {
+        let cases = [
+            (
+                "30 50",
+                4,
+                expected("'x' between width and height", Some('5')),
+            ),
+            ("30x50 @ (10, 20", 16, expected("')' after x and y", None)),
+            ("30x50 junk", 7, expected("the end of the text", Some('j'))),
+            ("", 1, expected("a number", None)),
+            (
+                "{ width: 30, depth: 5 }",
+                14,
+                ParseRectErrorKind::UnknownField(String::from("depth")),
+            ),
+            (
+                "{ width: 30, width: 5 }",
+                14,
+                ParseRectErrorKind::DuplicateField(String::from("width")),
+            ),
+            (
+                "{ height: 1 }",
+                14,
+                ParseRectErrorKind::MissingField("width"),
+            ),
+            ("30x50 @ (1, 2)", 7, ParseRectErrorKind::UnexpectedPosition),
+            (
+                "{ x: 1, width: 2, height: 3 }",
+                3,
+                ParseRectErrorKind::UnexpectedPosition,
+            ),
+        ];
+        for (text, column, kind) in cases {
+            assert_eq!(
+                rectangle_error(text),
+                ParseRectError { column, kind },
+                "{text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn columns_of_invalid_numbers() {
+        for (text, column, number) in [("30 x 5O", 6, "5O"), ("99999999999x1", 1, "99999999999")] {
+            let error = rectangle_error(text);
+            assert_eq!(error.column, column, "{text:?}");
+            assert!(
+                matches!(&error.kind, ParseRectErrorKind::InvalidNumber { text, .. } if text == number),
+                "{text:?}: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn errors_display_their_column() {
+        assert_eq!(
+            rectangle_error("30 x 5O").show("30 x 5O"),
+            "30 x 5O\n     ^ column 6: \"5O\" isn't a valid number (invalid digit found in string)"
+        );
+        assert_eq!(
+            rectangle_error("30x50 @ (10, 20").to_string(),
+            "column 16: expected ')' after x and y but the text ended"
+        );
+        assert_eq!(
+            rectangle_error("{ height: 1 }").to_string(),
+            "column 14: \"width\" is missing"
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        assert_eq!(Rectangle::new(30, 50).to_string(), "30x50");
+        assert_eq!(Rect::new(10, 20, 30, 50).to_string(), "30x50 @ (10, 20)");
+        assert_eq!("30x50 @ (10, 20)".parse(), Ok(Rect::new(10, 20, 30, 50)));
+        assert_eq!(
+            "{ x: 10, y: 20, width: 30, height: 50, }".parse(),
+            Ok(Rect::new(10, 20, 30, 50))
+        );
+        assert_eq!(
+            "{ y: 2, height: 5, width: 3 }".parse(),
+            Ok(Rect::new(0, 2, 3, 5))
+        );
+    }
+
+    // Characters the parser cares about, plus some it doesn't.
+    const ALPHABET: &[char] = &[
+        '0', '1', '9', '-', '+', '.', 'e', 'x', 'X', '@', '(', ')', ',', '{', '}', ':', ' ', 'w',
+        'i', 'd', 't', 'h', 'y', 'é', '\u{0}', '∞',
+    ];
+
+    // Half random characters from ALPHABET, half valid rects with a few
+    // characters inserted, removed or changed.
+    fn text() -> impl Strategy<Value = String> {
+        let letter = prop::sample::select(ALPHABET);
+        let random = prop::collection::vec(letter.clone(), 0..24);
+        let valid = any::<(i32, i32, i32, i32)>()
+            .prop_map(|(x, y, width, height)| Rect::new(x, y, width, height).to_string());
+        let edits = prop::collection::vec((any::<prop::sample::Index>(), 0..3u8, letter), 0..3);
+        prop_oneof![
+            random.prop_map(String::from_iter),
+            (valid, edits).prop_map(|(text, edits)| {
+                let mut chars: Vec<char> = text.chars().collect();
+                for (at, edit, new) in edits {
+                    let at = at.index(chars.len() + 1);
+                    match edit {
+                        0 => chars.insert(at, new),
+                        1 if at < chars.len() => drop(chars.remove(at)),
+                        _ if at < chars.len() => chars[at] = new,
+                        _ => {}
+                    }
+                }
+                String::from_iter(chars)
+            }),
+        ]
+    }
+
+    proptest! {
+        // random text is cheap to try, so try lots of it.
+        #![proptest_config(ProptestConfig::with_cases(4096))]
+
+        // Whatever the text, parsing doesn't panic, what it accepts comes
+        // back unchanged through Display, and errors point inside the text
+        // (or just past its end).
+        #[test]
+        fn any_text(text in text()) {
+            match text.parse::<Rect<i32>>() {
+                Ok(rect) => prop_assert_eq!(rect.to_string().parse::<Rect<i32>>(), Ok(rect)),
+                Err(error) => prop_assert!(error.column <= text.chars().count() + 1, "{}", error),
+            }
+            let _ = text.parse::<Rectangle<u8>>();
+            let _ = text.parse::<Rectangle<f64>>();
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn every_rect_round_trips(x: u32, y: u32, width: u32, height: u32) {
+            let size = Rectangle::new(width, height);
+            prop_assert_eq!(size.to_string().parse(), Ok(size));
+            let rect = size.at(x, y);
+            prop_assert_eq!(rect.to_string().parse(), Ok(rect));
+        }
+
+        #[test]
+        fn every_float_rectangle_round_trips(width in any::<f64>(), height in any::<f64>()) {
+            prop_assume!(width.is_finite() && height.is_finite());
+            let size = Rectangle::new(width, height);
+            prop_assert_eq!(size.to_string().parse(), Ok(size));
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use std::fmt;
+
+use num_traits::{CheckedAdd, Num};
+
+use crate::rectangle::Rectangle;
+
+// POSITIONED RECTANGLES
+
+// A Rectangle is only a size - it doesn't say where it is, so the most we
+// can ask two of them is which is bigger (greater_volume) or whether one
+// would fit inside the other (can_hold). A Rect is a Rectangle placed
+// somewhere: its top-left corner (origin) plus its size.
+//
+// Coordinates work like they do on a screen: x grows to the right and y
+// grows downwards.
+//
+// EDGES
+//
+// A Rect covers its left and top edges but not its right and bottom ones:
+// Rect::new(0, 0, 10, 10) contains (0, 0) and (9, 9), but not (10, 5).
+// That's how pixels work - a 10-wide rect covers pixels 0 to 9 - and it
+// means that:
+//
+//   - rects that only touch (one's right edge is the other's left edge)
+//     don't intersect, and their intersection is None.
+//   - splitting a rect into pieces (see difference) gives pieces that don't
+//     overlap, and every point is in exactly one of them.
+//   - a rect with a width or height of 0 is empty: it contains no points
+//     and intersects nothing, though it can still sit inside another rect.
+//
+// LIMITS
+//
+// The right edge is origin.x + width, so a Rect<u8> at x = 200 can be at
+// most 55 wide - any wider and right() overflows, which panics in a debug
+// build and wraps around in a release one. Everything that uses the right
+// or bottom edge (contains_point, intersects, ...) has the same limit.
+// checked_right() and checked_bottom() say None instead, for rects that
+// came from somewhere that might not respect it.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Point<T = u32> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T> Point<T> {
+    pub fn new(x: T, y: T) -> Point<T> {
+        Point { x, y }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Point<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect<T = u32> {
+    pub origin: Point<T>,
+    pub size: Rectangle<T>,
+}
+
+fn min<T: PartialOrd>(a: T, b: T) -> T {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+fn max<T: PartialOrd>(a: T, b: T) -> T {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+impl<T: Num + Copy> Rectangle<T> {
+    // Puts this size somewhere.
+    pub fn at(self, x: T, y: T) -> Rect<T> {
+        Rect {
+            origin: Point::new(x, y),
+            size: self,
+        }
+    }
+}
+
+impl<T: Num + Copy + PartialOrd> Rectangle<T> {
+    // Whether `other` would fit inside this rectangle without turning it.
+    // Same-sized rectangles fit.
+    pub fn can_hold(&self, other: &Rectangle<T>) -> bool {
+        self.width >= other.width && self.height >= other.height
+    }
+}
+
+impl<T: Num + Copy> Rect<T> {
+    pub fn new(x: T, y: T, width: T, height: T) -> Rect<T> {
+        Rectangle::new(width, height).at(x, y)
+    }
+
+    // The rect from `a` up to (but not including) `b`, where `a` is the
+    // top-left corner and `b` the bottom-right one. `b` mustn't be above or
+    // left of `a` - for unsigned types that would underflow.
+    pub fn from_corners(a: Point<T>, b: Point<T>) -> Rect<T> {
+        Rect::new(a.x, a.y, b.x - a.x, b.y - a.y)
+    }
+
+    pub fn left(&self) -> T {
+        self.origin.x
+    }
+
+    pub fn top(&self) -> T {
+        self.origin.y
+    }
+
+    pub fn right(&self) -> T {
+        self.origin.x + self.size.width
+    }
+
+    pub fn bottom(&self) -> T {
+        self.origin.y + self.size.height
+    }
+
+    pub fn area(&self) -> T {
+        self.size.area()
+    }
+}
+
+impl<T: Num + Copy + CheckedAdd> Rect<T> {
+    // right() and bottom(), or None if they're past what T can hold.
+    pub fn checked_right(&self) -> Option<T> {
+        self.origin.x.checked_add(&self.size.width)
+    }
+
+    pub fn checked_bottom(&self) -> Option<T> {
+        self.origin.y.checked_add(&self.size.height)
+    }
+}
+
+impl<T: Num + Copy + PartialOrd> Rect<T> {
+    pub fn is_empty(&self) -> bool {
+        self.size.width <= T::zero() || self.size.height <= T::zero()
+    }
+
+    // Left and top edges count, right and bottom edges don't.
+    pub fn contains_point(&self, point: Point<T>) -> bool {
+        point.x >= self.left()
+            && point.x < self.right()
+            && point.y >= self.top()
+            && point.y < self.bottom()
+    }
+
+    // Whether `other` lies entirely inside this rect. Edges may line up, so
+    // a rect can hold itself.
+    pub fn can_hold(&self, other: &Rect<T>) -> bool {
+        other.left() >= self.left()
+            && other.right() <= self.right()
+            && other.top() >= self.top()
+            && other.bottom() <= self.bottom()
+    }
+
+    // Whether they share any area. Rects that only touch don't.
+    pub fn intersects(&self, other: &Rect<T>) -> bool {
+        !self.is_empty()
+            && !other.is_empty()
+            && self.left() < other.right()
+            && other.left() < self.right()
+            && self.top() < other.bottom()
+            && other.top() < self.bottom()
+    }
+
+    // The area they share, or None if they don't share any.
+    pub fn intersection(&self, other: &Rect<T>) -> Option<Rect<T>> {
+        if !self.intersects(other) {
+            return None;
+        }
+        Some(Rect::from_corners(
+            Point::new(max(self.left(), other.left()), max(self.top(), other.top())),
+            Point::new(
+                min(self.right(), other.right()),
+                min(self.bottom(), other.bottom()),
+            ),
+        ))
+    }
+
+    // The smallest rect that covers both. Empty rects don't cover anything,
+    // so they're ignored - the union with an empty rect is the other one.
+    pub fn union_bounds(&self, other: &Rect<T>) -> Rect<T> {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        Rect::from_corners(
+            Point::new(min(self.left(), other.left()), min(self.top(), other.top())),
+            Point::new(
+                max(self.right(), other.right()),
+                max(self.bottom(), other.bottom()),
+            ),
+        )
+    }
+
+    // The parts of this rect that aren't in `other`, as up to four
+    // non-overlapping rects:
+    //
+    //     +-----------------+
+    //     |       top       |
+    //     +------+---+------+
+    //     | left |   | right|
+    //     +------+---+------+
+    //     |     bottom      |
+    //     +-----------------+
+    //
+    // The top and bottom pieces take the full width. Pieces that would be
+    // empty are left out, so no overlap gives back just this rect and
+    // complete cover gives nothing.
+    pub fn difference(&self, other: &Rect<T>) -> Vec<Rect<T>> {
+        let Some(hole) = self.intersection(other) else {
+            return if self.is_empty() { vec![] } else { vec![*self] };
+        };
+        let pieces = [
+            Rect::from_corners(self.origin, Point::new(self.right(), hole.top())),
+            Rect::from_corners(
+                Point::new(self.left(), hole.bottom()),
+                Point::new(self.right(), self.bottom()),
+            ),
+            Rect::from_corners(
+                Point::new(self.left(), hole.top()),
+                Point::new(hole.left(), hole.bottom()),
+            ),
+            Rect::from_corners(
+                Point::new(hole.right(), hole.top()),
+                Point::new(self.right(), hole.bottom()),
+            ),
+        ];
+        pieces
+            .into_iter()
+            .filter(|piece| !piece.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn edges_up_to_the_limit() {
+        let rect = Rect::new(200u8, 100, 55, 155);
+        assert_eq!((rect.right(), rect.bottom()), (255, 255));
+        assert_eq!(rect.checked_right(), Some(255));
+        assert_eq!(rect.checked_bottom(), Some(255));
+        // the last pixel is inside, as usual.
+        assert!(rect.contains_point(Point::new(254, 254)));
+        assert!(!rect.contains_point(Point::new(255, 254)));
+    }
+
+    #[test]
+    fn edges_past_the_limit() {
+        let rect = Rect::new(200u8, 100, 56, 156);
+        assert_eq!(rect.checked_right(), None);
+        assert_eq!(rect.checked_bottom(), None);
+        let rect = Rect::new(i32::MIN, i32::MAX, -1, 1);
+        assert_eq!(rect.checked_right(), None);
+        assert_eq!(rect.checked_bottom(), None);
+        assert_eq!(
+            Rect::new(i32::MAX, 0, -1, 0).checked_right(),
+            Some(i32::MAX - 1)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow")]
+    #[cfg(debug_assertions)]
+    fn right_past_the_limit_panics_in_debug_builds() {
+        Rect::new(200u8, 0, 56, 1).right();
+    }
+
+    #[test]
+    fn touching_rects_do_not_intersect() {
+        let a = Rect::new(0, 0, 10, 10);
+        for b in [
+            Rect::new(10, 0, 5, 10), // beside
+            Rect::new(0, 10, 10, 5), // below
+            Rect::new(-5, 0, 5, 10), // to the left
+            Rect::new(10, 10, 5, 5), // corner to corner
+        ] {
+            assert!(!a.intersects(&b), "{b:?}");
+            assert!(!b.intersects(&a), "{b:?}");
+            assert_eq!(a.intersection(&b), None);
+            assert_eq!(a.difference(&b), [a]);
+        }
+        // one unit further in and they share a strip.
+        assert_eq!(
+            a.intersection(&Rect::new(9, 0, 5, 10)),
+            Some(Rect::new(9, 0, 1, 10))
+        );
+    }
+
+    #[test]
+    fn empty_rects_intersect_nothing() {
+        let a = Rect::new(0, 0, 10, 10);
+        let line = Rect::new(5, 0, 0, 10);
+        assert!(line.is_empty());
+        assert!(!a.intersects(&line));
+        assert!(!line.intersects(&line));
+        assert!(a.can_hold(&line));
+        assert_eq!(line.difference(&a), []);
+    }
+
+    #[test]
+    fn union_bounds_ignores_empty_rects() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(20, 5, 5, 10);
+        assert_eq!(a.union_bounds(&b), Rect::new(0, 0, 25, 15));
+        assert_eq!(b.union_bounds(&a), Rect::new(0, 0, 25, 15));
+        // far away, but empty, so it doesn't stretch anything.
+        let empty = Rect::new(100, 100, 0, 5);
+        assert_eq!(a.union_bounds(&empty), a);
+        assert_eq!(empty.union_bounds(&a), a);
+        let other_empty = Rect::new(-50, -50, 3, 0);
+        assert_eq!(empty.union_bounds(&other_empty), empty);
+    }
+
+    #[test]
+    fn difference_cases() {
+        let a = Rect::new(0, 0, 10, 10);
+        // a hole in the middle leaves a frame of four pieces.
+        assert_eq!(
+            a.difference(&Rect::new(3, 3, 4, 4)),
+            [
+                Rect::new(0, 0, 10, 3),
+                Rect::new(0, 7, 10, 3),
+                Rect::new(0, 3, 3, 4),
+                Rect::new(7, 3, 3, 4),
+            ]
+        );
+        // biting off a corner leaves two.
+        assert_eq!(
+            a.difference(&Rect::new(5, 5, 10, 10)),
+            [Rect::new(0, 0, 10, 5), Rect::new(0, 5, 5, 5)]
+        );
+        // covered completely (or exactly) leaves nothing.
+        assert_eq!(a.difference(&Rect::new(-1, -1, 12, 12)), []);
+        assert_eq!(a.difference(&a), []);
+    }
+
+    fn rect() -> impl Strategy<Value = Rect<i32>> {
+        (-20..20, -20..20, 0..25, 0..25).prop_map(|(x, y, w, h)| Rect::new(x, y, w, h))
+    }
+
+    proptest! {
+        #[test]
+        fn difference_covers_exactly_what_the_hole_does_not(a in rect(), hole in rect()) {
+            let pieces = a.difference(&hole);
+            prop_assert!(pieces.len() <= 4);
+
+            let covered = a.intersection(&hole).map_or(0, |shared| shared.area());
+            let total: i32 = pieces.iter().map(Rect::area).sum();
+            prop_assert_eq!(total, a.area() - covered);
+
+            for (i, piece) in pieces.iter().enumerate() {
+                prop_assert!(!piece.is_empty());
+                prop_assert!(a.can_hold(piece));
+                prop_assert!(!piece.intersects(&hole));
+                for other in &pieces[i + 1..] {
+                    prop_assert!(!piece.intersects(other));
+                }
+            }
+            if hole.can_hold(&a) {
+                prop_assert!(pieces.is_empty());
+            }
+        }
+
+        #[test]
+        fn intersection_is_inside_both(a in rect(), b in rect()) {
+            match a.intersection(&b) {
+                Some(shared) => {
+                    prop_assert!(!shared.is_empty());
+                    prop_assert!(a.can_hold(&shared) && b.can_hold(&shared));
+                    prop_assert_eq!(b.intersection(&a), Some(shared));
+                }
+                None => prop_assert!(!a.intersects(&b)),
+            }
+            let bounds = a.union_bounds(&b);
+            if !a.is_empty() {
+                prop_assert!(bounds.can_hold(&a));
+            }
+            if !b.is_empty() {
+                prop_assert!(bounds.can_hold(&b));
+            }
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt;
+
+use num_traits::{CheckedAdd, CheckedMul, Num, One, SaturatingAdd, SaturatingMul};
+
+// Methods are declared similarly to functions, but are defined
+// within the context of a struct, and their first parameter is always
+// "self", which represents the instance of the struct the method is being
+// called on.
+
+// We're going to update the previous "area" function we built in our
+// "playing-with-structs" exercise to be a method.
+
+// (Rectangle moved out of main into its own module so the other modules can
+// use it. It's also generic now: Rectangle<u32> is the one from the
+// examples, but Rectangle<u8>, Rectangle<i64> or Rectangle<f64> work too.
+// `T: Num` means "any number type" - Num comes from the num-traits crate and
+// says the type has 0, 1, +, -, * and /.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rectangle<T = u32> {
+    pub width: T,
+    pub height: T,
+}
+
+// OVERFLOW
+
+// `self.width * self.height` on u32 can be too big for a u32. In a debug
+// build that panics; in a release build it silently wraps around, so a
+// 65536 x 65536 rectangle has an area of 0. The checked_ methods below
+// return this error instead, the saturating_ ones stop at the biggest value
+// the type can hold, and wide_area() multiplies in a bigger type so it
+// can't overflow at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverflowError {
+    pub operation: &'static str,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the rectangle's {} is too big for its number type",
+            self.operation
+        )
+    }
+}
+
+impl std::error::Error for OverflowError {}
+
+impl<T: Num + Copy> Rectangle<T> { // declaring a method on rectangle. Everything within the impl block will be associated with Rectangle.
+    pub fn area(&self) -> T { // &self is short for self: &Self. Within an impl block, Self is an alias for the type that the impl block is for.
+        // because we always need to declare self as the first parameter, Rust allows us to just write &self for convenience.
+        // Because we're using a reference, this method just borrows Self. Methods can take ownership of self and borrow self mutably as well, just as they can with any other parameter.
+        // We will occasionally create a method that takes ownership of self, but such occurrences are rare, and are only used if we want to transform our instance into a new value and prevent our user from using the old value.
+        self.width * self.height // (can overflow - see checked_area)
+    }
+
+    pub fn perimeter(&self) -> T {
+        let two = T::one() + T::one();
+        two * (self.width + self.height)
+    }
+
+    pub fn change_height(&mut self, num: T) { // note that in order to borrow self mutably, we first have to declare self as a mutable instance.
+        self.height = self.height * num; // (can overflow - see checked_change_height)
+    }
+
+    pub fn reassign(mut self, width: T, height: T) -> Rectangle<T> {
+        self.width = width;
+        self.height = height;
+        self
+    }
+}
+
+impl<T: Num + Copy + fmt::Display> Rectangle<T> {
+    pub fn print_width(&self, message: &str) { // can write multiple methods in here.
+        println!("{}{}", message, self.width);
+    }
+}
+
+impl<T: Num + Copy + PartialOrd> Rectangle<T> {
+    pub fn greater_volume(&self, rect: &Rectangle<T>) -> bool {
+        self.area() > rect.area()
+    }
+}
+
+// Checked versions: the integer types' `*` and `+` don't tell you when
+// they overflow, but checked_mul and checked_add do.
+impl<T: Num + Copy + CheckedMul + CheckedAdd> Rectangle<T> {
+    pub fn checked_area(&self) -> Result<T, OverflowError> {
+        self.width
+            .checked_mul(&self.height)
+            .ok_or(OverflowError { operation: "area" })
+    }
+
+    pub fn checked_perimeter(&self) -> Result<T, OverflowError> {
+        let overflow = OverflowError {
+            operation: "perimeter",
+        };
+        let half = self.width.checked_add(&self.height).ok_or(overflow)?;
+        half.checked_add(&half).ok_or(overflow)
+    }
+
+    // On overflow the height is left as it was.
+    pub fn checked_change_height(&mut self, num: T) -> Result<(), OverflowError> {
+        self.height = self.height.checked_mul(&num).ok_or(OverflowError {
+            operation: "height",
+        })?;
+        Ok(())
+    }
+}
+
+impl<T: Num + Copy + SaturatingMul + SaturatingAdd> Rectangle<T> {
+    pub fn saturating_area(&self) -> T {
+        self.width.saturating_mul(&self.height)
+    }
+
+    pub fn saturating_perimeter(&self) -> T {
+        let half = self.width.saturating_add(&self.height);
+        half.saturating_add(&half)
+    }
+
+    pub fn saturating_change_height(&mut self, num: T) {
+        self.height = self.height.saturating_mul(&num);
+    }
+}
+
+// WIDENING
+
+// An n-bit number times an n-bit number always fits in 2n bits, so doing
+// the multiplication in the next size up can never overflow. Widen says what
+// "the next size up" is for each integer type.
+pub trait Widen: Copy {
+    type Wide: Num + Copy;
+    fn widen(self) -> Self::Wide;
+}
+
+macro_rules! widen {
+    ($($narrow:ty => $wide:ty),*) => {$(
+        impl Widen for $narrow {
+            type Wide = $wide;
+            fn widen(self) -> $wide {
+                self as $wide
+            }
+        }
+    )*};
+}
+
+widen!(u8 => u16, u16 => u32, u32 => u64, u64 => u128, i8 => i16, i16 => i32, i32 => i64, i64 => i128);
+
+impl<T: Widen> Rectangle<T> {
+    // Rectangle<u32> -> u64, Rectangle<u64> -> u128, and so on.
+    pub fn wide_area(&self) -> T::Wide {
+        self.width.widen() * self.height.widen()
+    }
+
+    // Even a perimeter of two u32::MAX sides fits easily: 4 * (2^32 - 1).
+    pub fn wide_perimeter(&self) -> T::Wide {
+        let two = T::Wide::one() + T::Wide::one();
+        two * (self.width.widen() + self.height.widen())
+    }
+}
+
+// ASSOCIATED FUNCTIONS
+
+// We can declare associated functions within impl blocks that are not methods. (They don't have self as parameters).
+// We invoke these associated functions by writing the struct name along with :: syntax (which is also used for namespacing created by modules, which will be discussed later).
+// Associated functions are often used as constructor functions for structs. Many are called "new", but "new" isn't a special name within rust.
+
+impl<T: Num + Copy> Rectangle<T> {
+    pub fn new(width: T, height: T) -> Self {
+        Self { width, height }
+    }
+
+    pub fn square(size: T) -> Self { // associated function. Also a constructor function.
+        Self {
+            width: size,
+            height: size,
+        }
+    }
+}
+
+impl<T: fmt::Display> Rectangle<T> {
+    pub fn width(&self) {
+        println!("called the method width, which prints the property width: self.width == {}", self.width);
+    }
+}
+
+// As shown above, we can also have multiple impl blocks. Here they're
+// useful because of generics: each block can ask for just the traits its
+// methods need, so checked_area exists for integers (which have
+// checked_mul) but not for floats (which don't).
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::PrimInt;
+    use proptest::prelude::*;
+
+    // Properties that should hold for every rectangle, checked against
+    // wide_area and wide_perimeter (which can't overflow).
+    fn check_overflow_properties<T>(rect: Rectangle<T>)
+    where
+        T: Widen + PrimInt + SaturatingMul + SaturatingAdd + fmt::Debug,
+        T::Wide: PartialOrd + From<T> + fmt::Debug,
+    {
+        let (min, max) = (T::Wide::from(T::min_value()), T::Wide::from(T::max_value()));
+        // where the saturating_ methods should stop for a true value that
+        // doesn't fit.
+        let saturated = |wide: T::Wide| {
+            if wide > max {
+                T::max_value()
+            } else {
+                T::min_value()
+            }
+        };
+
+        // checked_area fails exactly when the true area doesn't fit, and is
+        // right when it does.
+        let area = rect.wide_area();
+        match rect.checked_area() {
+            Ok(checked) => {
+                assert_eq!(T::Wide::from(checked), area, "{rect:?}");
+                assert_eq!(rect.saturating_area(), checked, "{rect:?}");
+            }
+            Err(_) => {
+                assert!(area > max || area < min, "{rect:?}");
+                assert_eq!(rect.saturating_area(), saturated(area), "{rect:?}");
+            }
+        }
+
+        // the same for the perimeter.
+        let perimeter = rect.wide_perimeter();
+        match rect.checked_perimeter() {
+            Ok(checked) => assert_eq!(T::Wide::from(checked), perimeter, "{rect:?}"),
+            Err(_) => {
+                assert!(perimeter > max || perimeter < min, "{rect:?}");
+                assert_eq!(
+                    rect.saturating_perimeter(),
+                    saturated(perimeter),
+                    "{rect:?}"
+                );
+            }
+        }
+
+        // and checked_change_height matches checked multiplication, leaving
+        // the rectangle alone when it fails.
+        let mut changed = rect;
+        let result = changed.checked_change_height(rect.width);
+        assert_eq!(
+            result.is_ok(),
+            rect.height.checked_mul(&rect.width).is_some(),
+            "{rect:?}"
+        );
+        if result.is_err() {
+            assert_eq!(changed, rect, "a failed change shouldn't change anything");
+        }
+    }
+
+    #[test]
+    fn every_u8_rectangle() {
+        for width in 0..=u8::MAX {
+            for height in 0..=u8::MAX {
+                check_overflow_properties(Rectangle::new(width, height));
+            }
+        }
+    }
+
+    #[test]
+    fn every_i8_rectangle() {
+        for width in i8::MIN..=i8::MAX {
+            for height in i8::MIN..=i8::MAX {
+                check_overflow_properties(Rectangle::new(width, height));
+            }
+        }
+    }
+
+    // Random numbers are mostly huge, so shift them right by a random
+    // amount to get small sides as well.
+    fn side<T: Arbitrary + PrimInt>() -> impl Strategy<Value = T> {
+        let bits = T::zero().count_zeros();
+        (any::<T>(), 0..bits).prop_map(|(n, shift)| n >> shift as usize)
+    }
+
+    proptest! {
+        #[test]
+        fn u32_rectangles(width in side::<u32>(), height in side::<u32>()) {
+            check_overflow_properties(Rectangle::new(width, height));
+        }
+
+        #[test]
+        fn i32_rectangles(width in side::<i32>(), height in side::<i32>()) {
+            check_overflow_properties(Rectangle::new(width, height));
+        }
+
+        #[test]
+        fn u64_rectangles(width in side::<u64>(), height in side::<u64>()) {
+            check_overflow_properties(Rectangle::new(width, height));
+        }
+    }
+
+    #[test]
+    fn edges_of_the_range() {
+        for rect in [
+            Rectangle::new(u32::MAX, 1),
+            Rectangle::new(u32::MAX, 2),
+            Rectangle::new(65_536, 65_536),
+            Rectangle::new(65_535, 65_537),
+        ] {
+            check_overflow_properties(rect);
+        }
+        for rect in [
+            Rectangle::new(i32::MIN, 1),
+            Rectangle::new(i32::MIN, -1),
+            Rectangle::new(-1, i32::MIN),
+            Rectangle::new(i32::MAX, -1),
+            Rectangle::new(i32::MIN, i32::MIN),
+        ] {
+            check_overflow_properties(rect);
+        }
+    }
+}
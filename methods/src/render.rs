@@ -0,0 +1,358 @@
+use std::fmt::Write;
+
+use num_traits::{Num, ToPrimitive};
+
+use crate::rect::{Point, Rect};
+use crate::rectangle::Rectangle;
+use crate::shape::{Circle, Polygon, Shape, Triangle};
+
+// RENDERING
+
+// dbg!(&rect1) tells us a Rectangle's numbers, but not what it looks like.
+// A Drawing is a list of shapes, each with a Style, that can be written out
+// as an SVG image (open it in a browser) or as ASCII art for the terminal:
+//
+//     let mut drawing = Drawing::new();
+//     drawing.add(&rect, Style::filled("tomato"), Some("rect"));
+//     println!("{}", drawing.to_ascii(40));
+//
+// Shapes are drawn in the order they're added, so later ones cover earlier
+// ones. The picture is just big enough to fit them all.
+//
+// The Shape trait only knows areas and points, not how to draw an outline,
+// so drawable shapes also implement Render, which writes their SVG element.
+// ASCII art doesn't need Render: it asks contains_point() about the middle
+// of every character.
+
+pub trait Render: Shape {
+    // The SVG element for this shape, with `attributes` (the style, already
+    // written out) added to it.
+    fn svg(&self, attributes: &str) -> String;
+}
+
+// The most rows to_ascii() will write, however tall the drawing is.
+pub const MAX_ASCII_ROWS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    // any SVG colour: "red", "#ff0000", "hsl(0, 100%, 50%)" or "none".
+    pub fill: String,
+    pub stroke: String,
+    pub stroke_width: f64,
+    // what the shape is filled with in ASCII art.
+    pub ascii: char,
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style {
+            fill: String::from("#dddddd"),
+            stroke: String::from("#333333"),
+            stroke_width: 1.0,
+            ascii: '#',
+        }
+    }
+}
+
+impl Style {
+    pub fn filled(fill: &str) -> Style {
+        Style {
+            fill: String::from(fill),
+            ..Style::default()
+        }
+    }
+}
+
+struct Item<'a> {
+    shape: &'a dyn Render,
+    style: Style,
+    label: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Drawing<'a> {
+    items: Vec<Item<'a>>,
+}
+
+impl<'a> Drawing<'a> {
+    pub fn new() -> Drawing<'a> {
+        Drawing::default()
+    }
+
+    // A label is written in the middle (the centroid) of the shape. It's
+    // only shown in SVG - there's no room for words in ASCII art.
+    pub fn add(&mut self, shape: &'a dyn Render, style: Style, label: Option<&str>) -> &mut Self {
+        self.items.push(Item {
+            shape,
+            style,
+            label: label.map(String::from),
+        });
+        self
+    }
+
+    // Everything that gets drawn. Empty if there's nothing to draw.
+    pub fn bounds(&self) -> Rect<f64> {
+        self.items
+            .iter()
+            .map(|item| item.shape.bounding_box())
+            .fold(Rect::default(), |bounds, shape| bounds.union_bounds(&shape))
+    }
+
+    pub fn to_svg(&self) -> String {
+        // leave room for half of the widest stroke outside the shapes.
+        let margin = self
+            .items
+            .iter()
+            .map(|item| item.style.stroke_width / 2.0)
+            .fold(0.0, f64::max);
+        let bounds = self.bounds();
+        let (x, y) = (bounds.left() - margin, bounds.top() - margin);
+        let (width, height) = (
+            bounds.size.width + 2.0 * margin,
+            bounds.size.height + 2.0 * margin,
+        );
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"{x} {y} {width} {height}\">\n"
+        );
+        for item in &self.items {
+            let Style {
+                fill,
+                stroke,
+                stroke_width,
+                ..
+            } = &item.style;
+            let attributes = format!(
+                "fill=\"{}\" stroke=\"{}\" stroke-width=\"{stroke_width}\"",
+                escape(fill),
+                escape(stroke)
+            );
+            svg.push_str("  ");
+            svg.push_str(&item.shape.svg(&attributes));
+            svg.push('\n');
+        }
+        // labels go on top of every shape, so none of them get covered.
+        for item in &self.items {
+            if let Some(label) = &item.label {
+                let Point { x, y } = item.shape.centroid();
+                let _ = writeln!(
+                    svg,
+                    "  <text x=\"{x}\" y=\"{y}\" text-anchor=\"middle\" dominant-baseline=\"middle\" font-family=\"monospace\">{}</text>",
+                    escape(label)
+                );
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    // The drawing squeezed or stretched to `columns` characters wide. A
+    // terminal character is about twice as tall as it's wide, so each row
+    // covers twice the height that each column covers width, which keeps
+    // squares looking square. Blank space is ' ', trailing spaces are
+    // trimmed, and every row ends in '\n'.
+    //
+    // A drawing much taller than it's wide would need a row for every bit
+    // of its height (a 1 x 1e9 rect at 80 columns is 40 billion rows), so
+    // past MAX_ASCII_ROWS the rows are stretched to cover more height
+    // instead, and the picture comes out squashed.
+    pub fn to_ascii(&self, columns: usize) -> String {
+        let bounds = self.bounds();
+        if bounds.is_empty() || columns == 0 {
+            return String::new();
+        }
+        let cell_width = bounds.size.width / columns as f64;
+        let mut cell_height = cell_width * 2.0;
+        let mut rows = (bounds.size.height / cell_height).ceil() as usize;
+        if rows > MAX_ASCII_ROWS {
+            rows = MAX_ASCII_ROWS;
+            cell_height = bounds.size.height / rows as f64;
+        }
+
+        let mut text = String::new();
+        for row in 0..rows {
+            let mut line = String::new();
+            for column in 0..columns {
+                let middle = Point::new(
+                    bounds.left() + (column as f64 + 0.5) * cell_width,
+                    bounds.top() + (row as f64 + 0.5) * cell_height,
+                );
+                // the last shape added is the one on top.
+                let on_top = self
+                    .items
+                    .iter()
+                    .rev()
+                    .find(|item| item.shape.contains_point(middle));
+                line.push(on_top.map_or(' ', |item| item.style.ascii));
+            }
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+// Labels and colours can contain characters that mean something in SVG.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn polygon_svg(points: &[Point<f64>], attributes: &str) -> String {
+    let points: Vec<String> = points.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+    format!("<polygon points=\"{}\" {attributes}/>", points.join(" "))
+}
+
+impl<T: Num + Copy + PartialOrd + ToPrimitive> Render for Rect<T> {
+    fn svg(&self, attributes: &str) -> String {
+        let Rect { origin, size } = self.bounding_box();
+        format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {attributes}/>",
+            origin.x, origin.y, size.width, size.height
+        )
+    }
+}
+
+impl<T: Num + Copy + PartialOrd + ToPrimitive> Render for Rectangle<T> {
+    fn svg(&self, attributes: &str) -> String {
+        self.bounding_box().svg(attributes)
+    }
+}
+
+impl Render for Circle {
+    fn svg(&self, attributes: &str) -> String {
+        format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {attributes}/>",
+            self.center.x, self.center.y, self.radius
+        )
+    }
+}
+
+impl Render for Triangle {
+    fn svg(&self, attributes: &str) -> String {
+        polygon_svg(&[self.a, self.b, self.c], attributes)
+    }
+}
+
+impl Render for Polygon {
+    fn svg(&self, attributes: &str) -> String {
+        polygon_svg(self.points(), attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(ascii: char) -> Style {
+        Style {
+            ascii,
+            ..Style::default()
+        }
+    }
+
+    #[test]
+    fn empty_drawings_draw_nothing() {
+        let drawing = Drawing::new();
+        assert_eq!(drawing.bounds(), Rect::default());
+        assert_eq!(drawing.to_ascii(40), "");
+        assert_eq!(
+            drawing.to_svg(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"0\" height=\"0\" viewBox=\"0 0 0 0\">\n</svg>\n"
+        );
+        // nor does an empty shape, or no columns.
+        let line = Rect::new(0, 0, 10, 0);
+        let mut drawing = Drawing::new();
+        drawing.add(&line, Style::default(), None);
+        assert_eq!(drawing.to_ascii(40), "");
+        let rect = Rect::new(0, 0, 10, 10);
+        drawing.add(&rect, Style::default(), None);
+        assert_eq!(drawing.to_ascii(0), "");
+    }
+
+    #[test]
+    fn ascii_rect() {
+        let rect = Rect::new(0, 0, 8, 4);
+        let mut drawing = Drawing::new();
+        drawing.add(&rect, style('#'), None);
+        // rows are twice as tall as columns are wide.
+        assert_eq!(drawing.to_ascii(8), "########\n########\n");
+        assert_eq!(drawing.to_ascii(4), "####\n");
+    }
+
+    #[test]
+    fn ascii_circle_over_a_rect() {
+        let rect = Rect::new(0, 0, 12, 6);
+        let circle = Circle::new(Point::new(12.0, 6.0), 4.0).unwrap();
+        let mut drawing = Drawing::new();
+        drawing
+            .add(&rect, style('#'), None)
+            .add(&circle, style('o'), None);
+        assert_eq!(drawing.bounds(), Rect::new(0.0, 0.0, 16.0, 10.0));
+        assert_eq!(
+            drawing.to_ascii(16),
+            concat!(
+                "############\n",
+                "#########oooooo\n",
+                "########oooooooo\n",
+                "        oooooooo\n",
+                "         oooooo\n",
+            )
+        );
+    }
+
+    #[test]
+    fn tall_drawings_are_squashed() {
+        let tall = Rect::new(0.0, 0.0, 1.0, 1e9);
+        let mut drawing = Drawing::new();
+        drawing.add(&tall, style('|'), None);
+        let text = drawing.to_ascii(80);
+        assert_eq!(text.lines().count(), MAX_ASCII_ROWS);
+        assert!(text.lines().all(|line| line == "|".repeat(80)));
+    }
+
+    #[test]
+    fn svg_elements_and_labels() {
+        let rect = Rect::new(0, 0, 20, 10);
+        let triangle = Triangle::new(
+            Point::new(0.0, 0.0),
+            Point::new(6.0, 0.0),
+            Point::new(0.0, 3.0),
+        );
+        let mut drawing = Drawing::new();
+        drawing
+            .add(&rect, Style::filled("tomato"), Some("<a & b>"))
+            .add(
+                &triangle,
+                Style {
+                    stroke: String::from("say \"hi\""),
+                    stroke_width: 2.0,
+                    ..Style::default()
+                },
+                None,
+            );
+        let svg = drawing.to_svg();
+        let lines: Vec<&str> = svg.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                // a margin of half the widest stroke all round.
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"22\" height=\"12\" viewBox=\"-1 -1 22 12\">",
+                "  <rect x=\"0\" y=\"0\" width=\"20\" height=\"10\" fill=\"tomato\" stroke=\"#333333\" stroke-width=\"1\"/>",
+                "  <polygon points=\"0,0 6,0 0,3\" fill=\"#dddddd\" stroke=\"say &quot;hi&quot;\" stroke-width=\"2\"/>",
+                "  <text x=\"10\" y=\"5\" text-anchor=\"middle\" dominant-baseline=\"middle\" font-family=\"monospace\">&lt;a &amp; b&gt;</text>",
+                "</svg>",
+            ]
+        );
+    }
+
+    #[test]
+    fn escape_everything_svg_cares_about() {
+        assert_eq!(escape(r#"<"a" & 'b'>"#), "&lt;&quot;a&quot; &amp; 'b'&gt;");
+        // & goes first, so the others aren't escaped twice.
+        assert_eq!(escape("&lt;"), "&amp;lt;");
+        assert_eq!(escape("plain"), "plain");
+    }
+}
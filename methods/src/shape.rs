@@ -0,0 +1,503 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use num_traits::{Num, ToPrimitive};
+
+use crate::rect::{Point, Rect};
+use crate::rectangle::Rectangle;
+
+// SHAPES
+
+// Rectangle's methods belong to Rectangle alone - a function can't take "a
+// Rectangle or a circle". A trait is a list of methods that many types can
+// provide, and `&dyn Shape` means "any type that implements Shape", picked
+// at runtime:
+//
+//     fn describe(shape: &dyn Shape) { println!("{}", shape.area()) }
+//
+// Everything here works in f64. Circles need pi, and centroids of whole-
+// number shapes aren't whole numbers, so a Rect<u32> converts its numbers
+// when it's used as a Shape.
+//
+// Boundaries: points exactly on the edge of a circle, triangle or polygon
+// count as inside. Rects keep their own rule (left and top edges in, right
+// and bottom out - see rect.rs), so that rects that tile a space still
+// never share a point.
+
+pub trait Shape {
+    fn name(&self) -> &'static str;
+    fn area(&self) -> f64;
+    fn perimeter(&self) -> f64;
+    // The smallest Rect that covers the whole shape.
+    fn bounding_box(&self) -> Rect<f64>;
+    // The centre of mass, if the shape were cut out of card.
+    fn centroid(&self) -> Point<f64>;
+    fn contains_point(&self, point: Point<f64>) -> bool;
+}
+
+fn to_f64<T: ToPrimitive>(n: T) -> f64 {
+    // always Some for the built-in number types.
+    n.to_f64().unwrap_or(f64::NAN)
+}
+
+fn distance(a: Point<f64>, b: Point<f64>) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+// Twice the signed area of the triangle a, b, c: positive if the points go
+// clockwise on screen (y grows downwards), negative if anticlockwise, and
+// 0 if they're in a line.
+fn cross(a: Point<f64>, b: Point<f64>, c: Point<f64>) -> f64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+// Whether p lies on the segment from a to b.
+fn on_segment(p: Point<f64>, a: Point<f64>, b: Point<f64>) -> bool {
+    cross(a, b, p).abs() <= f64::EPSILON * (1.0 + distance(a, b))
+        && p.x >= a.x.min(b.x)
+        && p.x <= a.x.max(b.x)
+        && p.y >= a.y.min(b.y)
+        && p.y <= a.y.max(b.y)
+}
+
+fn bounds_of(points: &[Point<f64>]) -> Rect<f64> {
+    let (mut left, mut top) = (f64::INFINITY, f64::INFINITY);
+    let (mut right, mut bottom) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for p in points {
+        left = left.min(p.x);
+        top = top.min(p.y);
+        right = right.max(p.x);
+        bottom = bottom.max(p.y);
+    }
+    Rect::from_corners(Point::new(left, top), Point::new(right, bottom))
+}
+
+// RECTANGLES
+
+impl<T: Num + Copy + PartialOrd + ToPrimitive> Shape for Rect<T> {
+    fn name(&self) -> &'static str {
+        "rectangle"
+    }
+
+    fn area(&self) -> f64 {
+        to_f64(self.size.width) * to_f64(self.size.height)
+    }
+
+    fn perimeter(&self) -> f64 {
+        2.0 * (to_f64(self.size.width) + to_f64(self.size.height))
+    }
+
+    fn bounding_box(&self) -> Rect<f64> {
+        Rect::new(
+            to_f64(self.origin.x),
+            to_f64(self.origin.y),
+            to_f64(self.size.width),
+            to_f64(self.size.height),
+        )
+    }
+
+    fn centroid(&self) -> Point<f64> {
+        let bounds = self.bounding_box();
+        Point::new(
+            bounds.left() + bounds.size.width / 2.0,
+            bounds.top() + bounds.size.height / 2.0,
+        )
+    }
+
+    fn contains_point(&self, point: Point<f64>) -> bool {
+        Rect::contains_point(&self.bounding_box(), point)
+    }
+}
+
+// A Rectangle has no position, so as a Shape it sits at (0, 0).
+impl<T: Num + Copy + PartialOrd + ToPrimitive> Shape for Rectangle<T> {
+    fn name(&self) -> &'static str {
+        "rectangle"
+    }
+
+    fn area(&self) -> f64 {
+        Shape::area(&self.at(T::zero(), T::zero()))
+    }
+
+    fn perimeter(&self) -> f64 {
+        Shape::perimeter(&self.at(T::zero(), T::zero()))
+    }
+
+    fn bounding_box(&self) -> Rect<f64> {
+        self.at(T::zero(), T::zero()).bounding_box()
+    }
+
+    fn centroid(&self) -> Point<f64> {
+        self.at(T::zero(), T::zero()).centroid()
+    }
+
+    fn contains_point(&self, point: Point<f64>) -> bool {
+        Shape::contains_point(&self.at(T::zero(), T::zero()), point)
+    }
+}
+
+// CIRCLES
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    pub center: Point<f64>,
+    pub radius: f64,
+}
+
+// The radius has to be a real distance: 0 or more, and not NaN or
+// infinity. (0 is allowed - it's a single point.)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadiusError(pub f64);
+
+impl fmt::Display for RadiusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a circle's radius must be a finite number of 0 or more, not {}",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for RadiusError {}
+
+impl Circle {
+    pub fn new(center: Point<f64>, radius: f64) -> Result<Circle, RadiusError> {
+        if !(radius.is_finite() && radius >= 0.0) {
+            return Err(RadiusError(radius));
+        }
+        Ok(Circle { center, radius })
+    }
+}
+
+impl Shape for Circle {
+    fn name(&self) -> &'static str {
+        "circle"
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    fn perimeter(&self) -> f64 {
+        2.0 * PI * self.radius
+    }
+
+    fn bounding_box(&self) -> Rect<f64> {
+        let diameter = 2.0 * self.radius;
+        Rect::new(
+            self.center.x - self.radius,
+            self.center.y - self.radius,
+            diameter,
+            diameter,
+        )
+    }
+
+    fn centroid(&self) -> Point<f64> {
+        self.center
+    }
+
+    fn contains_point(&self, point: Point<f64>) -> bool {
+        distance(self.center, point) <= self.radius
+    }
+}
+
+// TRIANGLES
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    pub a: Point<f64>,
+    pub b: Point<f64>,
+    pub c: Point<f64>,
+}
+
+impl Triangle {
+    pub fn new(a: Point<f64>, b: Point<f64>, c: Point<f64>) -> Triangle {
+        Triangle { a, b, c }
+    }
+}
+
+impl Shape for Triangle {
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+
+    fn area(&self) -> f64 {
+        cross(self.a, self.b, self.c).abs() / 2.0
+    }
+
+    fn perimeter(&self) -> f64 {
+        distance(self.a, self.b) + distance(self.b, self.c) + distance(self.c, self.a)
+    }
+
+    fn bounding_box(&self) -> Rect<f64> {
+        bounds_of(&[self.a, self.b, self.c])
+    }
+
+    fn centroid(&self) -> Point<f64> {
+        Point::new(
+            (self.a.x + self.b.x + self.c.x) / 3.0,
+            (self.a.y + self.b.y + self.c.y) / 3.0,
+        )
+    }
+
+    // Inside means on the same side of all three edges (or on one). A
+    // triangle with its corners in a line has no inside: every point on
+    // that line would be "on both sides", so it contains nothing.
+    fn contains_point(&self, point: Point<f64>) -> bool {
+        if cross(self.a, self.b, self.c) == 0.0 {
+            return false;
+        }
+        let sides = [
+            cross(self.a, self.b, point),
+            cross(self.b, self.c, point),
+            cross(self.c, self.a, point),
+        ];
+        let no_negative = sides.iter().all(|&side| side >= 0.0);
+        let no_positive = sides.iter().all(|&side| side <= 0.0);
+        no_negative || no_positive
+    }
+}
+
+// POLYGONS
+
+// Any "simple" polygon: its edges join the points in order and back to the
+// first, and never cross each other. It doesn't have to be convex.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    points: Vec<Point<f64>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonError {
+    TooFewPoints(usize),
+    // the edges starting at these two points cross.
+    SelfIntersecting(usize, usize),
+    ZeroArea,
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolygonError::TooFewPoints(n) => {
+                write!(f, "a polygon needs at least 3 points, not {n}")
+            }
+            PolygonError::SelfIntersecting(i, j) => {
+                write!(f, "the edges from point {i} and point {j} cross")
+            }
+            PolygonError::ZeroArea => write!(f, "all the points are in a line"),
+        }
+    }
+}
+
+impl std::error::Error for PolygonError {}
+
+// Whether segments p1-p2 and q1-q2 touch or cross.
+fn segments_meet(p1: Point<f64>, p2: Point<f64>, q1: Point<f64>, q2: Point<f64>) -> bool {
+    let d1 = cross(q1, q2, p1);
+    let d2 = cross(q1, q2, p2);
+    let d3 = cross(p1, p2, q1);
+    let d4 = cross(p1, p2, q2);
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+    {
+        return true;
+    }
+    on_segment(p1, q1, q2)
+        || on_segment(p2, q1, q2)
+        || on_segment(q1, p1, p2)
+        || on_segment(q2, p1, p2)
+}
+
+impl Polygon {
+    // Checks every pair of edges that aren't neighbours, so it's O(n^2) -
+    // fine for the handful of points a hand-drawn polygon has.
+    pub fn new(points: Vec<Point<f64>>) -> Result<Polygon, PolygonError> {
+        let n = points.len();
+        if n < 3 {
+            return Err(PolygonError::TooFewPoints(n));
+        }
+        for i in 0..n {
+            for j in i + 1..n {
+                let neighbours = j == i + 1 || (i == 0 && j == n - 1);
+                if neighbours {
+                    continue;
+                }
+                let (p1, p2) = (points[i], points[(i + 1) % n]);
+                let (q1, q2) = (points[j], points[(j + 1) % n]);
+                if segments_meet(p1, p2, q1, q2) {
+                    return Err(PolygonError::SelfIntersecting(i, j));
+                }
+            }
+        }
+        let polygon = Polygon { points };
+        if polygon.signed_area() == 0.0 {
+            return Err(PolygonError::ZeroArea);
+        }
+        Ok(polygon)
+    }
+
+    pub fn points(&self) -> &[Point<f64>] {
+        &self.points
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point<f64>, Point<f64>)> + '_ {
+        let next = self.points.iter().cycle().skip(1);
+        self.points.iter().copied().zip(next.copied())
+    }
+
+    // The shoelace formula: add up the cross products of each edge's two
+    // ends. Positive if the points go clockwise on screen.
+    fn signed_area(&self) -> f64 {
+        self.edges()
+            .map(|(p, q)| p.x * q.y - q.x * p.y)
+            .sum::<f64>()
+            / 2.0
+    }
+
+    // How many times the outline goes round `point`: 0 means outside.
+    // Each edge that crosses the horizontal line through the point counts
+    // +1 going one way and -1 going the other.
+    fn winding_number(&self, point: Point<f64>) -> i32 {
+        let mut winding = 0;
+        for (p, q) in self.edges() {
+            if p.y <= point.y {
+                if q.y > point.y && cross(p, q, point) > 0.0 {
+                    winding += 1;
+                }
+            } else if q.y <= point.y && cross(p, q, point) < 0.0 {
+                winding -= 1;
+            }
+        }
+        winding
+    }
+}
+
+impl Shape for Polygon {
+    fn name(&self) -> &'static str {
+        "polygon"
+    }
+
+    fn area(&self) -> f64 {
+        self.signed_area().abs()
+    }
+
+    fn perimeter(&self) -> f64 {
+        self.edges().map(|(p, q)| distance(p, q)).sum()
+    }
+
+    fn bounding_box(&self) -> Rect<f64> {
+        bounds_of(&self.points)
+    }
+
+    fn centroid(&self) -> Point<f64> {
+        let area = self.signed_area();
+        let (mut x, mut y) = (0.0, 0.0);
+        for (p, q) in self.edges() {
+            let step = p.x * q.y - q.x * p.y;
+            x += (p.x + q.x) * step;
+            y += (p.y + q.y) * step;
+        }
+        Point::new(x / (6.0 * area), y / (6.0 * area))
+    }
+
+    fn contains_point(&self, point: Point<f64>) -> bool {
+        self.edges().any(|(p, q)| on_segment(point, p, q)) || self.winding_number(point) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: f64, y: f64) -> Point<f64> {
+        Point::new(x, y)
+    }
+
+    #[test]
+    fn circle_radius_must_be_a_real_distance() {
+        assert!(Circle::new(p(0.0, 0.0), 1.0).is_ok());
+        assert!(Circle::new(p(0.0, 0.0), 0.0).is_ok());
+        for radius in [-1.0, -0.0001, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(Circle::new(p(0.0, 0.0), radius).is_err(), "{radius}");
+        }
+    }
+
+    #[test]
+    fn circle_measurements() {
+        let circle = Circle::new(p(1.0, 2.0), 2.0).unwrap();
+        assert_eq!(circle.area(), 4.0 * PI);
+        assert_eq!(circle.perimeter(), 4.0 * PI);
+        assert_eq!(circle.bounding_box(), Rect::new(-1.0, 0.0, 4.0, 4.0));
+        assert!(circle.contains_point(p(3.0, 2.0))); // on the edge
+        assert!(!circle.contains_point(p(3.0, 3.0)));
+    }
+
+    #[test]
+    fn triangle_contains_its_inside_and_edges() {
+        let triangle = Triangle::new(p(0.0, 0.0), p(4.0, 0.0), p(0.0, 3.0));
+        assert_eq!(triangle.area(), 6.0);
+        assert_eq!(triangle.perimeter(), 12.0);
+        assert!(triangle.contains_point(p(1.0, 1.0)));
+        assert!(triangle.contains_point(p(2.0, 0.0))); // on an edge
+        assert!(triangle.contains_point(p(4.0, 0.0))); // a corner
+        assert!(!triangle.contains_point(p(3.0, 3.0)));
+        // the same triangle the other way round.
+        let reversed = Triangle::new(p(0.0, 3.0), p(4.0, 0.0), p(0.0, 0.0));
+        assert!(reversed.contains_point(p(1.0, 1.0)));
+    }
+
+    #[test]
+    fn degenerate_triangles_contain_nothing() {
+        let line = Triangle::new(p(0.0, 0.0), p(1.0, 1.0), p(2.0, 2.0));
+        assert_eq!(line.area(), 0.0);
+        for point in [
+            p(0.5, 0.5),
+            p(1.0, 1.0),
+            p(5.0, 5.0),
+            p(0.0, 0.0),
+            p(1.0, 0.0),
+        ] {
+            assert!(!line.contains_point(point), "{point}");
+        }
+        let dot = Triangle::new(p(1.0, 1.0), p(1.0, 1.0), p(1.0, 1.0));
+        assert!(!dot.contains_point(p(1.0, 1.0)));
+    }
+
+    #[test]
+    fn polygons_are_checked() {
+        assert_eq!(
+            Polygon::new(vec![p(0.0, 0.0), p(1.0, 1.0)]),
+            Err(PolygonError::TooFewPoints(2))
+        );
+        assert_eq!(
+            Polygon::new(vec![p(0.0, 0.0), p(2.0, 2.0), p(2.0, 0.0), p(0.0, 2.0)]),
+            Err(PolygonError::SelfIntersecting(0, 2))
+        );
+        assert_eq!(
+            Polygon::new(vec![p(0.0, 0.0), p(1.0, 1.0), p(2.0, 2.0)]),
+            Err(PolygonError::ZeroArea)
+        );
+    }
+
+    #[test]
+    fn l_shaped_polygon() {
+        let l = Polygon::new(vec![
+            p(0.0, 0.0),
+            p(2.0, 0.0),
+            p(2.0, 1.0),
+            p(1.0, 1.0),
+            p(1.0, 2.0),
+            p(0.0, 2.0),
+        ])
+        .unwrap();
+        assert_eq!(l.area(), 3.0);
+        assert_eq!(l.perimeter(), 8.0);
+        assert!(l.contains_point(p(0.5, 1.5)));
+        assert!(l.contains_point(p(1.0, 1.5))); // on the inner edge
+        assert!(!l.contains_point(p(1.5, 1.5))); // in the notch
+        let centroid = l.centroid();
+        assert!((centroid.x - 5.0 / 6.0).abs() < 1e-12);
+        assert!((centroid.y - 5.0 / 6.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,397 @@
+use std::cmp::Ordering;
+
+use num_traits::{Num, ToPrimitive};
+
+use crate::rect::{Point, Rect};
+
+// SPATIAL INDEX
+
+// With thousands of rects, "which rects contain this point?" means testing
+// every one of them. A quadtree cuts the world into four quarters, each
+// quarter into four more, and so on, and files each rect in the smallest
+// quarter that holds it whole. A query then only looks inside the quarters
+// it touches - most of the tree is skipped.
+//
+//     +-------+-------+
+//     |       |   |   |
+//     |       +---+---+      a node splits into four once it holds
+//     |       |   | x |      more than MAX_ITEMS rects
+//     +-------+---+---+
+//     |       |       |
+//     |       |       |
+//     +-------+-------+
+//
+// A rect that straddles a dividing line stays in the bigger node above it,
+// and one that's outside the world's bounds altogether stays in the root,
+// so every rect can be inserted - it's just slower to find if lots of them
+// are like that.
+//
+// insert() hands back an ItemId to refer to the rect later (to get or
+// remove it); queries return ItemIds too.
+
+const MAX_ITEMS: usize = 8;
+const MAX_DEPTH: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ItemId(usize);
+
+#[derive(Debug)]
+struct Node<T> {
+    bounds: Rect<T>,
+    items: Vec<ItemId>,
+    children: Option<Box<[Node<T>; 4]>>,
+}
+
+#[derive(Debug)]
+pub struct QuadTree<T = u32> {
+    root: Node<T>,
+    // every rect ever inserted, by ItemId; None once it's removed.
+    rects: Vec<Option<Rect<T>>>,
+    len: usize,
+}
+
+fn to_f64<T: ToPrimitive>(n: T) -> f64 {
+    n.to_f64().unwrap_or(f64::NAN)
+}
+
+// How far `point` is from the nearest part of `rect` (0 if it's inside).
+pub fn distance_to<T>(rect: &Rect<T>, point: Point<T>) -> f64
+where
+    T: Num + Copy + ToPrimitive,
+{
+    let (x, y) = (to_f64(point.x), to_f64(point.y));
+    let dx = (to_f64(rect.left()) - x)
+        .max(x - to_f64(rect.right()))
+        .max(0.0);
+    let dy = (to_f64(rect.top()) - y)
+        .max(y - to_f64(rect.bottom()))
+        .max(0.0);
+    dx.hypot(dy)
+}
+
+impl<T: Num + Copy + PartialOrd + ToPrimitive> Node<T> {
+    fn new(bounds: Rect<T>) -> Node<T> {
+        Node {
+            bounds,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+
+    // The four quarters. With whole numbers a 5-wide node splits 2 + 3.
+    fn quarters(&self) -> Option<[Rect<T>; 4]> {
+        let two = T::one() + T::one();
+        let Rect { origin, size } = self.bounds;
+        let (left_width, top_height) = (size.width / two, size.height / two);
+        if left_width == T::zero() || top_height == T::zero() {
+            return None; // too small to split any more
+        }
+        let (right_width, bottom_height) = (size.width - left_width, size.height - top_height);
+        let (middle_x, middle_y) = (origin.x + left_width, origin.y + top_height);
+        Some([
+            Rect::new(origin.x, origin.y, left_width, top_height),
+            Rect::new(middle_x, origin.y, right_width, top_height),
+            Rect::new(origin.x, middle_y, left_width, bottom_height),
+            Rect::new(middle_x, middle_y, right_width, bottom_height),
+        ])
+    }
+
+    // Which child would hold `rect` whole, if any.
+    fn child_for(&self, rect: &Rect<T>) -> Option<usize> {
+        self.children
+            .as_ref()?
+            .iter()
+            .position(|child| child.bounds.can_hold(rect))
+    }
+
+    fn insert(&mut self, id: ItemId, rects: &[Option<Rect<T>>], depth: usize) {
+        let rect = rects[id.0].expect("inserting a removed rect");
+        if let Some(i) = self.child_for(&rect) {
+            if let Some(children) = self.children.as_mut() {
+                return children[i].insert(id, rects, depth + 1);
+            }
+        }
+        self.items.push(id);
+        if self.children.is_none() && self.items.len() > MAX_ITEMS && depth < MAX_DEPTH {
+            self.split(rects, depth);
+        }
+    }
+
+    fn split(&mut self, rects: &[Option<Rect<T>>], depth: usize) {
+        let Some(quarters) = self.quarters() else {
+            return;
+        };
+        self.children = Some(Box::new(quarters.map(Node::new)));
+        // move down every rect that now fits in a child.
+        for id in std::mem::take(&mut self.items) {
+            self.insert(id, rects, depth);
+        }
+    }
+
+    fn remove(&mut self, id: ItemId, rect: &Rect<T>) -> bool {
+        if let Some(i) = self.child_for(rect) {
+            if let Some(children) = self.children.as_mut() {
+                return children[i].remove(id, rect);
+            }
+        }
+        match self.items.iter().position(|&item| item == id) {
+            Some(index) => {
+                self.items.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn containing(&self, point: Point<T>, rects: &[Option<Rect<T>>], found: &mut Vec<ItemId>) {
+        found.extend(
+            self.items
+                .iter()
+                .filter(|id| rects[id.0].is_some_and(|rect| rect.contains_point(point))),
+        );
+        for child in self.children.iter().flat_map(|children| children.iter()) {
+            if child.bounds.contains_point(point) {
+                child.containing(point, rects, found);
+            }
+        }
+    }
+
+    fn intersecting(&self, region: &Rect<T>, rects: &[Option<Rect<T>>], found: &mut Vec<ItemId>) {
+        found.extend(
+            self.items
+                .iter()
+                .filter(|id| rects[id.0].is_some_and(|rect| rect.intersects(region))),
+        );
+        for child in self.children.iter().flat_map(|children| children.iter()) {
+            if child.bounds.intersects(region) {
+                child.intersecting(region, rects, found);
+            }
+        }
+    }
+
+    fn nearest(
+        &self,
+        point: Point<T>,
+        rects: &[Option<Rect<T>>],
+        best: &mut Option<(ItemId, f64)>,
+    ) {
+        for &id in &self.items {
+            if let Some(rect) = rects[id.0] {
+                let distance = distance_to(&rect, point);
+                if best.is_none_or(|(_, best)| distance < best) {
+                    *best = Some((id, distance));
+                }
+            }
+        }
+        let Some(children) = self.children.as_ref() else {
+            return;
+        };
+        // closest quarters first, so `best` shrinks quickly and the far
+        // quarters can be skipped.
+        let mut order: Vec<(f64, &Node<T>)> = children
+            .iter()
+            .map(|child| (distance_to(&child.bounds, point), child))
+            .collect();
+        order.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        for (distance, child) in order {
+            if best.is_none_or(|(_, best)| distance < best) {
+                child.nearest(point, rects, best);
+            }
+        }
+    }
+}
+
+impl<T: Num + Copy + PartialOrd + ToPrimitive> QuadTree<T> {
+    // `bounds` is the area most rects will be in - it's what gets divided
+    // up. Rects outside it still work, they're just not divided.
+    pub fn new(bounds: Rect<T>) -> QuadTree<T> {
+        QuadTree {
+            root: Node::new(bounds),
+            rects: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, rect: Rect<T>) -> ItemId {
+        let id = ItemId(self.rects.len());
+        self.rects.push(Some(rect));
+        self.root.insert(id, &self.rects, 0);
+        self.len += 1;
+        id
+    }
+
+    // The removed rect, or None if there's no such item (any more).
+    // Nodes aren't merged back together after removals.
+    pub fn remove(&mut self, id: ItemId) -> Option<Rect<T>> {
+        let rect = self.rects.get(id.0).copied().flatten()?;
+        self.root.remove(id, &rect);
+        self.rects[id.0] = None;
+        self.len -= 1;
+        Some(rect)
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<&Rect<T>> {
+        self.rects.get(id.0)?.as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Every rect containing `point` (same edge rules as
+    // Rect::contains_point), in no particular order.
+    pub fn containing(&self, point: Point<T>) -> Vec<ItemId> {
+        let mut found = Vec::new();
+        self.root.containing(point, &self.rects, &mut found);
+        found
+    }
+
+    // Every rect sharing some area with `region` (touching doesn't count).
+    pub fn intersecting(&self, region: &Rect<T>) -> Vec<ItemId> {
+        let mut found = Vec::new();
+        self.root.intersecting(region, &self.rects, &mut found);
+        found
+    }
+
+    // The rect closest to `point` and how far away it is - 0 if the point
+    // is inside it. Ties go to whichever is found first.
+    pub fn nearest(&self, point: Point<T>) -> Option<(ItemId, f64)> {
+        let mut best = None;
+        self.root.nearest(point, &self.rects, &mut best);
+        best
+    }
+
+    // How many levels the tree has grown to (1 if it's never split).
+    pub fn depth(&self) -> usize {
+        fn depth<T>(node: &Node<T>) -> usize {
+            1 + node
+                .children
+                .iter()
+                .flat_map(|children| children.iter())
+                .map(depth)
+                .max()
+                .unwrap_or(0)
+        }
+        depth(&self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Rects scattered over (and a little beyond) a 1,000 x 1,000 world, so
+    // some straddle its edges or sit outside it altogether.
+    fn rects() -> impl Strategy<Value = Vec<Rect<u32>>> {
+        let rect = (0..1_100u32, 0..1_100u32, 0..150u32, 0..150u32)
+            .prop_map(|(x, y, width, height)| Rect::new(x, y, width, height));
+        prop::collection::vec(rect, 0..300)
+    }
+
+    fn points() -> impl Strategy<Value = Vec<Point<u32>>> {
+        prop::collection::vec(
+            (0..1_200u32, 0..1_200u32).prop_map(|(x, y)| Point::new(x, y)),
+            1..30,
+        )
+    }
+
+    fn tree_of(rects: &[Rect<u32>]) -> (QuadTree<u32>, Vec<(ItemId, Rect<u32>)>) {
+        let mut tree = QuadTree::new(Rect::new(0, 0, 1_000, 1_000));
+        let all = rects
+            .iter()
+            .map(|&rect| (tree.insert(rect), rect))
+            .collect();
+        (tree, all)
+    }
+
+    fn sorted(mut ids: Vec<ItemId>) -> Vec<ItemId> {
+        ids.sort();
+        ids
+    }
+
+    // The answer from checking every rect, which the tree has to match.
+    fn scan(all: &[(ItemId, Rect<u32>)], keep: impl Fn(&Rect<u32>) -> bool) -> Vec<ItemId> {
+        all.iter()
+            .filter(|(_, rect)| keep(rect))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    fn nearest_by_scan(all: &[(ItemId, Rect<u32>)], point: Point<u32>) -> Option<f64> {
+        all.iter()
+            .map(|(_, rect)| distance_to(rect, point))
+            .reduce(f64::min)
+    }
+
+    proptest! {
+        #[test]
+        fn tree_agrees_with_a_scan(rects in rects(), points in points()) {
+            let (tree, all) = tree_of(&rects);
+            prop_assert_eq!(tree.len(), rects.len());
+            for point in points {
+                prop_assert_eq!(
+                    sorted(tree.containing(point)),
+                    scan(&all, |rect| rect.contains_point(point))
+                );
+                let region = Rect::new(point.x, point.y, 120, 80);
+                prop_assert_eq!(
+                    sorted(tree.intersecting(&region)),
+                    scan(&all, |rect| rect.intersects(&region))
+                );
+                // ties can go to any of the closest rects, so compare how
+                // far away it is rather than which one it is.
+                let nearest = tree.nearest(point);
+                prop_assert_eq!(nearest.map(|(_, distance)| distance), nearest_by_scan(&all, point));
+                if let Some((id, distance)) = nearest {
+                    prop_assert_eq!(distance_to(tree.get(id).unwrap(), point), distance);
+                }
+            }
+        }
+
+        #[test]
+        fn removed_rects_are_never_found(rects in rects(), points in points()) {
+            let (mut tree, all) = tree_of(&rects);
+            let (gone, kept): (Vec<_>, Vec<_>) = all.iter().partition(|(id, _)| id.0 % 2 == 0);
+            for (id, rect) in &gone {
+                prop_assert_eq!(tree.remove(*id), Some(*rect));
+                prop_assert_eq!(tree.remove(*id), None);
+                prop_assert_eq!(tree.get(*id), None);
+            }
+            prop_assert_eq!(tree.len(), kept.len());
+            for point in points {
+                prop_assert_eq!(
+                    sorted(tree.containing(point)),
+                    scan(&kept, |rect| rect.contains_point(point))
+                );
+                prop_assert_eq!(
+                    tree.nearest(point).map(|(_, distance)| distance),
+                    nearest_by_scan(&kept, point)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = QuadTree::new(Rect::new(0u32, 0, 10, 10));
+        assert!(tree.is_empty());
+        assert_eq!(tree.depth(), 1);
+        assert_eq!(tree.containing(Point::new(1, 1)), []);
+        assert_eq!(tree.nearest(Point::new(1, 1)), None);
+    }
+
+    #[test]
+    fn many_copies_of_one_rect_stop_splitting() {
+        let mut tree = QuadTree::new(Rect::new(0u32, 0, 1_024, 1_024));
+        for _ in 0..1_000 {
+            tree.insert(Rect::new(3, 3, 1, 1));
+        }
+        assert!(tree.depth() <= MAX_DEPTH + 1);
+        assert_eq!(tree.containing(Point::new(3, 3)).len(), 1_000);
+    }
+}
@@ -0,0 +1,550 @@
+use std::fmt;
+
+use num_traits::{CheckedAdd, Num, ToPrimitive};
+
+use crate::rect::{Point, Rect};
+use crate::rectangle::Rectangle;
+
+// TRANSFORMATIONS
+
+// change_height() and reassign() were the only ways to change a Rectangle.
+// This adds scaling, moving, turning and fitting one rectangle inside
+// another. Every operation comes in two forms, like change_height (which
+// changes the rectangle it's called on) and reassign (which takes the
+// rectangle and hands back a changed one):
+//
+//     rect.scale(2.0, 2.0, Rounding::Nearest);            // &mut self: changes rect
+//     let big = rect.scaled(2.0, 2.0, Rounding::Nearest); // self: makes a new one
+//
+// ROUNDING
+//
+// Scaling a 3 x 3 Rectangle<u32> by 0.5 gives 1.5 x 1.5, which a u32 can't
+// hold, so the operations that can make fractions take a Rounding. Float
+// rectangles ignore it. Sizes that are too big (or below 0 for unsigned
+// types) stop at the type's largest (or smallest) value, and NaN becomes 0 -
+// that's what `as` does when it turns an f64 into an integer.
+//
+// A Rect's corners can't do that: a rect turned so it should start at
+// x = -10 would jump to x = 0 instead and come out the wrong size. So the
+// operations on Rects give an OutOfRange error when a corner won't fit,
+// and leave the rect as it was.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+    // halves go away from zero: 1.5 -> 2, -1.5 -> -2.
+    Nearest,
+}
+
+impl Rounding {
+    pub fn apply(self, value: f64) -> f64 {
+        match self {
+            Rounding::Down => value.floor(),
+            Rounding::Up => value.ceil(),
+            Rounding::Nearest => value.round(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfRange;
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the rect would have a corner outside its number type's range"
+        )
+    }
+}
+
+impl std::error::Error for OutOfRange {}
+
+// Turning an f64 back into a rectangle's number type: from_f64 stops at the
+// type's limits, checked_from_f64 says None instead.
+pub trait FromF64: Copy {
+    fn from_f64(value: f64, rounding: Rounding) -> Self;
+    fn checked_from_f64(value: f64, rounding: Rounding) -> Option<Self>;
+}
+
+macro_rules! from_f64 {
+    (integers: $($int:ty),*; floats: $($float:ty),*) => {
+        $(impl FromF64 for $int {
+            fn from_f64(value: f64, rounding: Rounding) -> $int {
+                rounding.apply(value) as $int
+            }
+
+            fn checked_from_f64(value: f64, rounding: Rounding) -> Option<$int> {
+                num_traits::cast(rounding.apply(value))
+            }
+        })*
+        $(impl FromF64 for $float {
+            fn from_f64(value: f64, _rounding: Rounding) -> $float {
+                value as $float
+            }
+
+            fn checked_from_f64(value: f64, _rounding: Rounding) -> Option<$float> {
+                let value = value as $float;
+                value.is_finite().then_some(value)
+            }
+        })*
+    };
+}
+
+from_f64!(integers: u8, u16, u32, u64, i8, i16, i32, i64; floats: f32, f64);
+
+pub(crate) fn to_f64<T: ToPrimitive>(n: T) -> f64 {
+    n.to_f64().unwrap_or(f64::NAN)
+}
+
+// How far to scale `size` so it fits inside `bounds` (min) or covers it
+// (max), keeping its shape. None for empty sizes, which can't be scaled to
+// anything else.
+fn aspect_factor<T: ToPrimitive + Copy>(
+    size: Rectangle<T>,
+    bounds: Rectangle<T>,
+    pick: fn(f64, f64) -> f64,
+) -> Option<f64> {
+    let across = to_f64(bounds.width) / to_f64(size.width);
+    let down = to_f64(bounds.height) / to_f64(size.height);
+    let factor = pick(across, down);
+    (factor.is_finite() && to_f64(size.width) > 0.0 && to_f64(size.height) > 0.0).then_some(factor)
+}
+
+// SIZES
+
+impl<T: Num + Copy + ToPrimitive + FromF64> Rectangle<T> {
+    // Stretches the width by `x` and the height by `y`.
+    pub fn scale(&mut self, x: f64, y: f64, rounding: Rounding) {
+        self.width = T::from_f64(to_f64(self.width) * x, rounding);
+        self.height = T::from_f64(to_f64(self.height) * y, rounding);
+    }
+
+    pub fn scaled(mut self, x: f64, y: f64, rounding: Rounding) -> Rectangle<T> {
+        self.scale(x, y, rounding);
+        self
+    }
+
+    // A Rectangle has no position, so turning it by 90 degrees just swaps
+    // its sides. `quarters` can be negative (anticlockwise) - only whether
+    // it's odd matters.
+    pub fn rotate_quarters(&mut self, quarters: i32) {
+        if quarters % 2 != 0 {
+            std::mem::swap(&mut self.width, &mut self.height);
+        }
+    }
+
+    pub fn rotated_quarters(mut self, quarters: i32) -> Rectangle<T> {
+        self.rotate_quarters(quarters);
+        self
+    }
+
+    // Turns it by any angle (clockwise, in degrees) and becomes the size of
+    // the box that the turned rectangle fits in:
+    //
+    //     +--------+          /\
+    //     |        |   ->    /  \     -> the box around the diamond
+    //     +--------+         \  /
+    //                         \/
+    pub fn rotate(&mut self, degrees: f64, rounding: Rounding) {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (width, height) = (to_f64(self.width), to_f64(self.height));
+        self.width = T::from_f64(width * cos.abs() + height * sin.abs(), rounding);
+        self.height = T::from_f64(width * sin.abs() + height * cos.abs(), rounding);
+    }
+
+    pub fn rotated(mut self, degrees: f64, rounding: Rounding) -> Rectangle<T> {
+        self.rotate(degrees, rounding);
+        self
+    }
+
+    // The biggest size with the same shape that fits inside `bounds` - a
+    // photo shrunk (or grown) to fit a frame, with bars at the sides if the
+    // shapes differ. Rounding::Up can end up 1 too big; use Down to be sure.
+    // Empty rectangles are left alone.
+    pub fn fit_within(&mut self, bounds: Rectangle<T>, rounding: Rounding) {
+        if let Some(factor) = aspect_factor(*self, bounds, f64::min) {
+            self.scale(factor, factor, rounding);
+        }
+    }
+
+    pub fn fitted_within(mut self, bounds: Rectangle<T>, rounding: Rounding) -> Rectangle<T> {
+        self.fit_within(bounds, rounding);
+        self
+    }
+
+    // The smallest size with the same shape that covers all of `bounds` -
+    // the photo grown to fill the frame, with the edges cut off.
+    pub fn fill(&mut self, bounds: Rectangle<T>, rounding: Rounding) {
+        if let Some(factor) = aspect_factor(*self, bounds, f64::max) {
+            self.scale(factor, factor, rounding);
+        }
+    }
+
+    pub fn filled(mut self, bounds: Rectangle<T>, rounding: Rounding) -> Rectangle<T> {
+        self.fill(bounds, rounding);
+        self
+    }
+}
+
+// POSITIONED RECTS
+
+// A Rect also has a position, so scaling and turning happen around an
+// anchor point, which stays where it is: scale about the top-left corner and
+// the rect grows right and down; about its centre and it grows in every
+// direction.
+//
+// The corners are worked out in f64 and each is rounded on its own, so rects
+// that touched before still touch afterwards (if they use the same Rounding).
+// All four edges have to fit in T, and so do the width and height - an
+// i8 rect from -100 to 100 is 200 wide, which an i8 can't hold.
+
+fn rounded_corners<T: Num + Copy + ToPrimitive + FromF64>(
+    (left, top): (f64, f64),
+    (right, bottom): (f64, f64),
+    rounding: Rounding,
+) -> Result<Rect<T>, OutOfRange> {
+    let (left, right) = (left.min(right), left.max(right));
+    let (top, bottom) = (top.min(bottom), top.max(bottom));
+    let edge = |value| T::checked_from_f64(value, rounding).ok_or(OutOfRange);
+    let (left, top, right, bottom) = (edge(left)?, edge(top)?, edge(right)?, edge(bottom)?);
+    let side = |from: T, to: T| {
+        T::checked_from_f64(to_f64(to) - to_f64(from), Rounding::Nearest).ok_or(OutOfRange)
+    };
+    Ok(Rect::new(left, top, side(left, right)?, side(top, bottom)?))
+}
+
+impl<T: Num + Copy + CheckedAdd> Rect<T> {
+    // Moves it by (x, y). For unsigned types that only goes right and down.
+    // Like the other operations on Rects, every edge has to fit in T
+    // afterwards - a Rect<u8> 10 wide can't move past x = 245.
+    pub fn translate(&mut self, x: T, y: T) -> Result<(), OutOfRange> {
+        *self = self.translated(x, y)?;
+        Ok(())
+    }
+
+    pub fn translated(self, x: T, y: T) -> Result<Rect<T>, OutOfRange> {
+        let moved = Rect {
+            origin: Point::new(
+                self.origin.x.checked_add(&x).ok_or(OutOfRange)?,
+                self.origin.y.checked_add(&y).ok_or(OutOfRange)?,
+            ),
+            size: self.size,
+        };
+        moved.checked_right().ok_or(OutOfRange)?;
+        moved.checked_bottom().ok_or(OutOfRange)?;
+        Ok(moved)
+    }
+}
+
+impl<T: Num + Copy + ToPrimitive + FromF64> Rect<T> {
+    pub fn center(&self) -> Point<f64> {
+        Point::new(
+            to_f64(self.left()) + to_f64(self.size.width) / 2.0,
+            to_f64(self.top()) + to_f64(self.size.height) / 2.0,
+        )
+    }
+
+    fn corners(&self) -> [(f64, f64); 4] {
+        let (left, top) = (to_f64(self.left()), to_f64(self.top()));
+        let (right, bottom) = (to_f64(self.right()), to_f64(self.bottom()));
+        [(left, top), (right, top), (right, bottom), (left, bottom)]
+    }
+
+    // The box around all four corners after `turn` moves each one.
+    fn turn_corners(
+        &mut self,
+        turn: impl Fn(f64, f64) -> (f64, f64),
+        rounding: Rounding,
+    ) -> Result<(), OutOfRange> {
+        let (mut left, mut top) = (f64::INFINITY, f64::INFINITY);
+        let (mut right, mut bottom) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for (x, y) in self.corners().map(|(x, y)| turn(x, y)) {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+        *self = rounded_corners((left, top), (right, bottom), rounding)?;
+        Ok(())
+    }
+
+    pub fn scale_about(
+        &mut self,
+        anchor: Point<f64>,
+        x: f64,
+        y: f64,
+        rounding: Rounding,
+    ) -> Result<(), OutOfRange> {
+        let [(left, top), _, (right, bottom), _] = self.corners();
+        *self = rounded_corners(
+            (
+                anchor.x + (left - anchor.x) * x,
+                anchor.y + (top - anchor.y) * y,
+            ),
+            (
+                anchor.x + (right - anchor.x) * x,
+                anchor.y + (bottom - anchor.y) * y,
+            ),
+            rounding,
+        )?;
+        Ok(())
+    }
+
+    pub fn scaled_about(
+        mut self,
+        anchor: Point<f64>,
+        x: f64,
+        y: f64,
+        rounding: Rounding,
+    ) -> Result<Rect<T>, OutOfRange> {
+        self.scale_about(anchor, x, y, rounding)?;
+        Ok(self)
+    }
+
+    // Turns it clockwise (on screen, where y grows downwards) by `quarters`
+    // quarter turns about `anchor`. Negative means anticlockwise. Corners
+    // only land on fractions if the anchor is on a half, so whole-number
+    // rects turned about a whole-number anchor stay exact.
+    pub fn rotate_quarters(&mut self, quarters: i32, anchor: Point<f64>) -> Result<(), OutOfRange> {
+        self.turn_corners(
+            |x, y| {
+                let (dx, dy) = (x - anchor.x, y - anchor.y);
+                match quarters.rem_euclid(4) {
+                    0 => (x, y),
+                    1 => (anchor.x - dy, anchor.y + dx),
+                    2 => (anchor.x - dx, anchor.y - dy),
+                    _ => (anchor.x + dy, anchor.y - dx),
+                }
+            },
+            Rounding::Nearest,
+        )
+    }
+
+    pub fn rotated_quarters(
+        mut self,
+        quarters: i32,
+        anchor: Point<f64>,
+    ) -> Result<Rect<T>, OutOfRange> {
+        self.rotate_quarters(quarters, anchor)?;
+        Ok(self)
+    }
+
+    // Turns it clockwise by any angle about `anchor` and becomes the box
+    // that the turned rect fits in (see Rectangle::rotate).
+    pub fn rotate(
+        &mut self,
+        degrees: f64,
+        anchor: Point<f64>,
+        rounding: Rounding,
+    ) -> Result<(), OutOfRange> {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        self.turn_corners(
+            |x, y| {
+                let (dx, dy) = (x - anchor.x, y - anchor.y);
+                (
+                    anchor.x + dx * cos - dy * sin,
+                    anchor.y + dx * sin + dy * cos,
+                )
+            },
+            rounding,
+        )
+    }
+
+    pub fn rotated(
+        mut self,
+        degrees: f64,
+        anchor: Point<f64>,
+        rounding: Rounding,
+    ) -> Result<Rect<T>, OutOfRange> {
+        self.rotate(degrees, anchor, rounding)?;
+        Ok(self)
+    }
+
+    // Resizes it as Rectangle::fit_within does and centres it in `bounds`.
+    pub fn fit_within(&mut self, bounds: &Rect<T>, rounding: Rounding) -> Result<(), OutOfRange> {
+        let size = self.size.fitted_within(bounds.size, rounding);
+        *self = size.centered_in(bounds, rounding)?;
+        Ok(())
+    }
+
+    pub fn fitted_within(
+        mut self,
+        bounds: &Rect<T>,
+        rounding: Rounding,
+    ) -> Result<Rect<T>, OutOfRange> {
+        self.fit_within(bounds, rounding)?;
+        Ok(self)
+    }
+
+    // Resizes it as Rectangle::fill does and centres it on `bounds`, so it
+    // hangs over the edges of `bounds` on two sides - which for unsigned
+    // types is only possible if there's room above or left of `bounds`.
+    pub fn fill(&mut self, bounds: &Rect<T>, rounding: Rounding) -> Result<(), OutOfRange> {
+        let size = self.size.filled(bounds.size, rounding);
+        *self = size.centered_in(bounds, rounding)?;
+        Ok(())
+    }
+
+    pub fn filled(mut self, bounds: &Rect<T>, rounding: Rounding) -> Result<Rect<T>, OutOfRange> {
+        self.fill(bounds, rounding)?;
+        Ok(self)
+    }
+}
+
+impl<T: Num + Copy + ToPrimitive + FromF64> Rectangle<T> {
+    // Puts this size in the middle of `bounds`.
+    fn centered_in(self, bounds: &Rect<T>, rounding: Rounding) -> Result<Rect<T>, OutOfRange> {
+        let center = bounds.center();
+        let (width, height) = (to_f64(self.width), to_f64(self.height));
+        let edge = |value| T::checked_from_f64(value, rounding).ok_or(OutOfRange);
+        let (x, y) = (
+            edge(center.x - width / 2.0)?,
+            edge(center.y - height / 2.0)?,
+        );
+        // the far edges have to fit too.
+        edge(to_f64(x) + width)?;
+        edge(to_f64(y) + height)?;
+        Ok(self.at(x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: Point<f64> = Point { x: 0.0, y: 0.0 };
+
+    #[test]
+    fn translating() {
+        let mut rect = Rect::new(10u8, 20, 30, 40);
+        assert_eq!(rect.translate(5, 6), Ok(()));
+        assert_eq!(rect, Rect::new(15, 26, 30, 40));
+        assert_eq!(rect.translated(0, 0), Ok(rect));
+        assert_eq!(
+            Rect::new(-5i32, 5, 1, 1).translated(-10, -10),
+            Ok(Rect::new(-15, -5, 1, 1))
+        );
+    }
+
+    #[test]
+    fn translating_past_the_edge_is_an_error() {
+        let mut rect = Rect::new(200u8, 100, 50, 50);
+        // the right edge can reach 255, but not go past it.
+        assert_eq!(rect.translated(5, 0), Ok(Rect::new(205, 100, 50, 50)));
+        assert_eq!(rect.translated(6, 0), Err(OutOfRange));
+        assert_eq!(rect.translate(0, 106), Err(OutOfRange));
+        // the origin itself overflowing.
+        assert_eq!(rect.translate(56, 0), Err(OutOfRange));
+        assert_eq!(
+            rect,
+            Rect::new(200, 100, 50, 50),
+            "a failed move changes nothing"
+        );
+
+        let mut rect = Rect::new(i32::MIN + 1, 0, 1, 1);
+        assert_eq!(rect.translate(-2, 0), Err(OutOfRange));
+        assert_eq!(rect.translate(-1, 0), Ok(()));
+        assert_eq!(rect.origin.x, i32::MIN);
+    }
+
+    #[test]
+    fn turning_past_zero_is_an_error_for_unsigned_types() {
+        let mut rect = Rect::new(0u32, 0, 10, 5);
+        assert_eq!(rect.rotated_quarters(1, ORIGIN), Err(OutOfRange));
+        assert_eq!(rect.rotate_quarters(1, ORIGIN), Err(OutOfRange));
+        assert_eq!(
+            rect,
+            Rect::new(0, 0, 10, 5),
+            "a failed turn changes nothing"
+        );
+        assert_eq!(rect.rotated(45.0, ORIGIN, Rounding::Up), Err(OutOfRange));
+        assert_eq!(
+            rect.rotated_quarters(1, Point::new(5.0, 5.0)),
+            Ok(Rect::new(5, 0, 5, 10))
+        );
+        assert_eq!(
+            Rect::new(0i32, 0, 10, 5).rotated_quarters(1, ORIGIN),
+            Ok(Rect::new(-5, 0, 5, 10))
+        );
+    }
+
+    #[test]
+    fn scaling_past_the_limits() {
+        let rect = Rect::new(0u8, 0, 100, 100);
+        assert_eq!(
+            rect.scaled_about(ORIGIN, 2.55, 1.0, Rounding::Nearest),
+            Ok(Rect::new(0, 0, 255, 100))
+        );
+        assert_eq!(
+            rect.scaled_about(ORIGIN, 2.56, 1.0, Rounding::Nearest),
+            Err(OutOfRange)
+        );
+        assert_eq!(
+            rect.scaled_about(Point::new(50.0, 50.0), 2.0, 2.0, Rounding::Nearest),
+            Err(OutOfRange)
+        );
+        // both edges fit in an i8, but the width doesn't.
+        assert_eq!(
+            Rect::new(-100i8, 0, 100, 1).scaled_about(
+                Point::new(-100.0, 0.0),
+                2.0,
+                1.0,
+                Rounding::Nearest
+            ),
+            Err(OutOfRange)
+        );
+    }
+
+    #[test]
+    fn fill_and_fit_need_room_for_the_corners() {
+        let frame = Rect::new(0u32, 0, 100, 50);
+        let poster = Rect::new(0u32, 0, 30, 40);
+        assert_eq!(
+            poster.fitted_within(&frame, Rounding::Down),
+            Ok(Rect::new(31, 0, 37, 50))
+        );
+        assert_eq!(poster.filled(&frame, Rounding::Down), Err(OutOfRange));
+        // with room above the frame, it can hang over.
+        let frame = Rect::new(0u32, 100, 100, 50);
+        assert_eq!(
+            poster.filled(&frame, Rounding::Down),
+            Ok(Rect::new(0, 58, 100, 133))
+        );
+        let frame = Rect::new(0i32, 0, 100, 50);
+        assert_eq!(
+            Rect::new(0i32, 0, 30, 40).filled(&frame, Rounding::Down),
+            Ok(Rect::new(0, -42, 100, 133))
+        );
+    }
+
+    #[test]
+    fn floats_only_fail_on_infinity() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 5.0);
+        assert_eq!(
+            rect.rotated_quarters(1, ORIGIN),
+            Ok(Rect::new(-5.0, 0.0, 5.0, 10.0))
+        );
+        assert_eq!(
+            rect.scaled_about(ORIGIN, f64::INFINITY, 1.0, Rounding::Nearest),
+            Err(OutOfRange)
+        );
+        assert_eq!(f32::checked_from_f64(1e300, Rounding::Nearest), None);
+    }
+
+    #[test]
+    fn checked_from_f64_at_the_limits() {
+        assert_eq!(u8::checked_from_f64(255.4, Rounding::Nearest), Some(255));
+        assert_eq!(u8::checked_from_f64(255.5, Rounding::Nearest), None);
+        assert_eq!(u8::checked_from_f64(-0.4, Rounding::Nearest), Some(0));
+        assert_eq!(u8::checked_from_f64(-0.4, Rounding::Down), None);
+        assert_eq!(i32::checked_from_f64(f64::NAN, Rounding::Down), None);
+        assert_eq!(u64::checked_from_f64(2f64.powi(64), Rounding::Down), None);
+        assert_eq!(
+            u8::from_f64(300.0, Rounding::Down),
+            255,
+            "from_f64 still stops at the limit"
+        );
+    }
+}
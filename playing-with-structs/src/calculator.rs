@@ -0,0 +1,650 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+use crate::Rectangle;
+
+// RECTANGLE CALCULATOR
+
+// The program above works out the area of one rectangle that's written into
+// the code. Run with arguments and it works out the area, perimeter and
+// diagonal of any rectangles you give it:
+//
+//     cargo run -- 30x50 door=80x200 --unit cm --sort area --format table
+//
+// Rectangles are WIDTHxHEIGHT, optionally with a name in front
+// (door=80x200). Unnamed ones are called rect1, rect2, ... in the order
+// they're read. They can come from:
+//
+//   - the arguments themselves
+//   - a file, with --file PATH: one rectangle per line; blank lines and
+//     lines starting with # are skipped
+//   - stdin, in the same format as a file, with --stdin - or if there
+//     were no rectangles or files in the arguments (`cat rects.txt | cargo
+//     run -- --format json`). With no arguments at all, main only runs the
+//     calculator when something is piped in (`cat rects.txt | cargo run`);
+//     otherwise it runs the examples above.
+//
+// Options:
+//   --unit UNIT         what the numbers measure (default px); it's only a
+//                       label - area comes out in UNIT² and the rest in UNIT
+//   --sort FIELD        name, width, height, area, perimeter or diagonal
+//   --reverse           reverse the order (biggest first, or Z to A)
+//   --filter EXPR       keep the rectangles where EXPR is true, like
+//                       "area>=1000" or "width=height"; repeat for more
+//   --format FORMAT     table (the default), json or debug
+
+const USAGE: &str = "usage: playing-with-structs [[NAME=]WIDTHxHEIGHT ...] [--file PATH] [--stdin]
+       [--unit UNIT] [--sort FIELD] [--reverse] [--filter EXPR] [--format table|json|debug]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+    Debug,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "debug" => Ok(Format::Debug),
+            other => Err(format!(
+                "unknown format '{other}' (expected table, json or debug)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Width,
+    Height,
+    Area,
+    Perimeter,
+    Diagonal,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Field::Name),
+            "width" => Ok(Field::Width),
+            "height" => Ok(Field::Height),
+            "area" => Ok(Field::Area),
+            "perimeter" => Ok(Field::Perimeter),
+            "diagonal" => Ok(Field::Diagonal),
+            other => Err(format!(
+                "unknown field '{other}' (expected name, width, height, area, perimeter or diagonal)"
+            )),
+        }
+    }
+}
+
+// A rectangle with everything worked out. Area and perimeter are u64: the
+// area() function above multiplies two u32s into a u32, which overflows
+// for anything bigger than 65536 x 65536.
+#[derive(Debug)]
+pub struct Measured {
+    pub name: String,
+    pub rectangle: Rectangle,
+    pub area: u64,
+    pub perimeter: u64,
+    pub diagonal: f64,
+}
+
+impl Measured {
+    pub fn new(name: String, rectangle: Rectangle) -> Measured {
+        let (width, height) = (u64::from(rectangle.width), u64::from(rectangle.height));
+        Measured {
+            name,
+            area: width * height,
+            perimeter: 2 * (width + height),
+            diagonal: (width as f64).hypot(height as f64),
+            rectangle,
+        }
+    }
+
+    // Any field but the name, as a number to compare.
+    fn number(&self, field: Field) -> Option<f64> {
+        match field {
+            Field::Name => None,
+            Field::Width => Some(f64::from(self.rectangle.width)),
+            Field::Height => Some(f64::from(self.rectangle.height)),
+            Field::Area => Some(self.area as f64),
+            Field::Perimeter => Some(self.perimeter as f64),
+            Field::Diagonal => Some(self.diagonal),
+        }
+    }
+
+    fn compare(&self, other: &Measured, field: Field) -> Ordering {
+        match (self.number(field), other.number(field)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            _ => self.name.cmp(&other.name),
+        }
+    }
+}
+
+// "30x50" or "door=30x50".
+pub fn parse_rectangle(text: &str) -> Result<(Option<String>, Rectangle), String> {
+    let (name, size) = match text.split_once('=') {
+        Some((name, size)) => (Some(name.trim().to_string()), size),
+        None => (None, text),
+    };
+    let (width, height) = size.split_once(['x', 'X']).ok_or(format!(
+        "'{text}' isn't a rectangle - expected WIDTHxHEIGHT, like 30x50"
+    ))?;
+    let side = |value: &str, which: &str| {
+        value.trim().parse::<u32>().map_err(|_| {
+            format!(
+                "'{text}' has a {which} of '{}', which isn't a whole number",
+                value.trim()
+            )
+        })
+    };
+    let rectangle = Rectangle {
+        width: side(width, "width")?,
+        height: side(height, "height")?,
+    };
+    Ok((name, rectangle))
+}
+
+// One rectangle per line; `source` is used in error messages.
+pub fn parse_lines(text: &str, source: &str) -> Result<Vec<(Option<String>, Rectangle)>, String> {
+    let mut rectangles = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rectangle =
+            parse_rectangle(line).map_err(|e| format!("{source} line {}: {e}", number + 1))?;
+        rectangles.push(rectangle);
+    }
+    Ok(rectangles)
+}
+
+// FILTERS
+
+// "area>=1000": a field, a comparison, and a number or another field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Filter {
+    field: Field,
+    comparison: Ordering,
+    // "<=" is "not greater", so it's stored as Greater with `negated`.
+    negated: bool,
+    value: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Number(f64),
+    Field(Field),
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad =
+            || format!("'{s}' isn't a filter - expected something like area>=1000 or width=height");
+        let start = s.find(['<', '>', '=', '!']).ok_or_else(bad)?;
+        let end = s[start..]
+            .find(|c| !"<>=!".contains(c))
+            .map_or(s.len(), |length| start + length);
+        let (comparison, negated) = match &s[start..end] {
+            "<" => (Ordering::Less, false),
+            ">" => (Ordering::Greater, false),
+            "=" | "==" => (Ordering::Equal, false),
+            "<=" => (Ordering::Greater, true),
+            ">=" => (Ordering::Less, true),
+            "!=" => (Ordering::Equal, true),
+            _ => return Err(bad()),
+        };
+        let field: Field = s[..start].trim().parse()?;
+        let value = s[end..].trim();
+        // NaN isn't less, equal or greater than anything, so a negated
+        // comparison with it ("area<=nan") would keep everything. NaN and
+        // infinity are left to fail as field names instead.
+        let value = match value.parse::<f64>() {
+            Ok(number) if number.is_finite() => Value::Number(number),
+            _ => Value::Field(value.parse()?),
+        };
+        if field == Field::Name || value == Value::Field(Field::Name) {
+            return Err(format!("'{s}': names can't be compared, only sorted"));
+        }
+        Ok(Filter {
+            field,
+            comparison,
+            negated,
+            value,
+        })
+    }
+}
+
+impl Filter {
+    pub fn keeps(&self, measured: &Measured) -> bool {
+        let value = match self.value {
+            Value::Number(number) => Some(number),
+            Value::Field(field) => measured.number(field),
+        };
+        let matches = match (measured.number(self.field), value) {
+            (Some(a), Some(b)) => a.partial_cmp(&b) == Some(self.comparison),
+            _ => false,
+        };
+        matches != self.negated
+    }
+}
+
+// OUTPUT
+
+fn table(rectangles: &[Measured], unit: &str) -> String {
+    let header = [
+        String::from("name"),
+        format!("width ({unit})"),
+        format!("height ({unit})"),
+        format!("area ({unit}²)"),
+        format!("perimeter ({unit})"),
+        format!("diagonal ({unit})"),
+    ];
+    let rows: Vec<[String; 6]> = rectangles
+        .iter()
+        .map(|m| {
+            [
+                m.name.clone(),
+                m.rectangle.width.to_string(),
+                m.rectangle.height.to_string(),
+                m.area.to_string(),
+                m.perimeter.to_string(),
+                format!("{:.2}", m.diagonal),
+            ]
+        })
+        .collect();
+
+    // each column as wide as its widest cell.
+    let mut widths = header.clone().map(|cell| cell.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                let padding = " ".repeat(width - cell.chars().count());
+                // names line up on the left, numbers on the right.
+                if i == 0 {
+                    format!("{cell}{padding}")
+                } else {
+                    format!("{padding}{cell}")
+                }
+            })
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json(rectangles: &[Measured], unit: &str) -> String {
+    let objects: Vec<String> = rectangles
+        .iter()
+        .map(|m| {
+            format!(
+                "  {{\"name\": {}, \"width\": {}, \"height\": {}, \"unit\": {}, \"area\": {}, \"perimeter\": {}, \"diagonal\": {}}}",
+                json_string(&m.name),
+                m.rectangle.width,
+                m.rectangle.height,
+                json_string(unit),
+                m.area,
+                m.perimeter,
+                m.diagonal
+            )
+        })
+        .collect();
+    if objects.is_empty() {
+        return String::from("[]\n");
+    }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+pub fn render(rectangles: &[Measured], unit: &str, format: Format) -> String {
+    match format {
+        Format::Table => table(rectangles, unit),
+        Format::Json => json(rectangles, unit),
+        Format::Debug => format!("{rectangles:#?}\n"),
+    }
+}
+
+pub fn cli(args: &[String]) -> Result<(), String> {
+    let mut given = Vec::new();
+    let mut files = Vec::new();
+    let mut use_stdin = false;
+    let mut unit = String::from("px");
+    let mut sort = None;
+    let mut reverse = false;
+    let mut filters: Vec<Filter> = Vec::new();
+    let mut format = Format::Table;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or(format!("{flag} needs a value"));
+        match arg.as_str() {
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            "--file" => files.push(value("--file")?.clone()),
+            "--stdin" | "-" => use_stdin = true,
+            "--unit" => unit = value("--unit")?.clone(),
+            "--sort" => sort = Some(value("--sort")?.parse::<Field>()?),
+            "--reverse" => reverse = true,
+            "--filter" => filters.push(value("--filter")?.parse()?),
+            "--format" => format = value("--format")?.parse()?,
+            other if other.starts_with("--") => {
+                return Err(format!("unknown option '{other}'\n{USAGE}"))
+            }
+            other => given.push(parse_rectangle(other)?),
+        }
+    }
+
+    for path in &files {
+        let text = fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+        given.extend(parse_lines(&text, path)?);
+    }
+    // only when asked, or when there's nothing else to read - an empty
+    // --file shouldn't leave us waiting for stdin.
+    if use_stdin || (files.is_empty() && given.is_empty()) {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("can't read stdin: {e}"))?;
+        given.extend(parse_lines(&text, "stdin")?);
+    }
+
+    let rectangles = measure(given, &filters, sort, reverse);
+    print!("{}", render(&rectangles, &unit, format));
+    Ok(())
+}
+
+// Names the unnamed rectangles, measures them all, and keeps the ones that
+// pass every filter, in the order asked for.
+pub fn measure(
+    given: Vec<(Option<String>, Rectangle)>,
+    filters: &[Filter],
+    sort: Option<Field>,
+    reverse: bool,
+) -> Vec<Measured> {
+    // named rectangles don't use up a number.
+    let mut unnamed = 0;
+    let mut rectangles: Vec<Measured> = given
+        .into_iter()
+        .map(|(name, rectangle)| {
+            let name = name.unwrap_or_else(|| {
+                unnamed += 1;
+                format!("rect{unnamed}")
+            });
+            Measured::new(name, rectangle)
+        })
+        .filter(|measured| filters.iter().all(|filter| filter.keeps(measured)))
+        .collect();
+    if let Some(field) = sort {
+        rectangles.sort_by(|a, b| a.compare(b, field));
+    }
+    if reverse {
+        rectangles.reverse();
+    }
+    rectangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(rectangle: &Rectangle) -> (u32, u32) {
+        (rectangle.width, rectangle.height)
+    }
+
+    fn parsed(text: &str) -> (Option<String>, (u32, u32)) {
+        let (name, rectangle) = parse_rectangle(text).unwrap();
+        (name, size(&rectangle))
+    }
+
+    fn rectangles(sizes: &[(&str, u32, u32)]) -> Vec<(Option<String>, Rectangle)> {
+        sizes
+            .iter()
+            .map(|&(name, width, height)| {
+                let name = Some(name.to_string()).filter(|name| !name.is_empty());
+                (name, Rectangle { width, height })
+            })
+            .collect()
+    }
+
+    fn names(measured: &[Measured]) -> Vec<&str> {
+        measured.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    fn filter(text: &str) -> Filter {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_rectangles() {
+        assert_eq!(parsed("30x50"), (None, (30, 50)));
+        assert_eq!(parsed("30X50"), (None, (30, 50)));
+        assert_eq!(parsed(" 30 x 50 "), (None, (30, 50)));
+        assert_eq!(
+            parsed("door=80x200"),
+            (Some(String::from("door")), (80, 200))
+        );
+        assert_eq!(
+            parsed("big door = 80x200"),
+            (Some(String::from("big door")), (80, 200))
+        );
+        assert_eq!(parsed("0x4294967295"), (None, (0, u32::MAX)));
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_rectangle() {
+        assert_eq!(
+            parse_rectangle("30by50").unwrap_err(),
+            "'30by50' isn't a rectangle - expected WIDTHxHEIGHT, like 30x50"
+        );
+        assert_eq!(
+            parse_rectangle("door=-3x5").unwrap_err(),
+            "'door=-3x5' has a width of '-3', which isn't a whole number"
+        );
+        assert_eq!(
+            parse_rectangle("3x4294967296").unwrap_err(),
+            "'3x4294967296' has a height of '4294967296', which isn't a whole number"
+        );
+        assert!(parse_rectangle("3x").is_err());
+        assert!(parse_rectangle("3x4x5").is_err());
+    }
+
+    #[test]
+    fn parses_lines_skipping_blanks_and_comments() {
+        let text = "# sizes\n30x50\n\n   \ndoor=80x200\n  # done\n";
+        let lines = parse_lines(text, "rects.txt").unwrap();
+        let lines: Vec<_> = lines
+            .iter()
+            .map(|(name, r)| (name.as_deref(), size(r)))
+            .collect();
+        assert_eq!(lines, [(None, (30, 50)), (Some("door"), (80, 200))]);
+
+        assert_eq!(
+            parse_lines("30x50\n\nwide\n", "stdin").unwrap_err(),
+            "stdin line 3: 'wide' isn't a rectangle - expected WIDTHxHEIGHT, like 30x50"
+        );
+        assert!(parse_lines("", "stdin").unwrap().is_empty());
+    }
+
+    #[test]
+    fn measures_without_overflowing() {
+        let m = Measured::new(
+            String::from("huge"),
+            Rectangle {
+                width: u32::MAX,
+                height: u32::MAX,
+            },
+        );
+        assert_eq!(m.area, u64::from(u32::MAX) * u64::from(u32::MAX));
+        assert_eq!(m.perimeter, 4 * u64::from(u32::MAX));
+        let m = Measured::new(
+            String::from("3-4-5"),
+            Rectangle {
+                width: 3,
+                height: 4,
+            },
+        );
+        assert_eq!((m.area, m.perimeter, m.diagonal), (12, 14, 5.0));
+    }
+
+    #[test]
+    fn every_comparison_filters_the_right_way() {
+        let m = Measured::new(
+            String::from("a"),
+            Rectangle {
+                width: 30,
+                height: 50,
+            },
+        );
+        // (area 1500) against a value below, equal to and above it.
+        let cases = [
+            ("<", [false, false, true]),
+            ("<=", [false, true, true]),
+            (">", [true, false, false]),
+            (">=", [true, true, false]),
+            ("=", [false, true, false]),
+            ("==", [false, true, false]),
+            ("!=", [true, false, true]),
+        ];
+        for (operator, expected) in cases {
+            for (value, keep) in [1499, 1500, 1501].into_iter().zip(expected) {
+                let text = format!("area{operator}{value}");
+                assert_eq!(filter(&text).keeps(&m), keep, "{text}");
+            }
+        }
+        assert!(filter("width < height").keeps(&m));
+        assert!(!filter("width>=height").keeps(&m));
+        assert!(filter("diagonal>58.3").keeps(&m));
+        assert!(!filter("diagonal>58.4").keeps(&m));
+    }
+
+    #[test]
+    fn rejects_bad_filters() {
+        for bad in ["area", "area=>5", "area<>5", "area<<5", "=5"] {
+            assert!(bad.parse::<Filter>().is_err(), "{bad}");
+        }
+        assert_eq!(
+            "size>3".parse::<Filter>().unwrap_err(),
+            "unknown field 'size' (expected name, width, height, area, perimeter or diagonal)"
+        );
+        assert_eq!(
+            "name=door".parse::<Filter>().unwrap_err(),
+            "unknown field 'door' (expected name, width, height, area, perimeter or diagonal)"
+        );
+        assert_eq!(
+            "width=name".parse::<Filter>().unwrap_err(),
+            "'width=name': names can't be compared, only sorted"
+        );
+        // NaN would make every negated comparison true.
+        assert!("area<=nan".parse::<Filter>().is_err());
+        assert!("area>=inf".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn names_filters_and_sorts() {
+        let given = rectangles(&[("", 10, 10), ("door", 80, 200), ("", 5, 50), ("", 30, 1)]);
+        let all = measure(given, &[], None, false);
+        assert_eq!(names(&all), ["rect1", "door", "rect2", "rect3"]);
+
+        let given = || rectangles(&[("b", 10, 10), ("c", 1, 400), ("a", 30, 1), ("d", 10, 10)]);
+        // sorting is stable, so b stays ahead of d when they tie.
+        let by_area = measure(given(), &[], Some(Field::Area), false);
+        assert_eq!(names(&by_area), ["a", "b", "d", "c"]);
+        let by_height = measure(given(), &[], Some(Field::Height), true);
+        assert_eq!(names(&by_height), ["c", "d", "b", "a"]);
+        let by_name = measure(given(), &[], Some(Field::Name), false);
+        assert_eq!(names(&by_name), ["a", "b", "c", "d"]);
+        // filtering happens before sorting, and every filter has to pass.
+        let filters = [filter("area>=30"), filter("width!=height")];
+        let kept = measure(given(), &filters, Some(Field::Width), true);
+        assert_eq!(names(&kept), ["a", "c"]);
+    }
+
+    #[test]
+    fn table_output() {
+        let given = rectangles(&[("door", 80, 200), ("", 3, 4)]);
+        let measured = measure(given, &[], None, false);
+        assert_eq!(
+            render(&measured, "cm", Format::Table),
+            concat!(
+                "name   width (cm)  height (cm)  area (cm²)  perimeter (cm)  diagonal (cm)\n",
+                "door           80          200       16000             560         215.41\n",
+                "rect1           3            4          12              14           5.00\n",
+            )
+        );
+        assert_eq!(
+            render(&[], "px", Format::Table),
+            "name  width (px)  height (px)  area (px²)  perimeter (px)  diagonal (px)\n"
+        );
+    }
+
+    #[test]
+    fn json_output() {
+        let given = rectangles(&[("say \"hi\"\n", 3, 4), ("", 1, 2)]);
+        let measured = measure(given, &[], None, false);
+        assert_eq!(
+            render(&measured, "in", Format::Json),
+            concat!(
+                "[\n",
+                r#"  {"name": "say \"hi\"\u000a", "width": 3, "height": 4, "unit": "in", "area": 12, "perimeter": 14, "diagonal": 5},"#,
+                "\n",
+                r#"  {"name": "rect1", "width": 1, "height": 2, "unit": "in", "area": 2, "perimeter": 6, "diagonal": 2.23606797749979}"#,
+                "\n]\n",
+            )
+        );
+        assert_eq!(render(&[], "in", Format::Json), "[]\n");
+        assert_eq!(json_string("a\\b\t"), r#""a\\b\u0009""#);
+    }
+
+    #[test]
+    fn parses_options() {
+        assert_eq!("json".parse(), Ok(Format::Json));
+        assert!("yaml".parse::<Format>().is_err());
+        assert_eq!("perimeter".parse(), Ok(Field::Perimeter));
+        assert!("Area".parse::<Field>().is_err());
+        let args = [String::from("--sort")];
+        assert_eq!(cli(&args).unwrap_err(), "--sort needs a value");
+        let args = [String::from("--colour")];
+        assert!(cli(&args)
+            .unwrap_err()
+            .starts_with("unknown option '--colour'"));
+    }
+}
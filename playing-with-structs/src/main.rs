@@ -1,4 +1,22 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::process;
+
+mod calculator;
+
 fn main() {
+    // running with arguments (e.g. `cargo run -- 30x50 --format json`), or
+    // with rectangles piped in (`cat rects.txt | cargo run`), runs the
+    // rectangle calculator in calculator.rs instead of the examples below.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() || !io::stdin().is_terminal() {
+        if let Err(message) = calculator::cli(&args) {
+            eprintln!("{message}");
+            process::exit(1);
+        }
+        return;
+    }
+
     // This program calculates the area of a rectangle:
 
     let rect1 = Rectangle {
@@ -35,7 +53,8 @@ fn main() {
                           // heap allocated.
     };
     // dbg!(&name); // get a move error, because name has been moved to scope
-    // of dbg! from line 34.
+    // of dbg! from line 50.
+    println!("{}", john.name); // but john owns it now, so this is fine.
 
 }
 
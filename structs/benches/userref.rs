@@ -0,0 +1,55 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use structs::csv;
+use structs::user::{build_user, User};
+use structs::userref;
+
+// How much does not copying save? Parse the same lines both ways:
+// borrowing with UserRef, and the owning way with csv::parse_record and
+// build_user, which copies every username and email into a new String.
+// Run with `cargo bench`.
+
+fn lines(count: usize) -> String {
+    let mut text = String::new();
+    for n in 0..count {
+        text.push_str(&format!("user{n},user{n}@example.com,{},{n}\n", n % 3 != 0));
+    }
+    text
+}
+
+fn borrowing(text: &str) -> usize {
+    userref::parse_records(text, ',')
+        .filter(|record| record.as_ref().is_ok_and(|user| user.active))
+        .count()
+}
+
+fn owning(text: &str) -> usize {
+    text.lines()
+        .map(|line| csv::parse_record(line).unwrap())
+        .map(|fields| User {
+            active: fields[2] == "true",
+            ..build_user(fields[1].clone(), fields[0].clone())
+        })
+        .filter(|user| user.active)
+        .count()
+}
+
+fn parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse active users");
+    for count in [1_000, 100_000] {
+        let text = lines(count);
+        // both ways have to agree, or the comparison means nothing.
+        assert_eq!(borrowing(&text), owning(&text));
+
+        group.bench_with_input(BenchmarkId::new("UserRef", count), &text, |b, text| {
+            b.iter(|| borrowing(black_box(text)))
+        });
+        group.bench_with_input(BenchmarkId::new("build_user", count), &text, |b, text| {
+            b.iter(|| owning(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parsing);
+criterion_main!(benches);
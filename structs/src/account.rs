@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use rand::Rng;
+
+use crate::registry::{validate_email, EmailError};
+use crate::session::{Session, SessionError, SessionManager};
+use crate::user::User;
+
+// TYPESTATE: USING UNIT-LIKE STRUCTS AS MARKERS
+
+// `struct AlwaysEqual;` holds no data, so what is it for? One answer is as
+// a label in a type. Account<Unverified> and Account<Verified> hold exactly
+// the same data, but they're different types, so they can have different
+// methods. An unverified account has no sign_in() at all - calling it isn't
+// a runtime error, it doesn't compile:
+//
+//     let account = Account::new(user);      // Account<Unverified>
+//     account.sign_in(&mut sessions);        // throws error: no method named `sign_in`
+//
+// (tests/compile_fail has this and the other mistakes the types rule out,
+// and checks that they really don't compile.)
+//
+// The only way to get an Account<Verified> is to hand the unverified one to
+// EmailVerifier::verify with the right code, and the only way to get an
+// Account<Admin> is to be promoted by an existing admin. Each step takes
+// the old account by value, so the old state can't be used afterwards.
+//
+// The markers are zero-sized, so all of this is free at runtime: an
+// Account<Verified> is the same size as the User inside it.
+//
+// (This wraps User rather than making User itself generic so the registry,
+// sessions and JSON files all keep working with plain Users.)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Unverified;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Verified;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Admin;
+
+// Only the three markers above can be account states. The trait lives in a
+// private module, so nothing outside this file can implement it.
+mod sealed {
+    pub trait State {
+        const NAME: &'static str;
+    }
+}
+
+impl sealed::State for Unverified {
+    const NAME: &'static str = "unverified";
+}
+
+impl sealed::State for Verified {
+    const NAME: &'static str = "verified";
+}
+
+impl sealed::State for Admin {
+    const NAME: &'static str = "admin";
+}
+
+// Capabilities: which states are allowed to do what. Methods that need a
+// capability are written once, for every state that has it.
+pub trait CanSignIn: sealed::State {}
+
+impl CanSignIn for Verified {}
+impl CanSignIn for Admin {}
+
+pub struct Account<S: sealed::State> {
+    user: User,
+    state: PhantomData<S>,
+}
+
+impl<S: sealed::State> Account<S> {
+    // Read-only access is fine in any state; handing out &mut User would
+    // let someone change the email without re-verifying it.
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    pub fn into_user(self) -> User {
+        self.user
+    }
+
+    pub fn state(&self) -> &'static str {
+        S::NAME
+    }
+
+    // Moving to another state keeps the user and swaps the label. Private,
+    // so the only transitions are the ones written out below.
+    fn into_state<T: sealed::State>(self) -> Account<T> {
+        Account {
+            user: self.user,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S: sealed::State> fmt::Debug for Account<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Account")
+            .field("state", &S::NAME)
+            .field("user", &self.user)
+            .finish()
+    }
+}
+
+impl Account<Unverified> {
+    // Every account starts out unverified, whatever it was before.
+    pub fn new(user: User) -> Account<Unverified> {
+        Account {
+            user,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S: CanSignIn> Account<S> {
+    pub fn sign_in(&mut self, sessions: &mut SessionManager) -> Result<Session, SessionError> {
+        sessions.sign_in(&mut self.user)
+    }
+
+    // A new email hasn't been checked yet, so the account goes back to
+    // being unverified (an admin loses admin rights until they re-verify).
+    pub fn change_email(mut self, email: &str) -> Result<Account<Unverified>, EmailError> {
+        validate_email(email)?;
+        self.user.email = email.to_string();
+        Ok(self.into_state())
+    }
+}
+
+impl Account<Verified> {
+    // Needs an admin to vouch for it - there's no other way to make an
+    // Account<Admin>, short of being the first one (see bootstrap_admin).
+    pub fn promote(self, _by: &Account<Admin>) -> Account<Admin> {
+        self.into_state()
+    }
+}
+
+impl Account<Admin> {
+    // The very first admin has to come from somewhere. Taking a verified
+    // account means even they had to prove their email.
+    pub fn bootstrap_admin(account: Account<Verified>) -> Account<Admin> {
+        account.into_state()
+    }
+
+    pub fn demote(self) -> Account<Verified> {
+        self.into_state()
+    }
+
+    // Privileged methods: only callable on an Account<Admin>.
+    pub fn deactivate<S: sealed::State>(&self, account: &mut Account<S>) {
+        account.user.active = false;
+    }
+
+    pub fn reactivate<S: sealed::State>(&self, account: &mut Account<S>) {
+        account.user.active = true;
+    }
+}
+
+// EMAIL VERIFICATION
+
+// A six-digit code can be guessed in a million tries, so each code only
+// gets a few: after max_attempts wrong guesses it's thrown away, and the
+// account stays locked out until a new one is issued.
+pub const DEFAULT_MAX_VERIFY_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    NoCodeIssued,
+    WrongCode { attempts_left: u32 },
+    LockedOut,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::NoCodeIssued => write!(f, "no verification code was sent to that address"),
+            VerifyError::WrongCode { attempts_left } => {
+                write!(
+                    f,
+                    "that verification code is wrong ({attempts_left} attempts left)"
+                )
+            }
+            VerifyError::LockedOut => write!(f, "too many wrong codes - ask for a new one"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+#[derive(Debug)]
+struct Pending {
+    // None once the code has been guessed wrong too many times.
+    code: Option<String>,
+    failures: u32,
+}
+
+// Hands out six-digit codes (which would be emailed to the user) and swaps
+// an unverified account for a verified one when the right code comes back.
+#[derive(Debug)]
+pub struct EmailVerifier {
+    pub max_attempts: u32,
+    // email (lowercased) -> the code sent to it
+    codes: HashMap<String, Pending>,
+}
+
+impl Default for EmailVerifier {
+    fn default() -> Self {
+        EmailVerifier::new()
+    }
+}
+
+impl EmailVerifier {
+    pub fn new() -> EmailVerifier {
+        EmailVerifier {
+            max_attempts: DEFAULT_MAX_VERIFY_ATTEMPTS,
+            codes: HashMap::new(),
+        }
+    }
+
+    // Issuing a new code replaces any earlier one for the same address,
+    // and starts the count of wrong guesses again.
+    pub fn issue(&mut self, account: &Account<Unverified>) -> String {
+        let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+        self.codes.insert(
+            account.user.email.to_lowercase(),
+            Pending {
+                code: Some(code.clone()),
+                failures: 0,
+            },
+        );
+        code
+    }
+
+    // On failure the account comes back unchanged, so the caller can try
+    // again - it wasn't consumed by the attempt.
+    pub fn verify(
+        &mut self,
+        account: Account<Unverified>,
+        code: &str,
+    ) -> Result<Account<Verified>, (Account<Unverified>, VerifyError)> {
+        let key = account.user.email.to_lowercase();
+        let Some(pending) = self.codes.get_mut(&key) else {
+            return Err((account, VerifyError::NoCodeIssued));
+        };
+        let Some(expected) = &pending.code else {
+            return Err((account, VerifyError::LockedOut));
+        };
+        if expected != code.trim() {
+            pending.failures += 1;
+            if pending.failures >= self.max_attempts {
+                pending.code = None;
+                return Err((account, VerifyError::LockedOut));
+            }
+            let attempts_left = self.max_attempts - pending.failures;
+            return Err((account, VerifyError::WrongCode { attempts_left }));
+        }
+        // codes only work once.
+        self.codes.remove(&key);
+        Ok(account.into_state())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::build_user;
+
+    fn unverified() -> Account<Unverified> {
+        Account::new(build_user(
+            String::from("zed@example.com"),
+            String::from("zed"),
+        ))
+    }
+
+    #[test]
+    fn wrong_codes_count_down_then_lock_out() {
+        let mut verifier = EmailVerifier::new();
+        verifier.max_attempts = 3;
+        let mut account = unverified();
+        let code = verifier.issue(&account);
+
+        for attempts_left in [2, 1] {
+            let (back, e) = verifier.verify(account, "nope").unwrap_err();
+            assert_eq!(e, VerifyError::WrongCode { attempts_left });
+            account = back;
+        }
+        let (back, e) = verifier.verify(account, "nope").unwrap_err();
+        assert_eq!(e, VerifyError::LockedOut);
+        // even the right code is turned away now.
+        let (back, e) = verifier.verify(back, &code).unwrap_err();
+        assert_eq!(e, VerifyError::LockedOut);
+
+        // a new code starts over.
+        let code = verifier.issue(&back);
+        assert!(verifier.verify(back, &code).is_ok());
+    }
+
+    #[test]
+    fn codes_only_work_once() {
+        let mut verifier = EmailVerifier::new();
+        let account = unverified();
+        let code = verifier.issue(&account);
+        let verified = verifier.verify(account, &code).unwrap();
+        let again = verified.change_email("zed@example.com").unwrap();
+        let (_, e) = verifier.verify(again, &code).unwrap_err();
+        assert_eq!(e, VerifyError::NoCodeIssued);
+    }
+}
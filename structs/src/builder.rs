@@ -0,0 +1,215 @@
+use std::fmt;
+
+use crate::credentials::{KdfParams, PasswordError, PasswordPolicy};
+use crate::registry::{validate_email, validate_username, EmailError, UsernameError};
+use crate::user::User;
+
+// BUILDING A USER
+
+// build_user takes every field as an argument, which gets unwieldy as User
+// grows. A builder takes the required fields up front and lets the rest be
+// set one call at a time, each call handing the builder back so they chain:
+//
+//     let user = UserBuilder::new("ann", "ann@example.com")
+//         .active(false)
+//         .password("correct horse battery staple")
+//         .build()?;
+//
+// Nothing is checked until build(), which validates everything at once and
+// hashes the password if there is one.
+
+#[derive(Clone)]
+pub struct UserBuilder {
+    username: String,
+    email: String,
+    active: bool,
+    sign_in_count: u64,
+    password: Option<String>,
+    policy: PasswordPolicy,
+    params: KdfParams,
+}
+
+// Debug is written by hand, like Credentials', so the password (which is
+// still plain text until build() hashes it) never ends up in logs or dbg!
+// output. It only says whether there is one.
+impl fmt::Debug for UserBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserBuilder")
+            .field("username", &self.username)
+            .field("email", &self.email)
+            .field("active", &self.active)
+            .field("sign_in_count", &self.sign_in_count)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("policy", &self.policy)
+            .field("params", &self.params)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    Username(UsernameError),
+    Email(EmailError),
+    Password(PasswordError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::Username(e) => write!(f, "username {e}"),
+            BuildError::Email(e) => write!(f, "email {e}"),
+            BuildError::Password(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl UserBuilder {
+    pub fn new(username: impl Into<String>, email: impl Into<String>) -> UserBuilder {
+        UserBuilder {
+            username: username.into(),
+            email: email.into(),
+            active: true,
+            // unlike build_user, a brand new user hasn't signed in yet - the
+            // SessionManager counts sign-ins from here on.
+            sign_in_count: 0,
+            password: None,
+            policy: PasswordPolicy::default(),
+            params: KdfParams::default(),
+        }
+    }
+
+    pub fn active(mut self, active: bool) -> UserBuilder {
+        self.active = active;
+        self
+    }
+
+    pub fn sign_in_count(mut self, count: u64) -> UserBuilder {
+        self.sign_in_count = count;
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> UserBuilder {
+        self.password = Some(password.into());
+        self
+    }
+
+    pub fn password_policy(mut self, policy: PasswordPolicy) -> UserBuilder {
+        self.policy = policy;
+        self
+    }
+
+    pub fn kdf_params(mut self, params: KdfParams) -> UserBuilder {
+        self.params = params;
+        self
+    }
+
+    pub fn build(self) -> Result<User, BuildError> {
+        validate_username(&self.username).map_err(BuildError::Username)?;
+        validate_email(&self.email).map_err(BuildError::Email)?;
+
+        let mut user = User {
+            active: self.active,
+            username: self.username,
+            email: self.email,
+            sign_in_count: self.sign_in_count,
+            credentials: None,
+        };
+        if let Some(password) = self.password {
+            user.set_password(&password, &self.policy, &self.params)
+                .map_err(BuildError::Password)?;
+        }
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHEAP: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn defaults_for_a_new_user() {
+        let user = UserBuilder::new("ann", "ann@example.com").build().unwrap();
+        assert_eq!(user.username, "ann");
+        assert_eq!(user.email, "ann@example.com");
+        assert!(user.active);
+        assert_eq!(user.sign_in_count, 0);
+        assert_eq!(user.credentials, None);
+    }
+
+    #[test]
+    fn every_setter_lands_in_the_user() {
+        let user = UserBuilder::new("ann", "ann@example.com")
+            .active(false)
+            .sign_in_count(7)
+            .kdf_params(CHEAP)
+            .password("correct horse battery staple")
+            .build()
+            .unwrap();
+        assert!(!user.active);
+        assert_eq!(user.sign_in_count, 7);
+        let credentials = user.credentials.unwrap();
+        assert!(credentials.verify("correct horse battery staple"));
+        assert!(!credentials.needs_rehash(&CHEAP));
+    }
+
+    #[test]
+    fn invalid_fields_are_reported() {
+        assert!(matches!(
+            UserBuilder::new("", "ann@example.com").build(),
+            Err(BuildError::Username(UsernameError::TooShort { .. }))
+        ));
+        assert_eq!(
+            UserBuilder::new("9lives", "ann@example.com").build(),
+            Err(BuildError::Username(UsernameError::MustStartWithLetter))
+        );
+        assert_eq!(
+            UserBuilder::new("ann", "ann.example.com").build(),
+            Err(BuildError::Email(EmailError::MissingAt))
+        );
+        assert_eq!(
+            UserBuilder::new("ann", "ann@example.com")
+                .password("short")
+                .build(),
+            Err(BuildError::Password(PasswordError::TooShort { min: 12 }))
+        );
+        // the username is checked first.
+        assert!(matches!(
+            UserBuilder::new("", "").password("").build(),
+            Err(BuildError::Username(_))
+        ));
+    }
+
+    #[test]
+    fn the_password_policy_can_be_changed() {
+        let relaxed = PasswordPolicy {
+            min_length: 4,
+            min_character_classes: 1,
+            ..PasswordPolicy::default()
+        };
+        let user = UserBuilder::new("ann", "ann@example.com")
+            .password_policy(relaxed)
+            .kdf_params(CHEAP)
+            .password("tree")
+            .build()
+            .unwrap();
+        assert!(user.credentials.unwrap().verify("tree"));
+    }
+
+    #[test]
+    fn debug_hides_the_password() {
+        let builder = UserBuilder::new("ann", "ann@example.com").password("hunter2 hunter2");
+        let debug = format!("{builder:?}");
+        assert!(!debug.contains("hunter2"), "{debug}");
+        assert!(debug.contains(r#"password: Some("<redacted>")"#), "{debug}");
+        let debug = format!("{:?}", UserBuilder::new("ann", "ann@example.com"));
+        assert!(debug.contains("password: None"), "{debug}");
+    }
+}
@@ -0,0 +1,957 @@
+use std::fmt;
+use std::str::FromStr;
+
+// COLOURS
+
+// `struct Color(i32, i32, i32)` will happily hold (-5, 300, 12), which isn't
+// a colour. The types here can only hold valid values: constructors check
+// their inputs and return a ColorError instead of building a bad colour.
+//
+//   Rgb8 - red, green, blue, 0..=255 each (what hex codes describe)
+//   Rgba - Rgb8 plus alpha (opacity), 0.0..=1.0
+//   Hsl  - hue (degrees, 0..360), saturation and lightness (0.0..=1.0)
+//   Hsv  - hue, saturation and value (0.0..=1.0)
+//   Lab  - CIE L*a*b*, which is built so that equal distances look like
+//          equal differences to a person (L* is lightness, 0..=100)
+//
+// Conversions are From impls (Hsl::from(rgb), or rgb.into()). The float
+// types keep full precision, so converting Rgb8 -> Hsl -> Rgb8 gives back
+// exactly the colour you started with - rounding only happens when going
+// back to whole-number channels.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorError {
+    OutOfRange {
+        component: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    Parse(String),
+}
+
+impl fmt::Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColorError::OutOfRange {
+                component,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "{component} must be between {min} and {max}, got {value}"
+            ),
+            ColorError::Parse(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ColorError {}
+
+fn check(component: &'static str, value: f64, min: f64, max: f64) -> Result<f64, ColorError> {
+    if value.is_finite() && value >= min && value <= max {
+        Ok(value)
+    } else {
+        Err(ColorError::OutOfRange {
+            component,
+            value,
+            min,
+            max,
+        })
+    }
+}
+
+// Hue is an angle, so 370 degrees is the same as 10 and -30 is 330.
+fn wrap_hue(h: f64) -> f64 {
+    let h = h.rem_euclid(360.0);
+    if h >= 360.0 {
+        0.0
+    } else {
+        h
+    }
+}
+
+// THE TYPES
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb8 {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rgba {
+    pub rgb: Rgb8,
+    alpha: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsl {
+    h: f64,
+    s: f64,
+    l: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsv {
+    h: f64,
+    s: f64,
+    v: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lab {
+    l: f64,
+    a: f64,
+    b: f64,
+}
+
+impl Rgb8 {
+    pub const BLACK: Rgb8 = Rgb8 { r: 0, g: 0, b: 0 };
+    pub const WHITE: Rgb8 = Rgb8 {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+
+    // u8 channels can't be out of range, so this one can't fail.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Rgb8 {
+        Rgb8 { r, g, b }
+    }
+
+    // For values that come in as wider integers, like the old Color's i32s.
+    pub fn new(r: i32, g: i32, b: i32) -> Result<Rgb8, ColorError> {
+        let channel = |name, value: i32| {
+            u8::try_from(value).map_err(|_| ColorError::OutOfRange {
+                component: name,
+                value: value as f64,
+                min: 0.0,
+                max: 255.0,
+            })
+        };
+        Ok(Rgb8 {
+            r: channel("red", r)?,
+            g: channel("green", g)?,
+            b: channel("blue", b)?,
+        })
+    }
+
+    fn to_unit(self) -> [f64; 3] {
+        [self.r, self.g, self.b].map(|c| c as f64 / 255.0)
+    }
+
+    fn from_unit(rgb: [f64; 3]) -> Rgb8 {
+        let [r, g, b] = rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        Rgb8 { r, g, b }
+    }
+
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    pub fn to_css(self) -> String {
+        format!("rgb({}, {}, {})", self.r, self.g, self.b)
+    }
+}
+
+impl Rgba {
+    pub fn new(rgb: Rgb8, alpha: f64) -> Result<Rgba, ColorError> {
+        Ok(Rgba {
+            rgb,
+            alpha: check("alpha", alpha, 0.0, 1.0)?,
+        })
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    pub fn to_hex(self) -> String {
+        let alpha = (self.alpha * 255.0).round() as u8;
+        format!("{}{:02x}", self.rgb.to_hex(), alpha)
+    }
+
+    pub fn to_css(self) -> String {
+        let Rgb8 { r, g, b } = self.rgb;
+        format!("rgba({r}, {g}, {b}, {})", round_to(self.alpha, 3))
+    }
+}
+
+impl From<Rgb8> for Rgba {
+    fn from(rgb: Rgb8) -> Rgba {
+        Rgba { rgb, alpha: 1.0 }
+    }
+}
+
+impl Hsl {
+    pub fn new(h: f64, s: f64, l: f64) -> Result<Hsl, ColorError> {
+        Ok(Hsl {
+            h: wrap_hue(check("hue", h, f64::MIN, f64::MAX)?),
+            s: check("saturation", s, 0.0, 1.0)?,
+            l: check("lightness", l, 0.0, 1.0)?,
+        })
+    }
+
+    pub fn hue(&self) -> f64 {
+        self.h
+    }
+    pub fn saturation(&self) -> f64 {
+        self.s
+    }
+    pub fn lightness(&self) -> f64 {
+        self.l
+    }
+
+    pub fn to_css(self) -> String {
+        format!(
+            "hsl({}, {}%, {}%)",
+            round_to(self.h, 1),
+            round_to(self.s * 100.0, 1),
+            round_to(self.l * 100.0, 1)
+        )
+    }
+}
+
+impl Hsv {
+    pub fn new(h: f64, s: f64, v: f64) -> Result<Hsv, ColorError> {
+        Ok(Hsv {
+            h: wrap_hue(check("hue", h, f64::MIN, f64::MAX)?),
+            s: check("saturation", s, 0.0, 1.0)?,
+            v: check("value", v, 0.0, 1.0)?,
+        })
+    }
+
+    pub fn hue(&self) -> f64 {
+        self.h
+    }
+    pub fn saturation(&self) -> f64 {
+        self.s
+    }
+    pub fn value(&self) -> f64 {
+        self.v
+    }
+}
+
+impl Lab {
+    // a* and b* have no hard limits, but nothing in sRGB gets past about
+    // ±128, so anything further out is almost certainly a mistake.
+    pub fn new(l: f64, a: f64, b: f64) -> Result<Lab, ColorError> {
+        Ok(Lab {
+            l: check("L*", l, 0.0, 100.0)?,
+            a: check("a*", a, -128.0, 128.0)?,
+            b: check("b*", b, -128.0, 128.0)?,
+        })
+    }
+
+    pub fn lightness(&self) -> f64 {
+        self.l
+    }
+    pub fn a(&self) -> f64 {
+        self.a
+    }
+    pub fn b(&self) -> f64 {
+        self.b
+    }
+
+    // How different two colours look ("delta E", CIE76). Around 2.3 is
+    // the smallest difference most people can notice.
+    pub fn delta_e(&self, other: &Lab) -> f64 {
+        ((self.l - other.l).powi(2) + (self.a - other.a).powi(2) + (self.b - other.b).powi(2))
+            .sqrt()
+    }
+}
+
+// CONVERSIONS
+
+impl From<Rgb8> for Hsl {
+    fn from(rgb: Rgb8) -> Hsl {
+        let [r, g, b] = rgb.to_unit();
+        let (h, max, min) = hue_max_min(r, g, b);
+        let l = (max + min) / 2.0;
+        let chroma = max - min;
+        let s = if chroma == 0.0 {
+            0.0
+        } else {
+            chroma / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        Hsl {
+            h,
+            s: s.clamp(0.0, 1.0),
+            l,
+        }
+    }
+}
+
+impl From<Hsl> for Rgb8 {
+    fn from(hsl: Hsl) -> Rgb8 {
+        let chroma = (1.0 - (2.0 * hsl.l - 1.0).abs()) * hsl.s;
+        Rgb8::from_unit(from_hue(hsl.h, chroma, hsl.l - chroma / 2.0))
+    }
+}
+
+impl From<Rgb8> for Hsv {
+    fn from(rgb: Rgb8) -> Hsv {
+        let [r, g, b] = rgb.to_unit();
+        let (h, max, min) = hue_max_min(r, g, b);
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        Hsv { h, s, v: max }
+    }
+}
+
+impl From<Hsv> for Rgb8 {
+    fn from(hsv: Hsv) -> Rgb8 {
+        let chroma = hsv.v * hsv.s;
+        Rgb8::from_unit(from_hue(hsv.h, chroma, hsv.v - chroma))
+    }
+}
+
+impl From<Hsl> for Hsv {
+    fn from(hsl: Hsl) -> Hsv {
+        let v = hsl.l + hsl.s * hsl.l.min(1.0 - hsl.l);
+        let s = if v == 0.0 {
+            0.0
+        } else {
+            2.0 * (1.0 - hsl.l / v)
+        };
+        Hsv { h: hsl.h, s, v }
+    }
+}
+
+impl From<Hsv> for Hsl {
+    fn from(hsv: Hsv) -> Hsl {
+        let l = hsv.v * (1.0 - hsv.s / 2.0);
+        let s = if l == 0.0 || l == 1.0 {
+            0.0
+        } else {
+            (hsv.v - l) / l.min(1.0 - l)
+        };
+        Hsl { h: hsv.h, s, l }
+    }
+}
+
+// The hue is which of the six 60-degree slices of the colour wheel we're
+// in, plus how far along it. Returns (hue, max channel, min channel).
+fn hue_max_min(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let h = if chroma == 0.0 {
+        0.0 // greys have no hue - 0 by convention.
+    } else if max == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+    (wrap_hue(h), max, min)
+}
+
+// The reverse: spread `chroma` across the channels according to the hue,
+// then lift everything by `m`.
+fn from_hue(h: f64, chroma: f64, m: f64) -> [f64; 3] {
+    let slice = h / 60.0;
+    let x = chroma * (1.0 - (slice.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match slice as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    [r + m, g + m, b + m]
+}
+
+// sRGB stores brightness on a curve (more steps for darks, where eyes are
+// more sensitive). Maths on light - mixing, luminance - needs the straight
+// "linear" values instead.
+fn to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// D65 white point - the "white" of ordinary daylight that sRGB is built on.
+const WHITE_X: f64 = 0.95047;
+const WHITE_Y: f64 = 1.0;
+const WHITE_Z: f64 = 1.08883;
+
+impl From<Rgb8> for Lab {
+    fn from(rgb: Rgb8) -> Lab {
+        let [r, g, b] = rgb.to_unit().map(to_linear);
+        // linear sRGB -> CIE XYZ
+        let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = 0.0193339 * r + 0.1191920 * g + 0.9503041 * b;
+
+        let f = |t: f64| {
+            const DELTA: f64 = 6.0 / 29.0;
+            if t > DELTA.powi(3) {
+                t.cbrt()
+            } else {
+                t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+            }
+        };
+        let (fx, fy, fz) = (f(x / WHITE_X), f(y / WHITE_Y), f(z / WHITE_Z));
+        Lab {
+            l: (116.0 * fy - 16.0).clamp(0.0, 100.0),
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+}
+
+impl From<Lab> for Rgb8 {
+    fn from(lab: Lab) -> Rgb8 {
+        let fy = (lab.l + 16.0) / 116.0;
+        let fx = fy + lab.a / 500.0;
+        let fz = fy - lab.b / 200.0;
+        let f_inv = |t: f64| {
+            const DELTA: f64 = 6.0 / 29.0;
+            if t > DELTA {
+                t.powi(3)
+            } else {
+                3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+            }
+        };
+        let (x, y, z) = (
+            WHITE_X * f_inv(fx),
+            WHITE_Y * f_inv(fy),
+            WHITE_Z * f_inv(fz),
+        );
+        // CIE XYZ -> linear sRGB
+        let r = 3.2404542 * x - 1.5371385 * y - 0.4985314 * z;
+        let g = -0.9692660 * x + 1.8760108 * y + 0.0415560 * z;
+        let b = 0.0556434 * x - 0.2040259 * y + 1.0572252 * z;
+        Rgb8::from_unit([r, g, b].map(from_linear))
+    }
+}
+
+// PARSING
+
+// Accepts the common ways of writing a colour in CSS:
+//   #rgb, #rgba, #rrggbb, #rrggbbaa
+//   rgb(255, 0, 0)  rgb(100% 0% 0%)  rgba(255, 0, 0, 0.5)  rgb(255 0 0 / 50%)
+//   hsl(120, 100%, 50%)  hsla(120deg 100% 50% / 0.5)
+impl FromStr for Rgba {
+    type Err = ColorError;
+
+    fn from_str(input: &str) -> Result<Rgba, ColorError> {
+        let text = input.trim().to_ascii_lowercase();
+        if let Some(hex) = text.strip_prefix('#') {
+            return parse_hex(hex);
+        }
+        let (name, args) = text
+            .strip_suffix(')')
+            .and_then(|t| t.split_once('('))
+            .ok_or_else(|| ColorError::Parse(format!("'{input}' is not a colour")))?;
+
+        let (values, alpha) = split_args(args)?;
+        let alpha = match alpha {
+            Some(alpha) => parse_number(alpha, 1.0, "alpha")?,
+            None => 1.0,
+        };
+
+        let rgb = match name.trim() {
+            "rgb" | "rgba" => {
+                let [r, g, b] = values.map(|v| parse_number(v, 255.0, "channel"));
+                Rgb8::new(round(r?), round(g?), round(b?))?
+            }
+            "hsl" | "hsla" => {
+                let [h, s, l] = values;
+                let h = h.strip_suffix("deg").unwrap_or(h);
+                let h: f64 = h
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|h: &f64| h.is_finite())
+                    .ok_or_else(|| ColorError::Parse(format!("'{h}' is not a hue")))?;
+                let s = parse_percent(s, "saturation")?;
+                let l = parse_percent(l, "lightness")?;
+                Rgb8::from(Hsl::new(h, s, l)?)
+            }
+            other => {
+                return Err(ColorError::Parse(format!(
+                    "unknown colour function '{other}'"
+                )))
+            }
+        };
+        Rgba::new(rgb, alpha)
+    }
+}
+
+impl FromStr for Rgb8 {
+    type Err = ColorError;
+
+    // Same formats as Rgba, but the colour has to be fully opaque.
+    fn from_str(input: &str) -> Result<Rgb8, ColorError> {
+        let rgba: Rgba = input.parse()?;
+        if rgba.alpha < 1.0 {
+            return Err(ColorError::Parse(format!(
+                "'{input}' is transparent - parse it as an Rgba instead"
+            )));
+        }
+        Ok(rgba.rgb)
+    }
+}
+
+fn round(value: f64) -> i32 {
+    value.round() as i32
+}
+
+fn parse_hex(hex: &str) -> Result<Rgba, ColorError> {
+    let digit = |c: char| {
+        c.to_digit(16)
+            .map(|d| d as u8)
+            .ok_or_else(|| ColorError::Parse(format!("'{c}' is not a hex digit")))
+    };
+    let digits: Vec<u8> = hex.chars().map(digit).collect::<Result<_, _>>()?;
+    let channels: Vec<u8> = match digits.len() {
+        // short form: #f80 is #ff8800
+        3 | 4 => digits.iter().map(|d| d * 17).collect(),
+        6 | 8 => digits
+            .chunks(2)
+            .map(|pair| pair[0] * 16 + pair[1])
+            .collect(),
+        n => {
+            return Err(ColorError::Parse(format!(
+                "hex colours have 3, 4, 6 or 8 digits, not {n}"
+            )))
+        }
+    };
+    let alpha = channels.get(3).map_or(1.0, |&a| a as f64 / 255.0);
+    Rgba::new(Rgb8::rgb(channels[0], channels[1], channels[2]), alpha)
+}
+
+// "255, 0, 0", "255 0 0 / 0.5" and "255, 0, 0, 0.5" -> three values and an
+// optional alpha.
+fn split_args(args: &str) -> Result<([&str; 3], Option<&str>), ColorError> {
+    let (args, slash_alpha) = match args.split_once('/') {
+        Some((args, alpha)) => (args, Some(alpha.trim())),
+        None => (args, None),
+    };
+    let parts: Vec<&str> = args
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect();
+    match (parts.as_slice(), slash_alpha) {
+        ([a, b, c], alpha) => Ok(([a, b, c], alpha)),
+        ([a, b, c, alpha], None) => Ok(([a, b, c], Some(alpha))),
+        _ => Err(ColorError::Parse(format!(
+            "expected 3 values and an optional alpha, got '{args}'"
+        ))),
+    }
+}
+
+// A plain number, or a percentage of `full`. f64's parser also takes
+// "nan" and "inf", which would turn into 0 or 255 when rounded to a
+// channel, so only finite numbers are let through.
+fn parse_number(text: &str, full: f64, what: &str) -> Result<f64, ColorError> {
+    let text = text.trim();
+    let result = match text.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().map(|p| p / 100.0 * full),
+        None => text.parse::<f64>(),
+    };
+    result
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| ColorError::Parse(format!("'{text}' is not a valid {what}")))
+}
+
+fn parse_percent(text: &str, what: &str) -> Result<f64, ColorError> {
+    let percent = text
+        .trim()
+        .strip_suffix('%')
+        .ok_or_else(|| ColorError::Parse(format!("{what} must be a percentage, got '{text}'")))?;
+    percent
+        .parse::<f64>()
+        .ok()
+        .filter(|p| p.is_finite())
+        .map(|p| p / 100.0)
+        .ok_or_else(|| ColorError::Parse(format!("'{text}' is not a valid {what}")))
+}
+
+fn round_to(value: f64, places: i32) -> f64 {
+    let scale = 10f64.powi(places);
+    (value * scale).round() / scale
+}
+
+impl fmt::Display for Rgb8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.alpha == 1.0 {
+            f.write_str(&self.rgb.to_hex())
+        } else {
+            f.write_str(&self.to_hex())
+        }
+    }
+}
+
+impl fmt::Display for Hsl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_css())
+    }
+}
+
+// BLENDING
+
+// The blend modes from CSS / image editors. Each one says how to combine a
+// channel of the colour underneath (the backdrop) with the one on top
+// (the source), working on 0.0..=1.0 values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Multiply,   // always darker - like stacking two slides
+    Screen,     // always lighter - the opposite of multiply
+    Overlay,    // multiply the darks, screen the lights
+    Darken,     // keep the darker channel
+    Lighten,    // keep the lighter channel
+    Difference, // how far apart the channels are
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 7] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::Difference,
+    ];
+
+    fn channel(self, backdrop: f64, source: f64) -> f64 {
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => backdrop * source,
+            BlendMode::Screen => backdrop + source - backdrop * source,
+            BlendMode::Overlay => {
+                if backdrop <= 0.5 {
+                    2.0 * backdrop * source
+                } else {
+                    1.0 - 2.0 * (1.0 - backdrop) * (1.0 - source)
+                }
+            }
+            BlendMode::Darken => backdrop.min(source),
+            BlendMode::Lighten => backdrop.max(source),
+            BlendMode::Difference => (backdrop - source).abs(),
+        }
+    }
+}
+
+// Puts `source` on top of `backdrop` using `mode`, then composites with
+// the usual "source over" rule, so a half-transparent source lets half of
+// the backdrop show through.
+pub fn blend(backdrop: Rgba, source: Rgba, mode: BlendMode) -> Rgba {
+    let (ab, as_) = (backdrop.alpha, source.alpha);
+    let cb = backdrop.rgb.to_unit();
+    let cs = source.rgb.to_unit();
+
+    let alpha = as_ + ab * (1.0 - as_);
+    if alpha == 0.0 {
+        return Rgba {
+            rgb: Rgb8::BLACK,
+            alpha: 0.0,
+        };
+    }
+
+    let mut out = [0.0; 3];
+    for i in 0..3 {
+        // where both are present, the blend mode decides the colour.
+        let mixed = (1.0 - ab) * cs[i] + ab * mode.channel(cb[i], cs[i]);
+        out[i] = (as_ * mixed + ab * cb[i] * (1.0 - as_)) / alpha;
+    }
+    Rgba {
+        rgb: Rgb8::from_unit(out),
+        alpha,
+    }
+}
+
+// CONTRAST (WCAG)
+
+// How bright a colour looks, from 0.0 (black) to 1.0 (white). Green counts
+// for much more than blue because our eyes are far more sensitive to it.
+pub fn relative_luminance(rgb: Rgb8) -> f64 {
+    let [r, g, b] = rgb.to_unit().map(to_linear);
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+// From 1:1 (the same colour) up to 21:1 (black on white). The order of
+// the arguments doesn't matter.
+pub fn contrast_ratio(a: Rgb8, b: Rgb8) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (light, dark) = if la > lb { (la, lb) } else { (lb, la) };
+    (light + 0.05) / (dark + 0.05)
+}
+
+// (clippy would rather these were Aa and Aaa, but AA and AAA are their names.)
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WcagLevel {
+    Fail,
+    AA,
+    AAA,
+}
+
+// Large text is at least 18pt, or 14pt bold - it gets lower thresholds
+// because it's easier to read.
+pub fn wcag_level(foreground: Rgb8, background: Rgb8, large_text: bool) -> WcagLevel {
+    let ratio = contrast_ratio(foreground, background);
+    let (aa, aaa) = if large_text { (3.0, 4.5) } else { (4.5, 7.0) };
+    if ratio >= aaa {
+        WcagLevel::AAA
+    } else if ratio >= aa {
+        WcagLevel::AA
+    } else {
+        WcagLevel::Fail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(input: &str) -> Rgba {
+        input.parse().unwrap_or_else(|e| panic!("{input}: {e}"))
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    // 2^24 colours, each through both float types and back.
+    #[test]
+    fn every_colour_round_trips_through_hsl_and_hsv() {
+        for value in 0..1u32 << 24 {
+            let [_, r, g, b] = value.to_be_bytes();
+            let rgb = Rgb8::rgb(r, g, b);
+            assert_eq!(Rgb8::from(Hsl::from(rgb)), rgb);
+            assert_eq!(Rgb8::from(Hsv::from(rgb)), rgb);
+        }
+    }
+
+    #[test]
+    fn hsl_and_hsv_convert_between_each_other() {
+        for rgb in [Rgb8::rgb(255, 136, 0), Rgb8::rgb(18, 52, 86), Rgb8::WHITE] {
+            let hsl = Hsl::from(rgb);
+            assert_eq!(Rgb8::from(Hsv::from(hsl)), rgb);
+            assert_eq!(Rgb8::from(Hsl::from(Hsv::from(rgb))), rgb);
+        }
+    }
+
+    #[test]
+    fn lab_round_trips_and_measures_difference() {
+        for rgb in [
+            Rgb8::BLACK,
+            Rgb8::WHITE,
+            Rgb8::rgb(255, 0, 0),
+            Rgb8::rgb(18, 52, 86),
+        ] {
+            assert_eq!(Rgb8::from(Lab::from(rgb)), rgb);
+        }
+        let white = Lab::from(Rgb8::WHITE);
+        assert!((white.lightness() - 100.0).abs() < 1e-3);
+        assert!(white.a().abs() < 1e-3 && white.b().abs() < 1e-3);
+        assert!(close(
+            Lab::from(Rgb8::BLACK).delta_e(&Lab::from(Rgb8::BLACK)),
+            0.0
+        ));
+        assert!((Lab::from(Rgb8::BLACK).delta_e(&white) - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn constructors_turn_away_bad_values() {
+        assert_eq!(Rgb8::new(255, 0, 12), Ok(Rgb8::rgb(255, 0, 12)));
+        assert_eq!(
+            Rgb8::new(-5, 300, 12),
+            Err(ColorError::OutOfRange {
+                component: "red",
+                value: -5.0,
+                min: 0.0,
+                max: 255.0
+            })
+        );
+        assert!(Rgba::new(Rgb8::BLACK, 1.5).is_err());
+        assert!(Rgba::new(Rgb8::BLACK, f64::NAN).is_err());
+        assert!(Hsl::new(0.0, 1.1, 0.5).is_err());
+        assert!(Hsl::new(f64::INFINITY, 1.0, 0.5).is_err());
+        assert!(Hsv::new(0.0, 0.5, -0.1).is_err());
+        assert!(Lab::new(50.0, 200.0, 0.0).is_err());
+        // hues wrap around instead.
+        assert_eq!(Hsl::new(370.0, 1.0, 0.5).unwrap().hue(), 10.0);
+        assert_eq!(Hsv::new(-30.0, 1.0, 0.5).unwrap().hue(), 330.0);
+        assert_eq!(Hsl::new(-360.0, 1.0, 0.5).unwrap().hue(), 0.0);
+    }
+
+    #[test]
+    fn parses_hex() {
+        assert_eq!(rgba("#f80"), Rgb8::rgb(255, 136, 0).into());
+        assert_eq!(rgba("#FF8800"), Rgb8::rgb(255, 136, 0).into());
+        assert_eq!(rgba("#f808").alpha(), 136.0 / 255.0);
+        let half = rgba("  #12345680 ");
+        assert_eq!(half.rgb, Rgb8::rgb(0x12, 0x34, 0x56));
+        assert_eq!(half.alpha(), 128.0 / 255.0);
+        assert_eq!(half.to_hex(), "#12345680");
+        assert_eq!(half.to_string(), "#12345680");
+        assert_eq!(rgba("#123456").to_string(), "#123456");
+        for bad in ["#12345", "#", "#ggg", "#1234567890"] {
+            assert!(bad.parse::<Rgba>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn parses_rgb_functions() {
+        let red = Rgba::from(Rgb8::rgb(255, 0, 0));
+        assert_eq!(rgba("rgb(255, 0, 0)"), red);
+        assert_eq!(rgba("RGB(100% 0% 0%)"), red);
+        assert_eq!(rgba("rgb(254.6, 0.4, 0)"), red);
+        let half = Rgba::new(Rgb8::rgb(255, 0, 0), 0.5).unwrap();
+        assert_eq!(rgba("rgba(255, 0, 0, 0.5)"), half);
+        assert_eq!(rgba("rgb(255 0 0 / 50%)"), half);
+        assert_eq!(half.to_css(), "rgba(255, 0, 0, 0.5)");
+        assert_eq!(Rgb8::rgb(255, 0, 0).to_css(), "rgb(255, 0, 0)");
+
+        assert!(matches!(
+            "rgb(256, 0, 0)".parse::<Rgba>(),
+            Err(ColorError::OutOfRange {
+                component: "red",
+                ..
+            })
+        ));
+        assert!("rgb(255, 0)".parse::<Rgba>().is_err());
+        assert!("rgb(1, 2, 3, 4, 5)".parse::<Rgba>().is_err());
+        assert!("rgb(255, 0, 0".parse::<Rgba>().is_err());
+        assert!("rgba(0, 0, 0, 2)".parse::<Rgba>().is_err());
+        assert_eq!(
+            "cmyk(0, 0, 0)".parse::<Rgba>(),
+            Err(ColorError::Parse(String::from(
+                "unknown colour function 'cmyk'"
+            )))
+        );
+    }
+
+    #[test]
+    fn parses_hsl_functions() {
+        assert_eq!(rgba("hsl(120, 100%, 50%)"), Rgb8::rgb(0, 255, 0).into());
+        assert_eq!(rgba("hsl(480, 100%, 50%)"), Rgb8::rgb(0, 255, 0).into());
+        let half = rgba("hsla(120deg 100% 25% / 0.5)");
+        assert_eq!(half.rgb, Rgb8::rgb(0, 128, 0));
+        assert_eq!(half.alpha(), 0.5);
+        assert!("hsl(120, 100, 50%)".parse::<Rgba>().is_err());
+        assert!("hsl(120, 100%, 150%)".parse::<Rgba>().is_err());
+        assert_eq!(
+            Hsl::new(210.0, 0.65, 0.2).unwrap().to_css(),
+            "hsl(210, 65%, 20%)"
+        );
+    }
+
+    #[test]
+    fn nan_and_infinity_are_not_numbers_here() {
+        for bad in [
+            "rgb(nan, 0, 0)",
+            "rgb(inf, 0, 0)",
+            "rgb(0, -inf, 0)",
+            "rgb(0, 0, nan%)",
+            "rgba(0, 0, 0, nan)",
+            "rgb(0 0 0 / inf)",
+            "hsl(nan, 100%, 50%)",
+            "hsl(infdeg, 100%, 50%)",
+            "hsl(120, nan%, 50%)",
+            "hsl(120, 100%, inf%)",
+        ] {
+            assert!(
+                matches!(bad.parse::<Rgba>(), Err(ColorError::Parse(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn rgb8_must_be_opaque() {
+        assert_eq!("#0000ff".parse::<Rgb8>(), Ok(Rgb8::rgb(0, 0, 255)));
+        assert_eq!("#0000ffff".parse::<Rgb8>(), Ok(Rgb8::rgb(0, 0, 255)));
+        assert!("#0000ff80".parse::<Rgb8>().is_err());
+    }
+
+    #[test]
+    fn blending() {
+        let red = Rgba::from(Rgb8::rgb(255, 0, 0));
+        let white = Rgba::from(Rgb8::WHITE);
+        let black = Rgba::from(Rgb8::BLACK);
+        let grey = Rgba::from(Rgb8::rgb(128, 128, 128));
+
+        assert_eq!(blend(white, red, BlendMode::Normal), red);
+        assert_eq!(blend(white, red, BlendMode::Multiply), red);
+        assert_eq!(blend(red, black, BlendMode::Multiply), black);
+        assert_eq!(blend(red, black, BlendMode::Screen), red);
+        assert_eq!(blend(red, white, BlendMode::Screen), white);
+        assert_eq!(
+            blend(red, white, BlendMode::Difference),
+            Rgb8::rgb(0, 255, 255).into()
+        );
+        assert_eq!(
+            blend(grey, red, BlendMode::Darken),
+            Rgb8::rgb(128, 0, 0).into()
+        );
+        assert_eq!(
+            blend(grey, red, BlendMode::Lighten),
+            Rgb8::rgb(255, 128, 128).into()
+        );
+        // a dark backdrop multiplies, a light one screens.
+        assert_eq!(blend(black, grey, BlendMode::Overlay), black);
+        assert_eq!(blend(white, grey, BlendMode::Overlay), white);
+
+        // half a white on black lets half the black through...
+        let half_white = Rgba::new(Rgb8::WHITE, 0.5).unwrap();
+        assert_eq!(blend(black, half_white, BlendMode::Normal), grey);
+        // ...and on nothing at all it's still half-transparent white.
+        let clear = Rgba::new(Rgb8::BLACK, 0.0).unwrap();
+        assert_eq!(blend(clear, half_white, BlendMode::Multiply), half_white);
+        assert_eq!(blend(clear, clear, BlendMode::Screen), clear);
+
+        for mode in BlendMode::ALL {
+            assert_eq!(blend(red, clear, mode), red, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn wcag_contrast() {
+        assert!(close(contrast_ratio(Rgb8::BLACK, Rgb8::WHITE), 21.0));
+        assert!(close(contrast_ratio(Rgb8::WHITE, Rgb8::BLACK), 21.0));
+        assert!(close(
+            contrast_ratio(Rgb8::rgb(1, 2, 3), Rgb8::rgb(1, 2, 3)),
+            1.0
+        ));
+        assert!(close(relative_luminance(Rgb8::WHITE), 1.0));
+        assert!(close(relative_luminance(Rgb8::BLACK), 0.0));
+
+        // #767676 is the lightest grey that passes AA on white; #777777 isn't.
+        let passes = Rgb8::rgb(0x76, 0x76, 0x76);
+        let fails = Rgb8::rgb(0x77, 0x77, 0x77);
+        assert!((contrast_ratio(passes, Rgb8::WHITE) - 4.54).abs() < 0.01);
+        assert!((contrast_ratio(fails, Rgb8::WHITE) - 4.48).abs() < 0.01);
+        assert_eq!(wcag_level(passes, Rgb8::WHITE, false), WcagLevel::AA);
+        assert_eq!(wcag_level(fails, Rgb8::WHITE, false), WcagLevel::Fail);
+        assert_eq!(wcag_level(fails, Rgb8::WHITE, true), WcagLevel::AA);
+        assert_eq!(wcag_level(passes, Rgb8::WHITE, true), WcagLevel::AAA);
+        assert_eq!(wcag_level(Rgb8::BLACK, Rgb8::WHITE, false), WcagLevel::AAA);
+    }
+}
@@ -0,0 +1,465 @@
+use std::fmt;
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+
+use crate::user::User;
+
+// PASSWORDS
+
+// A User can have a password, but we never keep the password itself. We
+// keep an Argon2id hash of it instead: a slow, memory-hungry key derivation
+// function, so someone who steals the hashes can't try billions of guesses a
+// second. Each hash gets its own random salt, so two users with the same
+// password end up with different hashes.
+
+// The stored string looks like
+//   $argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>
+// It carries the parameters it was made with, which is how we can tell when
+// an old hash should be redone with stronger settings.
+
+// In JSON it's just the string. Reading one back goes through from_hash,
+// so a file with a mangled hash fails to load instead of giving a user a
+// password nothing can match.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Credentials {
+    hash: String,
+}
+
+impl TryFrom<String> for Credentials {
+    type Error = PasswordError;
+
+    fn try_from(hash: String) -> Result<Self, Self::Error> {
+        Credentials::from_hash(&hash)
+    }
+}
+
+impl From<Credentials> for String {
+    fn from(credentials: Credentials) -> String {
+        credentials.hash
+    }
+}
+
+// Debug is written by hand so the hash never ends up in logs or dbg!
+// output (hashes can still be attacked offline).
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Credentials { hash: <redacted> }")
+    }
+}
+
+// How expensive hashing is. The defaults are the OWASP recommendation for
+// Argon2id: 19 MiB of memory, 2 passes, 1 lane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    fn hasher(&self) -> Result<Argon2<'static>, PasswordError> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| PasswordError::Hashing(e.to_string()))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+// PASSWORD POLICY
+
+// Length matters far more than symbol rules, so the policy mostly asks for
+// a long password. It also turns away the most common passwords and ones
+// that contain the username or email. The maximum length stops someone from
+// making us hash a megabyte of text on every sign-in attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub min_character_classes: usize, // lowercase, uppercase, digits, symbols
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 12,
+            max_length: 128,
+            min_character_classes: 2,
+        }
+    }
+}
+
+// Compared ignoring case, but only whole passwords: "password" is turned
+// away, "password-for-my-garden-shed" isn't.
+const COMMON_PASSWORDS: [&str; 16] = [
+    "password",
+    "123456",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "qwerty",
+    "qwertyuiop",
+    "letmein",
+    "iloveyou",
+    "admin",
+    "welcome",
+    "passw0rd",
+    "password1234",
+    "123456789012",
+    "qwerty123456",
+    "1q2w3e4r5t6y",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordError {
+    TooShort { min: usize },
+    TooLong { max: usize },
+    TooFewCharacterClasses { min: usize },
+    TooCommon,
+    ContainsUsername,
+    ContainsEmail,
+    NoPasswordSet,
+    Hashing(String),
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PasswordError::TooShort { min } => {
+                write!(f, "password must be at least {min} characters")
+            }
+            PasswordError::TooLong { max } => {
+                write!(f, "password must be at most {max} characters")
+            }
+            PasswordError::TooFewCharacterClasses { min } => write!(
+                f,
+                "password must mix at least {min} of: lowercase, uppercase, digits, symbols"
+            ),
+            PasswordError::TooCommon => write!(f, "password is too common"),
+            PasswordError::ContainsUsername => write!(f, "password can't contain the username"),
+            PasswordError::ContainsEmail => write!(f, "password can't contain the email address"),
+            PasswordError::NoPasswordSet => write!(f, "user has no password"),
+            PasswordError::Hashing(e) => write!(f, "couldn't hash password: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PasswordError {}
+
+impl PasswordPolicy {
+    pub fn check(&self, password: &str, user: &User) -> Result<(), PasswordError> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(PasswordError::TooShort {
+                min: self.min_length,
+            });
+        }
+        if length > self.max_length {
+            return Err(PasswordError::TooLong {
+                max: self.max_length,
+            });
+        }
+
+        let classes = [
+            password.chars().any(|c| c.is_lowercase()),
+            password.chars().any(|c| c.is_uppercase()),
+            password.chars().any(|c| c.is_ascii_digit()),
+            password.chars().any(|c| !c.is_alphanumeric()),
+        ];
+        if classes.iter().filter(|&&has| has).count() < self.min_character_classes {
+            return Err(PasswordError::TooFewCharacterClasses {
+                min: self.min_character_classes,
+            });
+        }
+
+        let lowered = password.to_lowercase();
+        if COMMON_PASSWORDS.contains(&lowered.as_str()) {
+            return Err(PasswordError::TooCommon);
+        }
+        if !user.username.is_empty() && lowered.contains(&user.username.to_lowercase()) {
+            return Err(PasswordError::ContainsUsername);
+        }
+        let local_part = user.email.split('@').next().unwrap_or_default();
+        if local_part.len() >= 3 && lowered.contains(&local_part.to_lowercase()) {
+            return Err(PasswordError::ContainsEmail);
+        }
+        Ok(())
+    }
+}
+
+impl Credentials {
+    // Wraps a hash string that was stored earlier (e.g. read from a file).
+    pub fn from_hash(hash: &str) -> Result<Credentials, PasswordError> {
+        PasswordHash::new(hash).map_err(|e| PasswordError::Hashing(e.to_string()))?;
+        Ok(Credentials {
+            hash: hash.to_string(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.hash
+    }
+
+    pub fn hash(password: &str, params: &KdfParams) -> Result<Credentials, PasswordError> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = params
+            .hasher()?
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| PasswordError::Hashing(e.to_string()))?;
+        Ok(Credentials {
+            hash: hash.to_string(),
+        })
+    }
+
+    // Hashes the attempt with the stored salt and parameters and compares
+    // the result. The comparison takes the same time however many bytes
+    // match, so timing it tells an attacker nothing about the real hash.
+    pub fn verify(&self, password: &str) -> bool {
+        let Ok(stored) = PasswordHash::new(&self.hash) else {
+            return false;
+        };
+        Argon2::default()
+            .verify_password(password.as_bytes(), &stored)
+            .is_ok()
+    }
+
+    // True if this hash was made with different settings than `params` (or
+    // with a different algorithm), meaning it should be redone.
+    pub fn needs_rehash(&self, params: &KdfParams) -> bool {
+        let Ok(stored) = PasswordHash::new(&self.hash) else {
+            return true;
+        };
+        let Ok(stored_params) = Params::try_from(&stored) else {
+            return true;
+        };
+        stored.algorithm != Algorithm::Argon2id.ident()
+            || stored.version != Some(Version::V0x13.into())
+            || stored_params.m_cost() != params.memory_kib
+            || stored_params.t_cost() != params.iterations
+            || stored_params.p_cost() != params.parallelism
+    }
+}
+
+impl User {
+    pub fn set_password(
+        &mut self,
+        password: &str,
+        policy: &PasswordPolicy,
+        params: &KdfParams,
+    ) -> Result<(), PasswordError> {
+        policy.check(password, self)?;
+        self.credentials = Some(Credentials::hash(password, params)?);
+        Ok(())
+    }
+
+    // Checks a password, and if it's right but the stored hash is out of
+    // date, quietly replaces it with one made with `params`. That's the only
+    // time we can rehash - it's the only time we have the password.
+    // Passwords longer than the policy allows are turned away before
+    // hashing, so nobody can make us hash a megabyte per attempt.
+    pub fn check_password(
+        &mut self,
+        password: &str,
+        policy: &PasswordPolicy,
+        params: &KdfParams,
+    ) -> Result<bool, PasswordError> {
+        if password.chars().count() > policy.max_length {
+            return Err(PasswordError::TooLong {
+                max: policy.max_length,
+            });
+        }
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or(PasswordError::NoPasswordSet)?;
+        if !credentials.verify(password) {
+            return Ok(false);
+        }
+        if credentials.needs_rehash(params) {
+            self.credentials = Some(Credentials::hash(password, params)?);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::build_user;
+
+    // The real defaults take a while per hash; these keep the tests quick.
+    const CHEAP: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn ann() -> User {
+        build_user(String::from("kowalski@example.com"), String::from("ann"))
+    }
+
+    fn check(password: &str) -> Result<(), PasswordError> {
+        PasswordPolicy::default().check(password, &ann())
+    }
+
+    #[test]
+    fn a_hash_verifies_its_own_password_only() {
+        let credentials = Credentials::hash("correct horse battery", &CHEAP).unwrap();
+        assert!(credentials
+            .as_str()
+            .starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+        assert!(credentials.verify("correct horse battery"));
+        assert!(!credentials.verify("correct horse battery "));
+        assert!(!credentials.verify(""));
+        // the same password gets a different salt each time.
+        let again = Credentials::hash("correct horse battery", &CHEAP).unwrap();
+        assert_ne!(credentials, again);
+    }
+
+    #[test]
+    fn changed_params_need_a_rehash() {
+        let credentials = Credentials::hash("correct horse battery", &CHEAP).unwrap();
+        assert!(!credentials.needs_rehash(&CHEAP));
+        let stronger = KdfParams {
+            iterations: 2,
+            ..CHEAP
+        };
+        assert!(credentials.needs_rehash(&stronger));
+        assert!(credentials.needs_rehash(&KdfParams {
+            memory_kib: 128,
+            ..CHEAP
+        }));
+        assert!(credentials.needs_rehash(&KdfParams {
+            parallelism: 2,
+            ..CHEAP
+        }));
+    }
+
+    #[test]
+    fn a_right_password_upgrades_an_old_hash() {
+        let mut user = ann();
+        let policy = PasswordPolicy::default();
+        user.set_password("Correct horse battery", &policy, &CHEAP)
+            .unwrap();
+        let old = user.credentials.clone().unwrap();
+        let stronger = KdfParams {
+            iterations: 2,
+            ..CHEAP
+        };
+
+        assert_eq!(
+            user.check_password("wrong horse battery", &policy, &stronger),
+            Ok(false)
+        );
+        assert_eq!(user.credentials.as_ref(), Some(&old));
+        assert_eq!(
+            user.check_password("Correct horse battery", &policy, &stronger),
+            Ok(true)
+        );
+        let new = user.credentials.clone().unwrap();
+        assert_ne!(new, old);
+        assert!(!new.needs_rehash(&stronger));
+        assert!(new.verify("Correct horse battery"));
+    }
+
+    #[test]
+    fn no_password_set() {
+        assert_eq!(
+            ann().check_password("anything at all", &PasswordPolicy::default(), &CHEAP),
+            Err(PasswordError::NoPasswordSet)
+        );
+    }
+
+    #[test]
+    fn overlong_passwords_are_turned_away_before_hashing() {
+        let mut user = ann();
+        let policy = PasswordPolicy::default();
+        user.set_password("Correct horse battery", &policy, &CHEAP)
+            .unwrap();
+        // 129 characters is over the limit even though some are multi-byte,
+        // and 128 isn't, though it's more than 128 bytes.
+        let long = "é".repeat(129);
+        assert_eq!(
+            user.check_password(&long, &policy, &CHEAP),
+            Err(PasswordError::TooLong { max: 128 })
+        );
+        assert_eq!(user.check_password(&long[2..], &policy, &CHEAP), Ok(false));
+    }
+
+    #[test]
+    fn policy_rules() {
+        assert_eq!(check("Short1!"), Err(PasswordError::TooShort { min: 12 }));
+        assert_eq!(
+            check(&"Ab1".repeat(43)),
+            Err(PasswordError::TooLong { max: 128 })
+        );
+        assert_eq!(check(&"Ab1".repeat(42)), Ok(()));
+        assert_eq!(
+            check("onlylowercaseletters"),
+            Err(PasswordError::TooFewCharacterClasses { min: 2 })
+        );
+        assert_eq!(check("lowercase and spaces"), Ok(()));
+        assert_eq!(
+            check("my name is Ann, hi"),
+            Err(PasswordError::ContainsUsername)
+        );
+        assert_eq!(
+            check("hello KOWALSKI 12"),
+            Err(PasswordError::ContainsEmail)
+        );
+        // short email local parts aren't checked; "ann" is the username here.
+        let mut bo = ann();
+        bo.email = String::from("bo@example.com");
+        bo.username = String::from("robert");
+        let policy = PasswordPolicy::default();
+        assert_eq!(policy.check("bo bo bo bo bo 1", &bo), Ok(()));
+    }
+
+    #[test]
+    fn only_whole_common_passwords_are_too_common() {
+        assert_eq!(check("Password1234"), Err(PasswordError::TooCommon));
+        assert_eq!(check("QWERTY123456"), Err(PasswordError::TooCommon));
+        assert_eq!(check("password1234!"), Ok(()));
+        assert_eq!(check("password-for-my-garden-shed"), Ok(()));
+    }
+
+    #[test]
+    fn stored_hashes_are_checked_on_the_way_in() {
+        let credentials = Credentials::hash("correct horse battery", &CHEAP).unwrap();
+        assert_eq!(
+            Credentials::from_hash(credentials.as_str()),
+            Ok(credentials.clone())
+        );
+        assert!(matches!(
+            Credentials::from_hash("not a hash"),
+            Err(PasswordError::Hashing(_))
+        ));
+
+        let json = serde_json::to_string(&credentials).unwrap();
+        assert_eq!(json, format!("\"{}\"", credentials.as_str()));
+        let back: Credentials = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, credentials);
+        assert!(serde_json::from_str::<Credentials>("\"not a hash\"").is_err());
+    }
+
+    #[test]
+    fn debug_hides_the_hash() {
+        let credentials = Credentials::hash("correct horse battery", &CHEAP).unwrap();
+        assert_eq!(
+            format!("{credentials:?}"),
+            "Credentials { hash: <redacted> }"
+        );
+    }
+}
@@ -0,0 +1,45 @@
+// A tiny CSV reader/writer - just enough for files of users. Fields that
+// contain a comma, a quote or a line break are wrapped in quotes, and
+// quotes inside them are doubled ("say ""hi""").
+
+pub fn write_record(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| quote(field)).collect();
+    fields.join(",")
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Splits one line into fields. Quoted fields spanning several lines aren't
+// supported - none of our fields can contain a line break.
+pub fn parse_record(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut in_quotes = false;
+
+    while let Some(c) = chars.next() {
+        match (in_quotes, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            (true, '"') => in_quotes = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => in_quotes = true,
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (false, c) => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(String::from("unterminated quoted field"));
+    }
+    fields.push(field);
+    Ok(fields)
+}
@@ -0,0 +1,16 @@
+// The modules live in a library as well as the binary, so the tests in
+// tests/ and benches/ can use them the same way main.rs does.
+
+pub mod account;
+pub mod builder;
+pub mod color;
+pub mod credentials;
+pub mod csv;
+pub mod newtype;
+pub mod patch;
+pub mod registry;
+pub mod session;
+pub mod transfer;
+pub mod user;
+pub mod userref;
+pub mod vector;
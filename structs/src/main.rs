@@ -1,3 +1,21 @@
+use structs::{
+    account, builder, color, credentials, newtype, patch, registry, session, transfer, user,
+    userref, vector,
+};
+
+use account::{Account, Admin, EmailVerifier, Verified};
+use builder::UserBuilder;
+use color::{BlendMode, Hsl, Hsv, Lab, Rgb8, Rgba};
+use credentials::{KdfParams, PasswordPolicy};
+use newtype::{DisplayName, Email, Percent, UserId, Username};
+use patch::UserPatch;
+use registry::UserRegistry;
+use session::{FailedSignIn, SessionManager};
+use transfer::{Format, UserWriter};
+use user::{build_user, User};
+use userref::UserRef;
+use vector::{ApproxEq, Matrix4, Point3, Vec3, DEFAULT_TOLERANCE};
+
 fn main() {
 
     // to create a struct, we define an "instance" of that
@@ -8,6 +26,7 @@ fn main() {
         username: String::from("someusername123"),
         active: true,
         sign_in_count: 1,
+        credentials: None, // no password - see PASSWORDS at the bottom.
     };
     println!("{}, {}, {}, {}", user1.email, user1.username, user1.active, user1.sign_in_count);
     // note that we use dot notation to get a specific value from
@@ -21,9 +40,12 @@ fn main() {
         username: String::from("someusername123"),
         active: true,
         sign_in_count: 1,
+        credentials: None,
     };
     user2.sign_in_count += 1;
     println!("{}",user2.sign_in_count);
+    // (bumping the count by hand is fine for an example - see SIGN-INS at
+    // the bottom for the SessionManager that does it for real.)
 
     //note that we can't mark specific fields as mutable - the
     // whole instance must be mutable.
@@ -58,6 +80,40 @@ fn main() {
     //     ..user1
     // };
 
+    // To change a user without moving anything out of it, patch.rs has
+    // UserPatch, which updates fields in place and says what changed:
+
+    let mut user7 = build_user(String::from("seven@example.com"), String::from("seven"));
+    let changes = UserPatch::new()
+        .email("lucky@example.com")
+        .sign_in_count(7)
+        .active(true) // already true, so it isn't reported as a change.
+        .apply(&mut user7);
+    for change in &changes {
+        println!("{change}");
+    }
+    println!("{} still has their username", user7.username);
+
+    // and builder.rs has UserBuilder for making users with only some fields
+    // spelled out:
+
+    let user8 = UserBuilder::new("eight", "eight@example.com")
+        .sign_in_count(8)
+        .build()
+        .unwrap();
+    println!("{user8:?}");
+
+    let user9 = UserBuilder::new("nine", "nine@example.com")
+        .active(false)
+        .password("9 lives left")
+        .password_policy(PasswordPolicy { min_length: 8, ..PasswordPolicy::default() })
+        .kdf_params(KdfParams { memory_kib: 8 * 1024, ..KdfParams::default() })
+        .build()
+        .unwrap();
+    println!("{user9:?}");
+    println!("{}", UserBuilder::new("8", "eight@example").build().unwrap_err());
+    println!("{:?}", UserBuilder::new("ten", "ten@example.com").password("ten green bottles")); // password: Some("<redacted>")
+
     // TUPLE STRUCTS
 
     // We can define structs that look similar to tuples called tuple Struct.
@@ -68,7 +124,9 @@ fn main() {
     // other tuples, or when naming a field in a regular struct would be verbose
     // or redundant.
 
+    #[allow(dead_code)] // only here to show that Color and Point are different types.
     struct Color(i32, i32, i32);
+    #[allow(dead_code)]
     struct Point(i32, i32, i32);
     
     let black = Color(0, 12, 3);
@@ -82,9 +140,94 @@ fn main() {
     takes_color(black);
     // takes_color(origin); // throws error
 
+    // The same trick works for single values. newtype.rs has a macro that
+    // wraps a String or a number in its own checked type:
+
+    let id = UserId::new(42u64).unwrap();
+    let email: Email = " Ann@Example.com ".parse().unwrap(); // stored as ann@example.com
+    let username = Username::new("ann").unwrap();
+    println!("#{id} {username} <{email}>");
+    // let wrong: Email = username; // throws error, just like takes_color(origin)
+    println!("{}", UserId::new(0u64).unwrap_err()); // 0 is not between 1 and 18446744073709551615
+    println!("{}", "abc".parse::<UserId>().unwrap_err()); // invalid digit found in string
+    println!("{}", "x".parse::<Username>().unwrap_err()); // must be at least 3 characters
+    println!("{}", email == "ANN@example.com".parse().unwrap()); // true
+
+    // they can be sorted, used as map keys, and go in and out of JSON as
+    // plain values (checked on the way back in):
+    let mut names = std::collections::BTreeMap::new();
+    names.insert(UserId::new(7u64).unwrap(), DisplayName::new("  Seven ").unwrap());
+    names.insert(id.clone(), DisplayName::new("Ann").unwrap());
+    println!("{}", serde_json::to_string(&names).unwrap()); // {"7":"Seven","42":"Ann"}
+    println!("{:?}", serde_json::from_str::<Vec<Percent>>("[0, 55, 100]").map(|p| p.into_iter().map(u8::from).sum::<u8>()));
+    println!("{}", serde_json::from_str::<Percent>("101").unwrap_err()); // invalid Percent: 101 is not between 0 and 100
+    println!("{:?}", Email::try_from(String::from("no-at-sign")));
+    println!("{} belongs to user {}", String::from(email), id.into_inner());
+
+    // Color will hold anything three i32s can, including things that aren't
+    // colours. color.rs has types that check their values:
+
+    println!("{:?}", Rgb8::new(0, 12, 3)); // Ok
+    println!("{}", Rgb8::new(0, 300, 3).unwrap_err()); // green must be between 0 and 255
+
+    let teal: Rgb8 = "#008080".parse().unwrap();
+    let hsl = Hsl::from(teal);
+    println!("{teal} is {hsl} - hue {}, saturation {}, lightness {:.3}", hsl.hue(), hsl.saturation(), hsl.lightness());
+    println!("and back again: {}", Rgb8::from(hsl)); // exactly #008080
+
+    let hsv = Hsv::from(teal);
+    println!("as HSV: {} {} {:.3}", hsv.hue(), hsv.saturation(), hsv.value());
+    println!("{}", Rgb8::from(Hsv::new(300.0, 1.0, 1.0).unwrap())); // #ff00ff
+
+    let lab = Lab::from(teal);
+    let near = Lab::new(lab.lightness(), lab.a() + 1.0, lab.b()).unwrap();
+    println!("as Lab: {lab:?}, a nudge of 1 in a* is a difference of {:.1}", lab.delta_e(&near));
+
+    let overlay: Rgba = "rgba(255, 0, 0, 0.5)".parse().unwrap();
+    println!("{} has alpha {}", overlay.to_css(), overlay.alpha());
+    println!("{}", "rgb(nan, 0, 0)".parse::<Rgb8>().unwrap_err()); // 'nan' is not a valid channel
+    for mode in BlendMode::ALL {
+        let mixed = color::blend(Rgba::from(teal), overlay, mode);
+        println!("{mode:?}: {}", mixed.rgb.to_css());
+    }
+
+    let ratio = color::contrast_ratio(teal, Rgb8::WHITE);
+    println!("teal on white: {ratio:.2}:1, {:?}", color::wcag_level(teal, Rgb8::WHITE, false));
+
     // You can still destructure tuple structs into individual pieces
     // and use dot-index notation to access individual values.
 
+    // Point can't be added or measured either. vector.rs has Point3 (a
+    // place) and Vec3 (an offset), generic over the number type:
+
+    let start = Point3::new(1, 4, 2); // where origin is
+    let end = Point3::new(4, 0, 2);
+    let step = end - start; // Point3 - Point3 = Vec3
+    println!("{start} to {end} is {step}, {:.3} as the crow flies", start.distance(end));
+    println!("manhattan {}, chebyshev {}", start.manhattan(end), start.chebyshev(end));
+    println!("{}", start + step * 2); // Point3 + Vec3 = Point3
+    // println!("{}", start + end); // throws error - adding two places means nothing
+
+    let i = Vec3::new(1, 0, 0);
+    let j = Vec3::new(0, 1, 0);
+    println!("i . j = {}, i x j = {}, |i - j|^2 = {}", i.dot(j), i.cross(j), (i - j).length_squared());
+
+    let mut drone = Point3::new(1.0, 0.0, 0.0);
+    drone += Vec3::new(0.0, 0.0, 0.5);
+    let quarter_turn = Matrix4::rotation_z(std::f64::consts::FRAC_PI_2);
+    let lift = Matrix4::translation(Vec3::new(0.0, 0.0, 10.0));
+    let moved = quarter_turn.then(lift).then(Matrix4::scale(2.0, 2.0, 1.0)) * drone;
+    println!("{moved:?}"); // x is 1.2e-16, not quite 0
+    println!("{}", moved.approx_eq(&Point3::new(0.0, 2.0, 10.5), DEFAULT_TOLERANCE)); // true
+    println!("{:?}", (quarter_turn * Vec3::new(3.0, 4.0, 0.0)).normalized());
+    println!("{}", (quarter_turn * Matrix4::identity()).approx_eq(&quarter_turn, DEFAULT_TOLERANCE));
+    let tumble = Matrix4::rotation_x(1.0).then(Matrix4::rotation_y(-0.5));
+    let spot = tumble * Point3::new(1.0, 2.0, 3.0);
+    // rotations don't change how far something is from the origin.
+    println!("{}", spot.to_vec().length().approx_eq(&Point3::origin().distance(Point3::new(1.0, 2.0, 3.0)), DEFAULT_TOLERANCE));
+    println!("{:?}", Vec3::<f64>::zero().normalized()); // None - no direction
+    println!("{}", (-Vec3::new(0.5f32, 1.0, 1.0) / 2.0).length());
+
     // UNIT-LIKE STRUCTS
 
     // You can also define structs with no fields! Tehse are called
@@ -96,42 +239,232 @@ fn main() {
 
     struct AlwaysEqual;
     
+    #[allow(unused_variables)] // there's nothing in it to use.
     let subject = AlwaysEqual;
 
+    // Another use: as a label on another type. account.rs uses the
+    // unit-like structs Unverified, Verified and Admin to track where an
+    // account is in its life, so the compiler refuses to let an unverified
+    // account sign in:
+
+    let mut sessions = SessionManager::new();
+    let mut verifier = EmailVerifier::new();
+    let account = Account::new(build_user(String::from("zed@example.com"), String::from("zed")));
+    println!("{} is {}", account.user().username, account.state());
+    // account.sign_in(&mut sessions); // throws error - no `sign_in` on Account<Unverified>
+
+    let code = verifier.issue(&account);
+    let account = match verifier.verify(account, "not the code") {
+        Ok(_) => unreachable!(),
+        Err((account, e)) => {
+            println!("{e}"); // that verification code is wrong
+            account // we get it back to try again
+        }
+    };
+    let mut zed = verifier.verify(account, &code).unwrap();
+    let signed_in = zed.sign_in(&mut sessions).is_ok();
+    println!("{} is {}, signed in: {signed_in}", zed.user().username, zed.state());
+    // verifier.verify(account, &code); // throws error - `account` was moved into verify()
+
+    let pat = Account::new(build_user(String::from("pat@example.com"), String::from("pat")));
+    let pat_code = verifier.issue(&pat);
+    let pat = Account::bootstrap_admin(verifier.verify(pat, &pat_code).unwrap());
+    pat.deactivate(&mut zed);
+    println!("{} deactivated zed: {:?}", pat.state(), zed.sign_in(&mut sessions).err());
+    pat.reactivate(&mut zed);
+    // zed.deactivate(&mut pat); // throws error - only Account<Admin> has deactivate()
+
+    let mut zed = zed.promote(&pat);
+    println!("zed is now {}, {:?}", zed.state(), zed.sign_in(&mut sessions).map(|s| s.username));
+    let zed = zed.demote().change_email("zed@new.example.com").unwrap();
+    println!("new email, so zed is {} again", zed.state()); // unverified
+    println!("{:?}", verifier.verify(zed, "123456").map_err(|(_, e)| e));
+
+    // guessing codes doesn't work either - after a few wrong ones even the
+    // right code is turned away until a new one is issued:
+    let mut que = Account::new(build_user(String::from("que@example.com"), String::from("que")));
+    let que_code = verifier.issue(&que);
+    for _ in 0..verifier.max_attempts {
+        let (account, e) = verifier.verify(que, "a guess").unwrap_err();
+        println!("{e}");
+        que = account;
+    }
+    println!("{:?}", verifier.verify(que, &que_code).map_err(|(_, e)| e)); // Err(LockedOut)
+    let bad_email = pat.change_email("pat@").map(|_| ()).unwrap_err();
+    println!("{bad_email:?}, markers take up {} bytes", std::mem::size_of::<Verified>());
+    println!("{}", std::mem::size_of::<Account<Admin>>() == std::mem::size_of::<User>()); // true
+    println!("{:?}", Account::new(build_user(String::from("q@example.com"), String::from("que"))).into_user().username);
+
     // OWNERSHIP AND STRUCTS - A BRIEF ASIDE:
 
     // Structs are able to store references to data owned by something else,
     // but we have to use "lifetimes", which is another feature of rust that
     // will be discussed later. Lifetimes ensure that the data referenced by a 
     // struct is valid for as long as the struct is.
-    
 
-}
+    // userref.rs has one: UserRef<'a> borrows its username and email from
+    // the text it was parsed from instead of copying them.
 
-fn build_user (email: String, username: String) -> User {
-    User {
-        email, // uses field init shorthand, which allows us to declare
-               // a field with the key name of the variable and the 
-               // value of the variable.
-        username,
-        active: true,
-        sign_in_count: 1,
+    let text = "username\temail\tactive\tsign_in_count\n\
+                ann\tann@example.com\ttrue\t3\n\
+                bob\t\"bob@example.com\"\tfalse\t0\n\
+                cat\tcat@example.com\tmaybe\t1\n";
+    for record in userref::parse_records(text, '\t') {
+        match record {
+            Ok(user) => println!("{user:?}"),
+            Err(e) => println!("{e}"), // line 4: active must be true or false, got 'maybe'
+        }
     }
-    // this whole function is return a new instance of the user
-    // struct
-}
+    let ann = userref::parse_records(text, '\t').next().unwrap().unwrap();
+    let owned: User = ann.try_into().unwrap(); // copies - `owned` doesn't borrow `text`
+    println!("{} is the same user again: {}", owned.username, UserRef::from(&owned) == ann);
+    println!("{}", userref::parse_records("\"a\"\"b\",x,true,1", ',').next().unwrap().unwrap_err());
+
+    // How much does not copying save? benches/userref.rs times parsing the
+    // same lines both ways - run it with `cargo bench`.
+
+    // USER REGISTRY
+
+    // registry.rs keeps users together, and won't let two of them share a
+    // username or email (see there for the validation rules):
+
+    let mut registry = UserRegistry::new();
+    registry.register(build_user(String::from("dave@dave.com"), String::from("DaveDave"))).unwrap();
+    registry.register(build_user(String::from("ann@example.com"), String::from("ann"))).unwrap();
+
+    match registry.register(build_user(String::from("Dave@Dave.com"), String::from("dave2"))) {
+        Ok(_) => println!("registered"),
+        Err((_, e)) => println!("couldn't register: {e}"), // email is taken
+    }
+    match registry.register(build_user(String::from("not an email"), String::from("xavier"))) {
+        Ok(_) => println!("registered"),
+        Err((mut user, e)) => {
+            println!("couldn't register: {e}");
+            user.email = String::from("xavier@example.com"); // we get the user back to try again
+            println!("second try: {:?}", registry.register(user).map(|user| &user.email));
+        }
+    }
+
+    println!("{:?}", registry.find("ann"));
+    println!("{:?}", registry.find("DAVE@dave.com").map(|user| &user.username));
+
+    let changes = registry.update("ann", UserPatch::new().username("annie")).unwrap();
+    println!("{changes:?}, found by new name: {}", registry.find("annie").is_some());
+    match registry.update("annie", UserPatch::new().email("dave@dave.com")) {
+        Ok(_) => println!("updated"),
+        Err(e) => println!("couldn't update: {e}"),
+    }
+
+    let path = std::env::temp_dir().join("structs-users.csv");
+    registry.save(&path).unwrap();
+    let loaded = UserRegistry::load(&path).unwrap();
+    println!("saved and loaded {} users from {}", loaded.len(), path.display());
+    if !loaded.is_empty() {
+        for user in loaded.iter() {
+            println!("{} <{}>", user.username, user.email);
+        }
+    }
+
+    // IMPORT AND EXPORT
+
+    // transfer.rs reads and writes users as CSV, JSON Lines or vCards, one
+    // record at a time. Bad records are reported with their line number
+    // instead of stopping the whole import:
+
+    let messy = "username,email,active,sign_in_count\n\
+                 eve,eve@example.com,true,2\n\
+                 mallory,mallory@example,true,0\n\
+                 trent,trent@example.com,yes,1\n\
+                 \n\
+                 peggy,peggy@example.com,false\n\
+                 victor,victor@example.com,false,9\n";
+    let report = transfer::import(messy.as_bytes(), Format::Csv);
+    println!("{report}");
+
+    // the same users as vCards and JSON Lines, and back again:
+    let mut cards = Vec::new();
+    transfer::export(&report.users, &mut cards, Format::VCard).unwrap();
+    let first_card: String = String::from_utf8_lossy(&cards).split_inclusive('\n').take(8).collect();
+    print!("{first_card}");
+    let lines = transfer::export(&report.users, Vec::new(), Format::JsonLines).unwrap();
+    println!("({lines} JSON lines)");
+    let mut writer = UserWriter::new(Vec::new(), Format::JsonLines);
+    for user in transfer::UserReader::new(&cards[..], Format::VCard) {
+        writer.write(&user.unwrap()).unwrap();
+    }
+    print!("{}", String::from_utf8(writer.finish().unwrap()).unwrap());
+
+    // bulk-loading fixtures into a registry - dave is already in there, so
+    // his line fails and everyone else goes in:
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    println!("{}", transfer::import_file(fixtures.join("users.vcf")).unwrap());
+    let with_dave = format!("{}{}", std::fs::read_to_string(fixtures.join("users.jsonl")).unwrap(),
+        r#"{"active":true,"username":"dave2","email":"DAVE@dave.com","sign_in_count":1}"#);
+    println!("{}", transfer::import_into(&mut registry, with_dave.as_bytes(), Format::JsonLines));
+    println!("the registry has {} users now", registry.len());
+    println!("{:?}", transfer::import_file("users.txt").err().map(|e| e.to_string()));
+
+    // SIGN-INS
 
-// STRUCTS
+    // session.rs tracks sign-ins instead of bumping sign_in_count by hand:
 
-// structs are an object-oriented-ish feature of rust - it allows
-// us to define certain types that contain data in an organized format.
-// They're similar to objects in javascript, but are more like
-// object orientation in other languages in that they actually define
-// new types that our program can use:
+    let mut sessions = SessionManager::new();
+    sessions.max_failed_attempts = 3;
+    let mut dave = build_user(String::from("dave@dave.com"), String::from("DaveDave"));
 
-struct User {
-    active: bool, // these are called "fields"
-    username: String,
-    email: String,
-    sign_in_count: u64,
-}
\ No newline at end of file
+    let session = sessions.sign_in(&mut dave).unwrap();
+    println!("{} has signed in {} times", dave.username, dave.sign_in_count);
+    println!("token is valid: {}", sessions.validate(&session.token).is_ok());
+    sessions.sign_out(&session.token).unwrap();
+    println!("after sign-out: {:?}", sessions.validate(&session.token).err());
+
+    // three bad passwords in a row and the account is switched off:
+    while let FailedSignIn::AttemptsLeft(left) = sessions.fail_sign_in(&mut dave) {
+        println!("wrong password, {left} attempts left");
+    }
+    let rejected = sessions.sign_in(&mut dave).err();
+    println!("active: {}, sign in: {:?}", dave.active, rejected);
+    sessions.reactivate(&mut dave);
+
+    // a fake clock two hours ahead shows sessions running out:
+    let _session = sessions.sign_in(&mut dave).unwrap();
+    fn two_hours_later() -> std::time::SystemTime {
+        std::time::SystemTime::now() + std::time::Duration::from_secs(2 * 60 * 60)
+    }
+    sessions.clock = two_hours_later;
+    println!("expired {} session(s), {} left", sessions.expire_sessions(), sessions.active_sessions(&dave.username));
+
+    for event in sessions.audit_log(&dave.username) {
+        println!("{event}");
+    }
+
+    // PASSWORDS
+
+    // credentials.rs stores a salted Argon2 hash of the password, never
+    // the password itself:
+
+    let policy = PasswordPolicy::default();
+    let params = KdfParams::default();
+    let mut ann = build_user(String::from("ann@example.com"), String::from("ann"));
+
+    for attempt in ["short", "Password1234", "ann-is-great-2024", "correct horse battery staple"] {
+        match ann.set_password(attempt, &policy, &params) {
+            Ok(()) => println!("'{attempt}' accepted"),
+            Err(e) => println!("'{attempt}' rejected: {e}"),
+        }
+    }
+    dbg!(&ann.credentials); // the hash is redacted from Debug output.
+
+    let wrong = sessions.sign_in_with_password(&mut ann, "Correct Horse", &policy, &params);
+    println!("{}", wrong.unwrap_err());
+    let huge = "x".repeat(100_000); // turned away before it's hashed.
+    println!("{}", sessions.sign_in_with_password(&mut ann, &huge, &policy, &params).unwrap_err());
+
+    // if we raise the cost later, the old hash is replaced the next time the
+    // right password comes in:
+    let stronger = KdfParams { iterations: params.iterations + 1, ..params };
+    let credentials = ann.credentials.clone().unwrap();
+    println!("needs rehash: {}", credentials.needs_rehash(&stronger));
+    sessions.sign_in_with_password(&mut ann, "correct horse battery staple", &policy, &stronger).unwrap();
+    println!("needs rehash: {}", ann.credentials.as_ref().unwrap().needs_rehash(&stronger));
+}
@@ -0,0 +1,323 @@
+use std::fmt;
+
+use crate::registry::{validate_email, validate_username, EmailError, UsernameError};
+
+// NEWTYPES
+
+// The tuple struct section shows that Color(i32, i32, i32) and
+// Point(i32, i32, i32) are different types even though they hold the same
+// data. A one-field tuple struct - a "newtype" - does the same for a single
+// value: an Email and a Username are both Strings underneath, but a
+// function that takes an Email can't be handed a Username by mistake.
+//
+// Writing a newtype properly takes a lot of boilerplate (a checked
+// constructor, Display, FromStr, serde...), so the newtype! macro below
+// writes it for us:
+//
+//     newtype! {
+//         // doc comments and attributes go here
+//         pub struct Email(String);
+//         normalize: |email: String| email.trim().to_lowercase();   (optional)
+//         validate: validate_email => EmailError;                  (optional)
+//     }
+//
+// The field is private, so the only way to make one is through new() (or
+// parse(), TryFrom, or deserializing), and those all run the validation.
+// If you're holding an Email, it's a valid email.
+//
+// Each generated type gets:
+//   - new(value) -> Result<Self, Error>, get() -> &Inner, into_inner()
+//   - Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash (so the inner
+//     type needs those too - no floats)
+//   - Display and FromStr, the same as the inner type's
+//   - TryFrom<Inner>, and From<Self> for Inner
+//   - Serialize/Deserialize as the bare inner value, validated on the way in
+
+// What parse() returns when it fails: either the text wasn't the inner
+// type at all ("abc" for a number), or it was but didn't pass validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError<E> {
+    Malformed(String),
+    Invalid(E),
+}
+
+impl<E: fmt::Display> fmt::Display for ParseError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Malformed(reason) => write!(f, "{reason}"),
+            ParseError::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ParseError<E> {}
+
+// A ready-made error for numbers that have to be within a range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfRange<T> {
+    pub value: T,
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: fmt::Display> fmt::Display for OutOfRange<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is not between {} and {}",
+            self.value, self.min, self.max
+        )
+    }
+}
+
+pub fn in_range<T: PartialOrd + Copy>(value: T, min: T, max: T) -> Result<(), OutOfRange<T>> {
+    if value < min || value > max {
+        Err(OutOfRange { value, min, max })
+    } else {
+        Ok(())
+    }
+}
+
+// The macro is exported (it lives at structs::newtype!), so other crates
+// can make newtypes too. Everything it names is spelled out with $crate::
+// or ::std:: so it works wherever it's used - even in a crate that doesn't
+// depend on serde itself, which is why serde is re-exported here.
+#[doc(hidden)]
+pub use serde;
+
+#[macro_export]
+macro_rules! newtype {
+    // no validation: anything the inner type can hold is fine.
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($inner:ty);
+        $(normalize: $normalize:expr;)?
+    ) => {
+        $crate::newtype! {
+            $(#[$meta])*
+            $vis struct $name($inner);
+            $(normalize: $normalize;)?
+            validate: |_: &$inner| -> Result<(), ::std::convert::Infallible> { Ok(()) }
+                => ::std::convert::Infallible;
+        }
+    };
+
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($inner:ty);
+        $(normalize: $normalize:expr;)?
+        validate: $validate:expr => $error:ty;
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis struct $name($inner);
+
+        // not every type uses every method, and that's fine.
+        #[allow(dead_code)]
+        impl $name {
+            $vis fn new(value: impl Into<$inner>) -> Result<$name, $error> {
+                let value: $inner = value.into();
+                $(let value: $inner = ($normalize)(value);)?
+                ($validate)(&value)?;
+                Ok($name(value))
+            }
+
+            $vis fn get(&self) -> &$inner {
+                &self.0
+            }
+
+            $vis fn into_inner(self) -> $inner {
+                self.0
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                ::std::fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = $crate::newtype::ParseError<$error>;
+
+            fn from_str(s: &str) -> Result<$name, Self::Err> {
+                let value: $inner = s
+                    .parse()
+                    .map_err(|e| $crate::newtype::ParseError::Malformed(format!("{e}")))?;
+                $name::new(value).map_err($crate::newtype::ParseError::Invalid)
+            }
+        }
+
+        // (can't fail for types with no validation, but it's still handy to
+        // have the same conversions on every newtype.)
+        #[allow(clippy::infallible_try_from)]
+        impl TryFrom<$inner> for $name {
+            type Error = $error;
+
+            fn try_from(value: $inner) -> Result<$name, $error> {
+                $name::new(value)
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(value: $name) -> $inner {
+                value.0
+            }
+        }
+
+        // serialized as the plain inner value - "ann@example.com", not
+        // {"0": "ann@example.com"} - so the JSON looks the same as before.
+        impl $crate::newtype::serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: $crate::newtype::serde::Serializer,
+            {
+                $crate::newtype::serde::Serialize::serialize(&self.0, serializer)
+            }
+        }
+
+        impl<'de> $crate::newtype::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<$name, D::Error>
+            where
+                D: $crate::newtype::serde::Deserializer<'de>,
+            {
+                let value: $inner = $crate::newtype::serde::Deserialize::deserialize(deserializer)?;
+                $name::new(value).map_err(|e| {
+                    <D::Error as $crate::newtype::serde::de::Error>::custom(format!(
+                        "invalid {}: {e}",
+                        stringify!($name)
+                    ))
+                })
+            }
+        }
+    };
+}
+
+// THE TYPES WE USE
+
+newtype! {
+    // A user's number. 0 is never handed out, so it can't be a real id.
+    pub struct UserId(u64);
+    validate: |id: &u64| in_range(*id, 1, u64::MAX) => OutOfRange<u64>;
+}
+
+newtype! {
+    // Same rules as the registry (see validate_username).
+    pub struct Username(String);
+    validate: validate_username => UsernameError;
+}
+
+newtype! {
+    // Stored lowercased, so two Emails that the registry would call
+    // duplicates are also == (and hash the same).
+    pub struct Email(String);
+    normalize: |email: String| email.trim().to_lowercase();
+    validate: validate_email => EmailError;
+}
+
+newtype! {
+    // A unit rather than an identifier - 0 to 100 and nothing else.
+    pub struct Percent(u8);
+    validate: |p: &u8| in_range(*p, 0, 100) => OutOfRange<u8>;
+}
+
+newtype! {
+    // No rules at all: just a label that can't be mixed up with other
+    // Strings.
+    pub struct DisplayName(String);
+    normalize: |name: String| name.trim().to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    newtype! {
+        // An even number, to have a rule that's easy to break.
+        struct Even(i32);
+        validate: |n: &i32| if n % 2 == 0 { Ok(()) } else { Err(format!("{n} is odd")) } => String;
+    }
+
+    #[test]
+    fn new_runs_the_validator() {
+        assert_eq!(Even::new(4).map(Even::into_inner), Ok(4));
+        assert_eq!(Even::new(3), Err(String::from("3 is odd")));
+        assert_eq!(
+            UserId::new(0u64),
+            Err(OutOfRange {
+                value: 0,
+                min: 1,
+                max: u64::MAX
+            })
+        );
+        assert_eq!(
+            Percent::new(101),
+            Err(OutOfRange {
+                value: 101,
+                min: 0,
+                max: 100
+            })
+        );
+        assert_eq!(Percent::try_from(100).map(u8::from), Ok(100));
+        assert!(Username::new("9lives").is_err());
+        assert!(Email::new("ann").is_err());
+    }
+
+    #[test]
+    fn normalizing_happens_before_validating() {
+        let email = Email::new("  Ann@Example.COM ").unwrap();
+        assert_eq!(email.get(), "ann@example.com");
+        assert_eq!(email, Email::new("ann@example.com").unwrap());
+        assert_eq!(DisplayName::new("  Ann  ").unwrap().to_string(), "Ann");
+    }
+
+    #[test]
+    fn parse_tells_malformed_from_invalid() {
+        assert_eq!("8".parse::<Even>().map(Even::into_inner), Ok(8));
+        assert_eq!(
+            "7".parse::<Even>(),
+            Err(ParseError::Invalid(String::from("7 is odd")))
+        );
+        assert_eq!(
+            "seven".parse::<Even>(),
+            Err(ParseError::Malformed(String::from(
+                "invalid digit found in string"
+            )))
+        );
+        assert!(matches!(
+            "300".parse::<Percent>(),
+            Err(ParseError::Malformed(_))
+        ));
+        assert!(matches!(
+            "101".parse::<Percent>(),
+            Err(ParseError::Invalid(_))
+        ));
+        assert_eq!(
+            "101".parse::<Percent>().unwrap_err().to_string(),
+            "101 is not between 0 and 100"
+        );
+    }
+
+    #[test]
+    fn serde_uses_the_bare_value_and_validates_it() {
+        let id = UserId::new(7u64).unwrap();
+        assert_eq!(serde_json::to_string(&id).unwrap(), "7");
+        assert_eq!(serde_json::from_str::<UserId>("7").unwrap(), id);
+        let email: Email = serde_json::from_str(r#""ANN@example.com""#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&email).unwrap(),
+            r#""ann@example.com""#
+        );
+
+        let error = serde_json::from_str::<UserId>("0").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("invalid UserId: 0 is not between"),
+            "{error}"
+        );
+        assert!(serde_json::from_str::<Even>("3").is_err());
+        assert!(serde_json::from_str::<Email>(r#""not an email""#).is_err());
+        assert!(serde_json::from_str::<Percent>("-1").is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+
+use crate::user::User;
+
+// PARTIAL UPDATES
+
+// Struct update syntax (`User { email, ..user1 }`) builds a whole new User
+// and moves user1's Strings into it, so user1 is unusable afterwards. A
+// UserPatch changes an existing User in place instead: every field is an
+// Option, None means "leave it alone", and nothing that isn't being changed
+// gets moved or copied.
+//
+// apply() reports which fields actually changed (setting a field to the
+// value it already has isn't a change), which is what goes in an audit log.
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserPatch {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub active: Option<bool>,
+    pub sign_in_count: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?} -> {:?}", self.field, self.old, self.new)
+    }
+}
+
+impl UserPatch {
+    pub fn new() -> UserPatch {
+        UserPatch::default()
+    }
+
+    pub fn username(mut self, username: impl Into<String>) -> UserPatch {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn email(mut self, email: impl Into<String>) -> UserPatch {
+        self.email = Some(email.into());
+        self
+    }
+
+    pub fn active(mut self, active: bool) -> UserPatch {
+        self.active = Some(active);
+        self
+    }
+
+    pub fn sign_in_count(mut self, count: u64) -> UserPatch {
+        self.sign_in_count = Some(count);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == UserPatch::default()
+    }
+
+    // The changes this patch would make to `user`, without making them.
+    pub fn diff(&self, user: &User) -> Vec<FieldChange> {
+        let mut changes = Vec::new();
+        push_change(
+            &mut changes,
+            "username",
+            &user.username,
+            self.username.as_ref(),
+        );
+        push_change(&mut changes, "email", &user.email, self.email.as_ref());
+        push_change(&mut changes, "active", &user.active, self.active.as_ref());
+        push_change(
+            &mut changes,
+            "sign_in_count",
+            &user.sign_in_count,
+            self.sign_in_count.as_ref(),
+        );
+        changes
+    }
+
+    // Applies the patch, consuming it - the new Strings move into the user
+    // rather than being cloned.
+    pub fn apply(self, user: &mut User) -> Vec<FieldChange> {
+        let changes = self.diff(user);
+        if let Some(username) = self.username {
+            user.username = username;
+        }
+        if let Some(email) = self.email {
+            user.email = email;
+        }
+        if let Some(active) = self.active {
+            user.active = active;
+        }
+        if let Some(count) = self.sign_in_count {
+            user.sign_in_count = count;
+        }
+        changes
+    }
+}
+
+fn push_change<T: PartialEq + ToString>(
+    changes: &mut Vec<FieldChange>,
+    field: &'static str,
+    old: &T,
+    new: Option<&T>,
+) {
+    if let Some(new) = new.filter(|new| *new != old) {
+        changes.push(FieldChange {
+            field,
+            old: old.to_string(),
+            new: new.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::build_user;
+
+    fn ann() -> User {
+        build_user(String::from("ann@example.com"), String::from("ann"))
+    }
+
+    fn change(field: &'static str, old: &str, new: &str) -> FieldChange {
+        FieldChange {
+            field,
+            old: old.to_string(),
+            new: new.to_string(),
+        }
+    }
+
+    #[test]
+    fn an_empty_patch_changes_nothing() {
+        let patch = UserPatch::new();
+        assert!(patch.is_empty());
+        let mut user = ann();
+        assert_eq!(patch.apply(&mut user), []);
+        assert_eq!(user, ann());
+    }
+
+    #[test]
+    fn apply_reports_every_changed_field() {
+        let mut user = ann();
+        let patch = UserPatch::new()
+            .username("anne")
+            .email("anne@example.com")
+            .active(false)
+            .sign_in_count(5);
+        assert!(!patch.is_empty());
+        assert_eq!(patch.diff(&user), patch.clone().apply(&mut user.clone()));
+
+        let changes = patch.apply(&mut user);
+        assert_eq!(
+            changes,
+            [
+                change("username", "ann", "anne"),
+                change("email", "ann@example.com", "anne@example.com"),
+                change("active", "true", "false"),
+                change("sign_in_count", "1", "5"),
+            ]
+        );
+        assert_eq!(user.username, "anne");
+        assert_eq!(user.email, "anne@example.com");
+        assert!(!user.active);
+        assert_eq!(user.sign_in_count, 5);
+        assert_eq!(changes[0].to_string(), r#"username: "ann" -> "anne""#);
+    }
+
+    #[test]
+    fn setting_the_same_value_is_not_a_change() {
+        let mut user = ann();
+        let patch = UserPatch::new()
+            .username("ann")
+            .active(true)
+            .sign_in_count(2);
+        assert_eq!(patch.apply(&mut user), [change("sign_in_count", "1", "2")]);
+    }
+
+    #[test]
+    fn diff_leaves_the_user_alone() {
+        let user = ann();
+        let changes = UserPatch::new().email("bob@example.com").diff(&user);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(user.email, "ann@example.com");
+    }
+}
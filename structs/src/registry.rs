@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::patch::{FieldChange, UserPatch};
+use crate::transfer;
+use crate::user::User;
+
+// USER REGISTRY
+
+// Keeps every User in one place and makes sure no two share a username or
+// an email. Users live in a Vec, and two HashMaps point from each key to a
+// position in that Vec, so lookups by either key don't have to scan.
+// Keys are compared case-insensitively - "Dave@Dave.com" and
+// "dave@dave.com" are the same address.
+
+#[derive(Debug, Default)]
+pub struct UserRegistry {
+    users: Vec<User>,
+    by_username: HashMap<String, usize>,
+    by_email: HashMap<String, usize>,
+}
+
+// VALIDATION
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UsernameError {
+    TooShort { min: usize },
+    TooLong { max: usize },
+    MustStartWithLetter,
+    InvalidChar(char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmailError {
+    MissingAt,
+    MultipleAt,
+    EmptyLocalPart,
+    LocalPartTooLong { max: usize },
+    InvalidLocalChar(char),
+    InvalidDomain(String),
+}
+
+impl fmt::Display for UsernameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UsernameError::TooShort { min } => write!(f, "must be at least {min} characters"),
+            UsernameError::TooLong { max } => write!(f, "must be at most {max} characters"),
+            UsernameError::MustStartWithLetter => write!(f, "must start with a letter"),
+            UsernameError::InvalidChar(c) => write!(
+                f,
+                "'{c}' is not allowed (use letters, digits, '_', '-' or '.')"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for EmailError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmailError::MissingAt => write!(f, "is missing an '@'"),
+            EmailError::MultipleAt => write!(f, "has more than one '@'"),
+            EmailError::EmptyLocalPart => write!(f, "has nothing before the '@'"),
+            EmailError::LocalPartTooLong { max } => {
+                write!(f, "has more than {max} characters before the '@'")
+            }
+            EmailError::InvalidLocalChar(c) => write!(f, "'{c}' is not allowed before the '@'"),
+            EmailError::InvalidDomain(reason) => write!(f, "has an invalid domain: {reason}"),
+        }
+    }
+}
+
+pub const USERNAME_MIN: usize = 3;
+pub const USERNAME_MAX: usize = 32;
+const EMAIL_LOCAL_MAX: usize = 64;
+
+// Usernames: 3 to 32 ASCII letters, digits, '_', '-' or '.', starting
+// with a letter.
+pub fn validate_username(username: &str) -> Result<(), UsernameError> {
+    let length = username.chars().count();
+    if length < USERNAME_MIN {
+        return Err(UsernameError::TooShort { min: USERNAME_MIN });
+    }
+    if length > USERNAME_MAX {
+        return Err(UsernameError::TooLong { max: USERNAME_MAX });
+    }
+    if let Some(c) = username
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+    {
+        return Err(UsernameError::InvalidChar(c));
+    }
+    if !username.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(UsernameError::MustStartWithLetter);
+    }
+    Ok(())
+}
+
+// A practical subset of what email addresses can look like, not the whole
+// RFC: `local@domain.tld`, where the domain is dot-separated labels of
+// letters, digits and hyphens, and the last label is at least 2 letters.
+pub fn validate_email(email: &str) -> Result<(), EmailError> {
+    let mut parts = email.split('@');
+    let local = parts.next().unwrap_or_default();
+    let Some(domain) = parts.next() else {
+        return Err(EmailError::MissingAt);
+    };
+    if parts.next().is_some() {
+        return Err(EmailError::MultipleAt);
+    }
+
+    if local.is_empty() {
+        return Err(EmailError::EmptyLocalPart);
+    }
+    if local.len() > EMAIL_LOCAL_MAX {
+        return Err(EmailError::LocalPartTooLong {
+            max: EMAIL_LOCAL_MAX,
+        });
+    }
+    if let Some(c) = local
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || "._%+-".contains(*c)))
+    {
+        return Err(EmailError::InvalidLocalChar(c));
+    }
+    if local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+        return Err(EmailError::InvalidLocalChar('.'));
+    }
+
+    validate_domain(domain).map_err(|reason| EmailError::InvalidDomain(reason.to_string()))
+}
+
+fn validate_domain(domain: &str) -> Result<(), &'static str> {
+    let labels: Vec<&str> = domain.split('.').collect();
+    if labels.len() < 2 {
+        return Err("needs at least one '.'");
+    }
+    for label in &labels {
+        if label.is_empty() {
+            return Err("has an empty part between dots");
+        }
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err("may only contain letters, digits and '-'");
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err("parts can't start or end with '-'");
+        }
+    }
+    let tld = labels[labels.len() - 1];
+    if tld.len() < 2 || !tld.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err("must end in a top-level domain of 2 or more letters");
+    }
+    Ok(())
+}
+
+// ERRORS
+
+#[derive(Debug)]
+pub enum RegistryError {
+    InvalidUsername(String, UsernameError),
+    InvalidEmail(String, EmailError),
+    DuplicateUsername(String),
+    DuplicateEmail(String),
+    UnknownUser(String),
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    UnknownFormat(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::InvalidUsername(name, e) => write!(f, "username '{name}' {e}"),
+            RegistryError::InvalidEmail(email, e) => write!(f, "email '{email}' {e}"),
+            RegistryError::DuplicateUsername(name) => write!(f, "username '{name}' is taken"),
+            RegistryError::DuplicateEmail(email) => {
+                write!(f, "email '{email}' is already registered")
+            }
+            RegistryError::UnknownUser(name) => write!(f, "no user named '{name}'"),
+            RegistryError::Io(e) => write!(f, "{e}"),
+            RegistryError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            RegistryError::UnknownFormat(path) => {
+                write!(
+                    f,
+                    "don't know how to read/write '{path}' (use .json or .csv)"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl From<io::Error> for RegistryError {
+    fn from(e: io::Error) -> Self {
+        RegistryError::Io(e)
+    }
+}
+
+fn key(value: &str) -> String {
+    value.to_lowercase()
+}
+
+impl UserRegistry {
+    pub fn new() -> UserRegistry {
+        UserRegistry::default()
+    }
+
+    // Checks the user over and adds it. The user is moved in, so if
+    // registering fails it comes back with the error, ready to be fixed up
+    // and tried again.
+    // (the error is big because it holds a whole User, but handing it back
+    // is the point, and registering happens once per user, not in a loop.)
+    #[allow(clippy::result_large_err)]
+    pub fn register(&mut self, user: User) -> Result<&User, (User, RegistryError)> {
+        if let Err(e) = self.check(&user) {
+            return Err((user, e));
+        }
+
+        let index = self.users.len();
+        self.by_username.insert(key(&user.username), index);
+        self.by_email.insert(key(&user.email), index);
+        self.users.push(user);
+        Ok(&self.users[index])
+    }
+
+    // Everything register checks, without adding anything.
+    pub fn check(&self, user: &User) -> Result<(), RegistryError> {
+        validate_username(&user.username)
+            .map_err(|e| RegistryError::InvalidUsername(user.username.clone(), e))?;
+        validate_email(&user.email)
+            .map_err(|e| RegistryError::InvalidEmail(user.email.clone(), e))?;
+
+        if self.by_username.contains_key(&key(&user.username)) {
+            return Err(RegistryError::DuplicateUsername(user.username.clone()));
+        }
+        if self.by_email.contains_key(&key(&user.email)) {
+            return Err(RegistryError::DuplicateEmail(user.email.clone()));
+        }
+        Ok(())
+    }
+
+    // Applies a patch to a registered user. New usernames and emails go
+    // through the same checks as register (a user can keep their own,
+    // of course), and the lookup tables are updated to match.
+    pub fn update(
+        &mut self,
+        username: &str,
+        patch: UserPatch,
+    ) -> Result<Vec<FieldChange>, RegistryError> {
+        let index = *self
+            .by_username
+            .get(&key(username))
+            .ok_or_else(|| RegistryError::UnknownUser(username.to_string()))?;
+        if patch.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if let Some(new) = &patch.username {
+            validate_username(new).map_err(|e| RegistryError::InvalidUsername(new.clone(), e))?;
+            if self.by_username.get(&key(new)).is_some_and(|&i| i != index) {
+                return Err(RegistryError::DuplicateUsername(new.clone()));
+            }
+        }
+        if let Some(new) = &patch.email {
+            validate_email(new).map_err(|e| RegistryError::InvalidEmail(new.clone(), e))?;
+            if self.by_email.get(&key(new)).is_some_and(|&i| i != index) {
+                return Err(RegistryError::DuplicateEmail(new.clone()));
+            }
+        }
+
+        let user = &mut self.users[index];
+        let (old_username, old_email) = (key(&user.username), key(&user.email));
+        let changes = patch.apply(user);
+
+        self.by_username.remove(&old_username);
+        self.by_email.remove(&old_email);
+        self.by_username.insert(key(&user.username), index);
+        self.by_email.insert(key(&user.email), index);
+        Ok(changes)
+    }
+
+    pub fn by_username(&self, username: &str) -> Option<&User> {
+        let index = self.by_username.get(&key(username))?;
+        self.users.get(*index)
+    }
+
+    pub fn by_email(&self, email: &str) -> Option<&User> {
+        let index = self.by_email.get(&key(email))?;
+        self.users.get(*index)
+    }
+
+    // Looks a user up by whichever key they typed - anything with an '@'
+    // is treated as an email.
+    pub fn find(&self, username_or_email: &str) -> Option<&User> {
+        if username_or_email.contains('@') {
+            self.by_email(username_or_email)
+        } else {
+            self.by_username(username_or_email)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &User> {
+        self.users.iter()
+    }
+
+    // PERSISTENCE
+
+    // The file extension picks the format: .json or .csv.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RegistryError> {
+        let path = path.as_ref();
+        let text = match Format::of(path)? {
+            Format::Json => serde_json::to_string_pretty(&self.users)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Format::Csv => {
+                let mut text = String::from(transfer::CSV_HEADER);
+                text.push('\n');
+                for user in &self.users {
+                    text.push_str(&transfer::csv_row(user));
+                    text.push('\n');
+                }
+                text
+            }
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    // Every loaded user goes through register, so a file with a bad email
+    // or a duplicate username is rejected just like a bad sign-up would be.
+    pub fn load(path: impl AsRef<Path>) -> Result<UserRegistry, RegistryError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let users = match Format::of(path)? {
+            Format::Json => {
+                serde_json::from_str::<Vec<User>>(&text).map_err(|e| RegistryError::Parse {
+                    line: e.line(),
+                    message: e.to_string(),
+                })?
+            }
+            Format::Csv => users_from_csv(&text)?,
+        };
+
+        let mut registry = UserRegistry::new();
+        for user in users {
+            registry.register(user).map_err(|(_, e)| e)?;
+        }
+        Ok(registry)
+    }
+}
+
+fn users_from_csv(text: &str) -> Result<Vec<User>, RegistryError> {
+    let mut users = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || (index == 0 && transfer::is_csv_header(line)) {
+            continue;
+        }
+        let user = transfer::user_from_csv_row(line).map_err(|message| RegistryError::Parse {
+            line: index + 1,
+            message,
+        })?;
+        users.push(user);
+    }
+
+    Ok(users)
+}
+
+enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    fn of(path: &Path) -> Result<Format, RegistryError> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Ok(Format::Json),
+            Some("csv") => Ok(Format::Csv),
+            _ => Err(RegistryError::UnknownFormat(path.display().to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::build_user;
+
+    fn user(username: &str, email: &str) -> User {
+        build_user(email.to_string(), username.to_string())
+    }
+
+    fn registry() -> UserRegistry {
+        let mut registry = UserRegistry::new();
+        registry.register(user("ann", "ann@example.com")).unwrap();
+        registry.register(user("Bob", "Bob@Example.com")).unwrap();
+        registry
+    }
+
+    #[test]
+    fn usernames_and_emails_are_unique_whatever_their_case() {
+        let mut registry = registry();
+        let (rejected, e) = registry
+            .register(user("ANN", "new@example.com"))
+            .unwrap_err();
+        assert!(matches!(e, RegistryError::DuplicateUsername(name) if name == "ANN"));
+        assert_eq!(
+            rejected.username, "ANN",
+            "the user comes back with the error"
+        );
+        let (_, e) = registry
+            .register(user("cat", "bob@example.COM"))
+            .unwrap_err();
+        assert!(matches!(e, RegistryError::DuplicateEmail(_)));
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn lookups_ignore_case() {
+        let registry = registry();
+        assert_eq!(
+            registry.by_username("BOB").unwrap().email,
+            "Bob@Example.com"
+        );
+        assert_eq!(
+            registry.by_email("ANN@EXAMPLE.COM").unwrap().username,
+            "ann"
+        );
+        assert_eq!(registry.find("bob@example.com").unwrap().username, "Bob");
+        assert_eq!(registry.find("Ann").unwrap().username, "ann");
+        assert!(registry.find("cat").is_none());
+    }
+
+    #[test]
+    fn invalid_users_are_rejected() {
+        let mut registry = UserRegistry::new();
+        let (_, e) = registry
+            .register(user("1ann", "ann@example.com"))
+            .unwrap_err();
+        assert!(matches!(
+            e,
+            RegistryError::InvalidUsername(_, UsernameError::MustStartWithLetter)
+        ));
+        let (_, e) = registry.register(user("ann", "ann@example")).unwrap_err();
+        assert!(matches!(
+            e,
+            RegistryError::InvalidEmail(_, EmailError::InvalidDomain(_))
+        ));
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn username_rules() {
+        assert_eq!(validate_username("ann.b-c_1"), Ok(()));
+        assert_eq!(
+            validate_username("an"),
+            Err(UsernameError::TooShort { min: 3 })
+        );
+        assert_eq!(
+            validate_username(&"a".repeat(33)),
+            Err(UsernameError::TooLong { max: 32 })
+        );
+        assert_eq!(
+            validate_username("ann!"),
+            Err(UsernameError::InvalidChar('!'))
+        );
+        assert_eq!(
+            validate_username("_ann"),
+            Err(UsernameError::MustStartWithLetter)
+        );
+    }
+
+    #[test]
+    fn email_rules() {
+        assert_eq!(validate_email("a.b+c@mail.example.co"), Ok(()));
+        assert_eq!(validate_email("ann"), Err(EmailError::MissingAt));
+        assert_eq!(validate_email("a@b@c.com"), Err(EmailError::MultipleAt));
+        assert_eq!(validate_email("@c.com"), Err(EmailError::EmptyLocalPart));
+        assert_eq!(
+            validate_email(&format!("{}@c.com", "a".repeat(65))),
+            Err(EmailError::LocalPartTooLong { max: 64 })
+        );
+        assert_eq!(
+            validate_email("a b@c.com"),
+            Err(EmailError::InvalidLocalChar(' '))
+        );
+        assert_eq!(
+            validate_email("a..b@c.com"),
+            Err(EmailError::InvalidLocalChar('.'))
+        );
+        for bad in [
+            "a@com",
+            "a@b..com",
+            "a@-b.com",
+            "a@b.c",
+            "a@b.c0m",
+            "a@b_c.com",
+        ] {
+            assert!(
+                matches!(validate_email(bad), Err(EmailError::InvalidDomain(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn update_moves_the_lookup_keys() {
+        let mut registry = registry();
+        let changes = registry
+            .update(
+                "ANN",
+                UserPatch::new().username("anna").email("anna@example.com"),
+            )
+            .unwrap();
+        let fields: Vec<&str> = changes.iter().map(|c| c.field).collect();
+        assert_eq!(fields, ["username", "email"]);
+        assert!(registry.by_username("ann").is_none());
+        assert!(registry.by_email("ann@example.com").is_none());
+        assert_eq!(registry.find("anna@example.com").unwrap().username, "anna");
+
+        // keeping your own name in a different case is fine; taking
+        // someone else's isn't.
+        assert!(registry
+            .update("anna", UserPatch::new().username("ANNA"))
+            .is_ok());
+        let e = registry
+            .update("anna", UserPatch::new().username("bob"))
+            .unwrap_err();
+        assert!(matches!(e, RegistryError::DuplicateUsername(_)));
+        let e = registry
+            .update("anna", UserPatch::new().email("nope"))
+            .unwrap_err();
+        assert!(matches!(e, RegistryError::InvalidEmail(..)));
+        assert_eq!(
+            registry.by_username("anna").unwrap().email,
+            "anna@example.com"
+        );
+        let e = registry
+            .update("cat", UserPatch::new().active(false))
+            .unwrap_err();
+        assert!(matches!(e, RegistryError::UnknownUser(_)));
+    }
+
+    fn round_trip(extension: &str) {
+        let mut registry = registry();
+        registry
+            .update("bob", UserPatch::new().active(false).sign_in_count(7))
+            .unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "structs-registry-test-{}.{extension}",
+            std::process::id()
+        ));
+        registry.save(&path).unwrap();
+        let loaded = UserRegistry::load(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            registry.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(loaded.find("BOB").unwrap().sign_in_count, 7);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip("json");
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        round_trip("csv");
+    }
+
+    #[test]
+    fn unknown_extensions_are_refused() {
+        let e = registry().save("users.txt").unwrap_err();
+        assert!(matches!(e, RegistryError::UnknownFormat(_)));
+    }
+
+    #[test]
+    fn loading_checks_every_user() {
+        let text = "username,email,active,sign_in_count,password_hash\n\
+                    ann,ann@example.com,true,1,\n\
+                    ANN,other@example.com,true,1,\n";
+        let users = users_from_csv(text).unwrap();
+        assert_eq!(users.len(), 2);
+        let mut registry = UserRegistry::new();
+        let results: Vec<bool> = users
+            .into_iter()
+            .map(|u| registry.register(u).is_ok())
+            .collect();
+        assert_eq!(results, [true, false]);
+        assert!(matches!(
+            users_from_csv("ann,ann@example.com,maybe,1,\n"),
+            Err(RegistryError::Parse { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,540 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand::RngCore;
+
+use crate::credentials::{KdfParams, PasswordError, PasswordPolicy};
+use crate::user::User;
+
+// SIGN-INS AND SESSIONS
+
+// Instead of bumping `user.sign_in_count += 1` by hand, the SessionManager
+// does the bookkeeping for each sign-in attempt:
+//   - a successful sign-in bumps sign_in_count and hands out a Session
+//     with a random token that expires after `session_ttl`
+//   - a failed one is counted, and after `max_failed_attempts` in a row the
+//     user is deactivated (active = false) until someone turns them back on
+//   - every one of these things is written to the user's audit log
+//
+// sign_in_with_password checks the user's password (see credentials.rs)
+// and picks between the two. Callers with some other way of checking who
+// someone is can call sign_in or fail_sign_in themselves.
+
+pub const DEFAULT_MAX_FAILED_ATTEMPTS: u32 = 5;
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub token: String,
+    pub username: String,
+    pub created_at: SystemTime,
+    pub expires_at: SystemTime,
+}
+
+impl Session {
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditKind {
+    SignedIn { session: String }, // first few characters of the token
+    SignInFailed { attempt: u32 },
+    SignInRejected, // tried to sign in while deactivated
+    Deactivated { after_attempts: u32 },
+    Reactivated,
+    SignedOut { session: String },
+    SessionExpired { session: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    pub at: SystemTime,
+    pub kind: AuditKind,
+}
+
+impl fmt::Display for AuditEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = self
+            .at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        write!(f, "[{secs}] ")?;
+        match &self.kind {
+            AuditKind::SignedIn { session } => write!(f, "signed in (session {session}…)"),
+            AuditKind::SignInFailed { attempt } => write!(f, "failed sign-in #{attempt}"),
+            AuditKind::SignInRejected => write!(f, "sign-in rejected: account is deactivated"),
+            AuditKind::Deactivated { after_attempts } => {
+                write!(f, "deactivated after {after_attempts} failed sign-ins")
+            }
+            AuditKind::Reactivated => write!(f, "reactivated"),
+            AuditKind::SignedOut { session } => write!(f, "signed out (session {session}…)"),
+            AuditKind::SessionExpired { session } => write!(f, "session {session}… expired"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    Inactive(String),
+    WrongPassword(FailedSignIn),
+    Password(PasswordError),
+    UnknownSession,
+    Expired,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionError::Inactive(username) => write!(f, "user '{username}' is deactivated"),
+            SessionError::WrongPassword(FailedSignIn::AttemptsLeft(left)) => {
+                write!(f, "wrong password ({left} attempts left)")
+            }
+            SessionError::WrongPassword(FailedSignIn::Deactivated) => {
+                write!(f, "wrong password - account is now deactivated")
+            }
+            SessionError::Password(e) => write!(f, "{e}"),
+            SessionError::UnknownSession => write!(f, "no such session"),
+            SessionError::Expired => write!(f, "session has expired"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+// What happened to a failed sign-in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailedSignIn {
+    AttemptsLeft(u32),
+    Deactivated,
+}
+
+pub struct SessionManager {
+    pub max_failed_attempts: u32,
+    pub session_ttl: Duration,
+    sessions: HashMap<String, Session>,
+    failures: HashMap<String, u32>,
+    audit: HashMap<String, Vec<AuditEvent>>,
+    // The clock is a plain function so a fake one can stand in for
+    // SystemTime::now - handy for checking expiry without waiting an hour.
+    pub clock: fn() -> SystemTime,
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        SessionManager::new()
+    }
+}
+
+impl SessionManager {
+    pub fn new() -> SessionManager {
+        SessionManager {
+            max_failed_attempts: DEFAULT_MAX_FAILED_ATTEMPTS,
+            session_ttl: DEFAULT_SESSION_TTL,
+            sessions: HashMap::new(),
+            failures: HashMap::new(),
+            audit: HashMap::new(),
+            clock: SystemTime::now,
+        }
+    }
+
+    fn log(&mut self, username: &str, kind: AuditKind) {
+        let event = AuditEvent {
+            at: (self.clock)(),
+            kind,
+        };
+        self.audit
+            .entry(username.to_string())
+            .or_default()
+            .push(event);
+    }
+
+    pub fn sign_in(&mut self, user: &mut User) -> Result<Session, SessionError> {
+        if !user.active {
+            self.log(&user.username, AuditKind::SignInRejected);
+            return Err(SessionError::Inactive(user.username.clone()));
+        }
+
+        let now = (self.clock)();
+        let session = Session {
+            token: new_token(),
+            username: user.username.clone(),
+            created_at: now,
+            expires_at: saturating_add(now, self.session_ttl),
+        };
+
+        user.sign_in_count += 1;
+        self.failures.remove(&user.username);
+        self.sessions.insert(session.token.clone(), session.clone());
+        self.log(
+            &user.username,
+            AuditKind::SignedIn {
+                session: short(&session.token),
+            },
+        );
+        Ok(session)
+    }
+
+    // Checks the password and signs in or counts a failure. A correct
+    // password with an out-of-date hash gets rehashed with `params` on the
+    // way through.
+    pub fn sign_in_with_password(
+        &mut self,
+        user: &mut User,
+        password: &str,
+        policy: &PasswordPolicy,
+        params: &KdfParams,
+    ) -> Result<Session, SessionError> {
+        if !user.active {
+            return self.sign_in(user); // rejected and logged, without hashing.
+        }
+        match user.check_password(password, policy, params) {
+            Ok(true) => self.sign_in(user),
+            Ok(false) => Err(SessionError::WrongPassword(self.fail_sign_in(user))),
+            Err(e) => Err(SessionError::Password(e)),
+        }
+    }
+
+    pub fn fail_sign_in(&mut self, user: &mut User) -> FailedSignIn {
+        if !user.active {
+            self.log(&user.username, AuditKind::SignInRejected);
+            return FailedSignIn::Deactivated;
+        }
+
+        let attempts = self.failures.entry(user.username.clone()).or_insert(0);
+        *attempts += 1;
+        let attempt = *attempts;
+        self.log(&user.username, AuditKind::SignInFailed { attempt });
+
+        if attempt >= self.max_failed_attempts {
+            user.active = false;
+            self.failures.remove(&user.username);
+            self.log(
+                &user.username,
+                AuditKind::Deactivated {
+                    after_attempts: attempt,
+                },
+            );
+            // a locked-out user shouldn't keep any sessions they already had.
+            self.end_sessions_for(&user.username);
+            FailedSignIn::Deactivated
+        } else {
+            FailedSignIn::AttemptsLeft(self.max_failed_attempts - attempt)
+        }
+    }
+
+    // Turns a deactivated user back on with a clean slate.
+    pub fn reactivate(&mut self, user: &mut User) {
+        user.active = true;
+        self.failures.remove(&user.username);
+        self.log(&user.username, AuditKind::Reactivated);
+    }
+
+    pub fn sign_out(&mut self, token: &str) -> Result<(), SessionError> {
+        let session = self
+            .sessions
+            .remove(token)
+            .ok_or(SessionError::UnknownSession)?;
+        self.log(
+            &session.username,
+            AuditKind::SignedOut {
+                session: short(token),
+            },
+        );
+        Ok(())
+    }
+
+    // Looks a token up. An expired session is removed (and logged) the
+    // first time someone tries to use it.
+    pub fn validate(&mut self, token: &str) -> Result<&Session, SessionError> {
+        let now = (self.clock)();
+        let expired = match self.sessions.get(token) {
+            None => return Err(SessionError::UnknownSession),
+            Some(session) => session.is_expired(now),
+        };
+        if expired {
+            if let Some(session) = self.sessions.remove(token) {
+                self.log(
+                    &session.username,
+                    AuditKind::SessionExpired {
+                        session: short(token),
+                    },
+                );
+            }
+            return Err(SessionError::Expired);
+        }
+        self.sessions.get(token).ok_or(SessionError::UnknownSession)
+    }
+
+    // Drops every expired session, returning how many there were.
+    pub fn expire_sessions(&mut self) -> usize {
+        let now = (self.clock)();
+        let expired: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| session.is_expired(now))
+            .cloned()
+            .collect();
+        for session in &expired {
+            self.sessions.remove(&session.token);
+            self.log(
+                &session.username,
+                AuditKind::SessionExpired {
+                    session: short(&session.token),
+                },
+            );
+        }
+        expired.len()
+    }
+
+    fn end_sessions_for(&mut self, username: &str) {
+        let tokens: Vec<String> = self
+            .sessions
+            .values()
+            .filter(|session| session.username == username)
+            .map(|session| session.token.clone())
+            .collect();
+        for token in tokens {
+            self.sessions.remove(&token);
+            self.log(
+                username,
+                AuditKind::SignedOut {
+                    session: short(&token),
+                },
+            );
+        }
+    }
+
+    pub fn active_sessions(&self, username: &str) -> usize {
+        self.sessions
+            .values()
+            .filter(|session| session.username == username)
+            .count()
+    }
+
+    pub fn audit_log(&self, username: &str) -> &[AuditEvent] {
+        self.audit
+            .get(username)
+            .map_or(&[], |events| events.as_slice())
+    }
+}
+
+// `time + duration`, except that a duration too long to add (a session_ttl
+// of Duration::MAX, say, for sessions that never expire) stops at the
+// latest time SystemTime can hold instead of panicking. The latest time
+// depends on the platform, so it's found by adding as much of the duration
+// as still fits, in halves.
+fn saturating_add(time: SystemTime, duration: Duration) -> SystemTime {
+    let (mut time, mut left) = (time, duration);
+    let mut step = duration;
+    while step > Duration::ZERO {
+        match time.checked_add(step) {
+            Some(later) => {
+                time = later;
+                left -= step;
+                step = step.min(left);
+            }
+            None => step /= 2,
+        }
+    }
+    time
+}
+
+// 32 random bytes as hex. thread_rng is a cryptographically secure
+// generator, so tokens can't be guessed from earlier ones.
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+// Only the start of a token goes in the audit log - the log shouldn't be
+// enough to hijack a session.
+fn short(token: &str) -> String {
+    token.chars().take(8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    use crate::user::build_user;
+
+    // A clock the tests can wind forward. Each test runs on its own thread,
+    // so each gets its own time.
+    thread_local! {
+        static NOW: Cell<u64> = const { Cell::new(1_000) };
+    }
+
+    fn fake_clock() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(NOW.get())
+    }
+
+    fn wind_to(secs: u64) {
+        NOW.set(secs);
+    }
+
+    fn manager() -> SessionManager {
+        SessionManager {
+            max_failed_attempts: 3,
+            session_ttl: Duration::from_secs(60),
+            clock: fake_clock,
+            ..SessionManager::new()
+        }
+    }
+
+    fn ann() -> User {
+        build_user(String::from("ann@example.com"), String::from("ann"))
+    }
+
+    fn kinds(manager: &SessionManager) -> Vec<AuditKind> {
+        manager
+            .audit_log("ann")
+            .iter()
+            .map(|e| e.kind.clone())
+            .collect()
+    }
+
+    #[test]
+    fn sessions_expire_after_the_ttl() {
+        let (mut manager, mut user) = (manager(), ann());
+        let session = manager.sign_in(&mut user).unwrap();
+        assert_eq!(session.expires_at, UNIX_EPOCH + Duration::from_secs(1_060));
+        assert_eq!(user.sign_in_count, 2);
+
+        wind_to(1_059);
+        assert_eq!(manager.validate(&session.token), Ok(&session));
+        wind_to(1_060);
+        assert_eq!(manager.validate(&session.token), Err(SessionError::Expired));
+        // and it's gone after that.
+        assert_eq!(
+            manager.validate(&session.token),
+            Err(SessionError::UnknownSession)
+        );
+        assert_eq!(manager.active_sessions("ann"), 0);
+        let expired = AuditKind::SessionExpired {
+            session: short(&session.token),
+        };
+        assert_eq!(kinds(&manager).last(), Some(&expired));
+    }
+
+    #[test]
+    fn expire_sessions_drops_only_the_old_ones() {
+        let (mut manager, mut user) = (manager(), ann());
+        manager.sign_in(&mut user).unwrap();
+        wind_to(1_030);
+        let newer = manager.sign_in(&mut user).unwrap();
+        wind_to(1_060);
+        assert_eq!(manager.expire_sessions(), 1);
+        assert_eq!(manager.active_sessions("ann"), 1);
+        assert!(manager.validate(&newer.token).is_ok());
+    }
+
+    #[test]
+    fn a_huge_ttl_never_expires_instead_of_panicking() {
+        let (mut manager, mut user) = (manager(), ann());
+        manager.session_ttl = Duration::MAX;
+        let session = manager.sign_in(&mut user).unwrap();
+        assert!(session.expires_at > fake_clock() + Duration::from_secs(1 << 40));
+        wind_to(u32::MAX as u64);
+        assert!(manager.validate(&session.token).is_ok());
+    }
+
+    #[test]
+    fn too_many_failures_deactivate_the_user() {
+        let (mut manager, mut user) = (manager(), ann());
+        let session = manager.sign_in(&mut user).unwrap();
+        assert_eq!(
+            manager.fail_sign_in(&mut user),
+            FailedSignIn::AttemptsLeft(2)
+        );
+        assert_eq!(
+            manager.fail_sign_in(&mut user),
+            FailedSignIn::AttemptsLeft(1)
+        );
+        assert!(user.active);
+        assert_eq!(manager.fail_sign_in(&mut user), FailedSignIn::Deactivated);
+        assert!(!user.active);
+        // and any session they had is over.
+        assert_eq!(
+            manager.validate(&session.token),
+            Err(SessionError::UnknownSession)
+        );
+        assert_eq!(
+            manager.sign_in(&mut user),
+            Err(SessionError::Inactive(String::from("ann")))
+        );
+        assert_eq!(manager.fail_sign_in(&mut user), FailedSignIn::Deactivated);
+    }
+
+    #[test]
+    fn a_successful_sign_in_resets_the_count() {
+        let (mut manager, mut user) = (manager(), ann());
+        manager.fail_sign_in(&mut user);
+        manager.fail_sign_in(&mut user);
+        manager.sign_in(&mut user).unwrap();
+        assert_eq!(
+            manager.fail_sign_in(&mut user),
+            FailedSignIn::AttemptsLeft(2)
+        );
+    }
+
+    #[test]
+    fn reactivation_starts_over() {
+        let (mut manager, mut user) = (manager(), ann());
+        for _ in 0..3 {
+            manager.fail_sign_in(&mut user);
+        }
+        manager.reactivate(&mut user);
+        assert!(user.active);
+        assert_eq!(
+            manager.fail_sign_in(&mut user),
+            FailedSignIn::AttemptsLeft(2)
+        );
+        assert!(manager.sign_in(&mut user).is_ok());
+    }
+
+    #[test]
+    fn everything_goes_in_the_audit_log() {
+        let (mut manager, mut user) = (manager(), ann());
+        let session = manager.sign_in(&mut user).unwrap();
+        let token = short(&session.token);
+        assert_eq!(token.len(), 8);
+        manager.sign_out(&session.token).unwrap();
+        assert_eq!(
+            manager.sign_out(&session.token),
+            Err(SessionError::UnknownSession)
+        );
+        for _ in 0..3 {
+            manager.fail_sign_in(&mut user);
+        }
+        let _ = manager.sign_in(&mut user);
+        manager.reactivate(&mut user);
+
+        assert_eq!(
+            kinds(&manager),
+            [
+                AuditKind::SignedIn {
+                    session: token.clone()
+                },
+                AuditKind::SignedOut { session: token },
+                AuditKind::SignInFailed { attempt: 1 },
+                AuditKind::SignInFailed { attempt: 2 },
+                AuditKind::SignInFailed { attempt: 3 },
+                AuditKind::Deactivated { after_attempts: 3 },
+                AuditKind::SignInRejected,
+                AuditKind::Reactivated,
+            ]
+        );
+        let log = manager.audit_log("ann");
+        assert_eq!(
+            log[5].to_string(),
+            "[1000] deactivated after 3 failed sign-ins"
+        );
+        assert!(manager.audit_log("bob").is_empty());
+    }
+}
@@ -0,0 +1,552 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::credentials::Credentials;
+use crate::csv;
+use crate::registry::{validate_email, validate_username, UserRegistry};
+use crate::user::User;
+
+// IMPORT AND EXPORT
+
+// UserRegistry::load reads a whole file and gives up at the first bad
+// line, which is right for the registry's own files but not for bulk-
+// loading fixtures someone typed by hand. This module reads and writes
+// users one record at a time in three formats:
+//
+//   CSV          the same columns as the registry's .csv files
+//   JSON Lines   one JSON object per line (.jsonl)
+//   vCard        BEGIN:VCARD ... END:VCARD blocks, like a contacts export (.vcf)
+//
+// UserReader is an iterator over a BufRead, so a file is never held in
+// memory all at once. A bad record comes out as an Err with its line
+// number and reading carries on with the next one; import() collects
+// everything into an ImportReport that says what happened.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    JsonLines,
+    VCard,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            "jsonl" => Some(Format::JsonLines),
+            "vcf" | "vcard" => Some(Format::VCard),
+            _ => None,
+        }
+    }
+}
+
+// ERRORS
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+// CSV ROWS
+
+// Shared with UserRegistry::save/load so both write the same files.
+pub const CSV_HEADER: &str = "username,email,active,sign_in_count,password_hash";
+
+pub fn csv_row(user: &User) -> String {
+    let active = user.active.to_string();
+    let count = user.sign_in_count.to_string();
+    let hash = user.credentials.as_ref().map_or("", |c| c.as_str());
+    csv::write_record(&[&user.username, &user.email, &active, &count, hash])
+}
+
+pub fn is_csv_header(line: &str) -> bool {
+    line.starts_with("username,email,")
+}
+
+pub fn user_from_csv_row(line: &str) -> Result<User, String> {
+    let fields = csv::parse_record(line)?;
+    // the password_hash column is optional, so files written before there
+    // were passwords still load.
+    let (username, email, active, count, hash) = match fields.as_slice() {
+        [username, email, active, count] => (username, email, active, count, ""),
+        [username, email, active, count, hash] => (username, email, active, count, hash.as_str()),
+        _ => {
+            return Err(format!(
+                "expected 4 or 5 fields ({CSV_HEADER}), found {}",
+                fields.len()
+            ))
+        }
+    };
+    Ok(User {
+        username: username.clone(),
+        email: email.clone(),
+        active: parse_active(active)?,
+        sign_in_count: parse_count(count)?,
+        credentials: parse_hash(hash)?,
+    })
+}
+
+fn parse_active(text: &str) -> Result<bool, String> {
+    text.parse()
+        .map_err(|_| format!("active must be true or false, got '{text}'"))
+}
+
+fn parse_count(text: &str) -> Result<u64, String> {
+    text.parse()
+        .map_err(|_| format!("sign_in_count must be a number, got '{text}'"))
+}
+
+fn parse_hash(text: &str) -> Result<Option<Credentials>, String> {
+    match text {
+        "" => Ok(None),
+        hash => Credentials::from_hash(hash)
+            .map(Some)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+// VCARD
+
+// Just the properties we need: NICKNAME is the username and EMAIL the
+// email, plus X- extensions for the rest. FN is written so contact apps
+// show a name, and ignored (like any other property) when reading.
+// Property names are case-insensitive, and parameters (EMAIL;TYPE=work:)
+// are skipped over.
+fn vcard(user: &User) -> String {
+    let mut card = String::from("BEGIN:VCARD\nVERSION:4.0\n");
+    card.push_str(&format!("FN:{}\n", vcard_escape(&user.username)));
+    card.push_str(&format!("NICKNAME:{}\n", vcard_escape(&user.username)));
+    card.push_str(&format!("EMAIL:{}\n", vcard_escape(&user.email)));
+    card.push_str(&format!("X-ACTIVE:{}\n", user.active));
+    card.push_str(&format!("X-SIGN-IN-COUNT:{}\n", user.sign_in_count));
+    if let Some(credentials) = &user.credentials {
+        card.push_str(&format!("X-PASSWORD-HASH:{}\n", credentials.as_str()));
+    }
+    card.push_str("END:VCARD\n");
+    card
+}
+
+// vCard backslash-escapes commas, semicolons, backslashes and newlines.
+fn vcard_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ',' | ';' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn vcard_unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// The fields of a card being read, filled in as its lines go by.
+#[derive(Default)]
+struct CardFields {
+    start: usize,
+    username: Option<String>,
+    email: Option<String>,
+    active: Option<bool>,
+    sign_in_count: Option<u64>,
+    credentials: Option<Credentials>,
+}
+
+impl CardFields {
+    fn set(&mut self, line: &str) -> Result<(), String> {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(format!("expected NAME:value, got '{line}'"));
+        };
+        // EMAIL;TYPE=work -> EMAIL
+        let name = name
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        match name.as_str() {
+            "NICKNAME" => self.username = Some(vcard_unescape(value)),
+            "EMAIL" => self.email = Some(vcard_unescape(value)),
+            "X-ACTIVE" => self.active = Some(parse_active(value)?),
+            "X-SIGN-IN-COUNT" => self.sign_in_count = Some(parse_count(value)?),
+            "X-PASSWORD-HASH" => self.credentials = parse_hash(value)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn into_user(self) -> Result<User, String> {
+        Ok(User {
+            username: self.username.ok_or("card has no NICKNAME (username)")?,
+            email: self.email.ok_or("card has no EMAIL")?,
+            // same defaults as build_user.
+            active: self.active.unwrap_or(true),
+            sign_in_count: self.sign_in_count.unwrap_or(1),
+            credentials: self.credentials,
+        })
+    }
+}
+
+// READING
+
+pub struct UserReader<R: BufRead> {
+    input: R,
+    format: Format,
+    line_number: usize,
+    // where the record being read started (a card's BEGIN:VCARD line).
+    record_line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> UserReader<R> {
+    pub fn new(input: R, format: Format) -> UserReader<R> {
+        UserReader {
+            input,
+            format,
+            line_number: 0,
+            record_line: 0,
+            done: false,
+        }
+    }
+
+    // The next line without its line ending, or None at the end. A line
+    // that isn't UTF-8 is an error for that line only, and reading carries
+    // on with the next one. An I/O error ends the reading - there's no point
+    // trying the next line.
+    fn next_line(&mut self) -> Option<Result<String, ImportError>> {
+        if self.done {
+            return None;
+        }
+        // read_until rather than read_line, which would throw the whole
+        // line away and give up on a bad byte.
+        let mut bytes = Vec::new();
+        match self.input.read_until(b'\n', &mut bytes) {
+            Ok(0) => {
+                self.done = true;
+                None
+            }
+            Ok(_) => {
+                self.line_number += 1;
+                while bytes.last().is_some_and(|&b| b == b'\n' || b == b'\r') {
+                    bytes.pop();
+                }
+                match String::from_utf8(bytes) {
+                    Ok(line) => Some(Ok(line)),
+                    Err(e) => Some(Err(
+                        self.error(format!("not valid UTF-8 ({})", e.utf8_error()))
+                    )),
+                }
+            }
+            Err(e) => {
+                self.done = true;
+                // the line we were trying to read, not the last one read.
+                Some(Err(ImportError {
+                    line: self.line_number + 1,
+                    message: e.to_string(),
+                }))
+            }
+        }
+    }
+
+    fn error(&self, message: String) -> ImportError {
+        ImportError {
+            line: self.line_number,
+            message,
+        }
+    }
+
+    fn next_card(&mut self, begin: usize) -> Result<User, ImportError> {
+        let mut card = CardFields {
+            start: begin,
+            ..CardFields::default()
+        };
+        let mut problem = None;
+        loop {
+            let line = match self.next_line() {
+                Some(Ok(line)) => line,
+                // keep reading to END:VCARD even after a bad line, so the
+                // next card starts in the right place - but report the first
+                // problem.
+                Some(Err(e)) => {
+                    problem.get_or_insert(e);
+                    continue;
+                }
+                None => {
+                    return Err(problem.unwrap_or(ImportError {
+                        line: card.start,
+                        message: String::from("card has no END:VCARD"),
+                    }))
+                }
+            };
+            if line.eq_ignore_ascii_case("END:VCARD") {
+                break;
+            }
+            if problem.is_none() {
+                if let Err(message) = card.set(&line) {
+                    problem = Some(self.error(message));
+                }
+            }
+        }
+        if let Some(e) = problem {
+            return Err(e);
+        }
+        let start = card.start;
+        card.into_user().map_err(|message| ImportError {
+            line: start,
+            message,
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for UserReader<R> {
+    type Item = Result<User, ImportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.next_line()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            self.record_line = self.line_number;
+            let user = match self.format {
+                Format::Csv if self.line_number == 1 && is_csv_header(&line) => continue,
+                Format::Csv => user_from_csv_row(&line).map_err(|m| self.error(m)),
+                Format::JsonLines => {
+                    serde_json::from_str::<User>(&line).map_err(|e| self.error(e.to_string()))
+                }
+                Format::VCard if line.eq_ignore_ascii_case("BEGIN:VCARD") => {
+                    self.next_card(self.line_number)
+                }
+                Format::VCard => Err(self.error(format!("expected BEGIN:VCARD, got '{line}'"))),
+            };
+            let line = self.record_line;
+            // every format gets the same checks as a sign-up.
+            return Some(user.and_then(|user| {
+                validate(&user).map_err(|message| ImportError { line, message })?;
+                Ok(user)
+            }));
+        }
+    }
+}
+
+fn validate(user: &User) -> Result<(), String> {
+    validate_username(&user.username).map_err(|e| format!("username '{}' {e}", user.username))?;
+    validate_email(&user.email).map_err(|e| format!("email '{}' {e}", user.email))
+}
+
+// REPORTS
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub users: Vec<User>,
+    pub errors: Vec<ImportError>,
+}
+
+impl ImportReport {
+    pub fn records(&self) -> usize {
+        self.users.len() + self.errors.len()
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} records: {} imported, {} failed",
+            self.records(),
+            self.users.len(),
+            self.errors.len()
+        )?;
+        for e in &self.errors {
+            write!(f, "\n  {e}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn import<R: BufRead>(input: R, format: Format) -> ImportReport {
+    let mut report = ImportReport::default();
+    for record in UserReader::new(input, format) {
+        match record {
+            Ok(user) => report.users.push(user),
+            Err(e) => report.errors.push(e),
+        }
+    }
+    report
+}
+
+// Picks the format from the file extension.
+pub fn import_file(path: impl AsRef<Path>) -> io::Result<ImportReport> {
+    let path = path.as_ref();
+    let format = Format::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "don't know how to import '{}' (use .csv, .jsonl or .vcf)",
+                path.display()
+            ),
+        )
+    })?;
+    Ok(import(BufReader::new(File::open(path)?), format))
+}
+
+// Registers each imported user as it's read. Users the registry turns
+// away (a username that's taken, say) are reported against their line
+// like any other bad record; the rest still go in.
+pub fn import_into<R: BufRead>(
+    registry: &mut UserRegistry,
+    input: R,
+    format: Format,
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut reader = UserReader::new(input, format);
+    while let Some(record) = reader.next() {
+        let result = record.and_then(|user| {
+            registry
+                .register(user)
+                .cloned()
+                .map_err(|(_, e)| ImportError {
+                    line: reader.record_line,
+                    message: e.to_string(),
+                })
+        });
+        match result {
+            Ok(user) => report.users.push(user),
+            Err(e) => report.errors.push(e),
+        }
+    }
+    report
+}
+
+// WRITING
+
+// Writes users one at a time. The CSV header goes out with the first
+// user, so writing nobody produces an empty file.
+pub struct UserWriter<W: Write> {
+    output: W,
+    format: Format,
+    written: usize,
+}
+
+impl<W: Write> UserWriter<W> {
+    pub fn new(output: W, format: Format) -> UserWriter<W> {
+        UserWriter {
+            output,
+            format,
+            written: 0,
+        }
+    }
+
+    pub fn write(&mut self, user: &User) -> io::Result<()> {
+        match self.format {
+            Format::Csv => {
+                if self.written == 0 {
+                    writeln!(self.output, "{CSV_HEADER}")?;
+                }
+                writeln!(self.output, "{}", csv_row(user))?;
+            }
+            Format::JsonLines => {
+                serde_json::to_writer(&mut self.output, user)?;
+                writeln!(self.output)?;
+            }
+            Format::VCard => write!(self.output, "{}", vcard(user))?,
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    // Flushes and hands back the output (handy when it's a Vec<u8>).
+    pub fn finish(mut self) -> io::Result<W> {
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+pub fn export<'a, W: Write>(
+    users: impl IntoIterator<Item = &'a User>,
+    output: W,
+    format: Format,
+) -> io::Result<usize> {
+    let mut writer = UserWriter::new(output, format);
+    for user in users {
+        writer.write(user)?;
+    }
+    let written = writer.written();
+    writer.finish()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(report: &ImportReport) -> Vec<usize> {
+        report.errors.iter().map(|e| e.line).collect()
+    }
+
+    #[test]
+    fn bad_utf8_only_fails_its_own_line() {
+        let mut input = b"ann,ann@example.com,true,1\n".to_vec();
+        input.extend_from_slice(b"b\xffb,bob@example.com,true,1\n");
+        input.extend_from_slice(b"cat,cat@example.com,true,1\n");
+        let report = import(&input[..], Format::Csv);
+        assert_eq!(report.users.len(), 2);
+        assert_eq!(lines(&report), [2]);
+        assert!(report.errors[0].message.contains("UTF-8"));
+    }
+
+    #[test]
+    fn bad_utf8_inside_a_card_fails_that_card() {
+        let input = b"BEGIN:VCARD\nNICKNAME:a\xffn\nEMAIL:ann@example.com\nEND:VCARD\n\
+                      BEGIN:VCARD\nNICKNAME:bob\nEMAIL:bob@example.com\nEND:VCARD\n";
+        let report = import(&input[..], Format::VCard);
+        assert_eq!(report.users.len(), 1);
+        assert_eq!(report.users[0].username, "bob");
+        assert_eq!(lines(&report), [2]);
+    }
+
+    #[test]
+    fn registry_rejections_are_reported_not_panicked_on() {
+        let mut registry = UserRegistry::new();
+        let input = "ann,ann@example.com,true,1\nann,other@example.com,true,1\n";
+        let report = import_into(&mut registry, input.as_bytes(), Format::Csv);
+        assert_eq!(report.users.len(), 1);
+        assert_eq!(lines(&report), [2]);
+        assert_eq!(registry.len(), 1);
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+use crate::credentials::Credentials;
+
+// STRUCTS
+
+// structs are an object-oriented-ish feature of rust - it allows
+// us to define certain types that contain data in an organized format.
+// They're similar to objects in javascript, but are more like
+// object orientation in other languages in that they actually define
+// new types that our program can use:
+
+// (User lives in its own module now so the registry and friends can use it.
+// Fields are pub so main can still build one by hand like in the examples.)
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct User {
+    pub active: bool, // these are called "fields"
+    pub username: String,
+    pub email: String,
+    pub sign_in_count: u64,
+    // a hashed password, if the user has set one (see credentials.rs).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<Credentials>,
+}
+
+pub fn build_user(email: String, username: String) -> User {
+    User {
+        email, // uses field init shorthand, which allows us to declare
+        // a field with the key name of the variable and the
+        // value of the variable.
+        username,
+        active: true,
+        sign_in_count: 1,
+        credentials: None,
+    }
+    // this whole function is return a new instance of the user
+    // struct
+}
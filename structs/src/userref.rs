@@ -0,0 +1,376 @@
+use std::fmt;
+
+use crate::credentials::{Credentials, PasswordError};
+use crate::user::User;
+
+// STRUCTS THAT BORROW: LIFETIMES
+
+// User owns its Strings, so making one from a line of a file means copying
+// the username and email out of the file's text into new allocations.
+// When all we want is to look at a million lines - count the active users,
+// find one email - that copying is most of the work.
+//
+// UserRef holds &str slices pointing straight into the text instead. The
+// 'a says how long that's allowed: a UserRef<'a> can't outlive the buffer
+// it was parsed from, and the compiler checks it:
+//
+//     let user = {
+//         let text = std::fs::read_to_string("users.csv")?;
+//         parse_records(&text, ',').next()   // borrows text...
+//     };                                     // throws error: `text` dropped here while still borrowed
+//
+// Parsing never allocates - not even for errors, which borrow the bad
+// field too. When a UserRef needs to outlive its buffer, to_user() copies
+// it into an owned User.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserRef<'a> {
+    pub username: &'a str,
+    pub email: &'a str,
+    pub active: bool,
+    pub sign_in_count: u64,
+    pub password_hash: Option<&'a str>,
+}
+
+impl<'a> UserRef<'a> {
+    // Copies the borrowed fields into a User of its own. The hash is
+    // checked here rather than while parsing, since checking it is only
+    // worth doing for records we're going to keep.
+    pub fn to_user(self) -> Result<User, PasswordError> {
+        Ok(User {
+            username: self.username.to_string(),
+            email: self.email.to_string(),
+            active: self.active,
+            sign_in_count: self.sign_in_count,
+            credentials: self.password_hash.map(Credentials::from_hash).transpose()?,
+        })
+    }
+}
+
+impl TryFrom<UserRef<'_>> for User {
+    type Error = PasswordError;
+
+    fn try_from(user: UserRef<'_>) -> Result<User, PasswordError> {
+        user.to_user()
+    }
+}
+
+// Going the other way borrows from the User, so the UserRef can't outlive
+// it.
+impl<'a> From<&'a User> for UserRef<'a> {
+    fn from(user: &'a User) -> UserRef<'a> {
+        UserRef {
+            username: &user.username,
+            email: &user.email,
+            active: user.active,
+            sign_in_count: user.sign_in_count,
+            password_hash: user.credentials.as_ref().map(Credentials::as_str),
+        }
+    }
+}
+
+// ERRORS
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordError<'a> {
+    pub line: usize,
+    pub kind: RecordErrorKind<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordErrorKind<'a> {
+    FieldCount(usize),
+    Active(&'a str),
+    SignInCount(&'a str),
+    UnterminatedQuote,
+    // A quoted field with "" inside would have to be rebuilt without the
+    // doubled quotes, which means allocating. csv::parse_record can read
+    // those lines.
+    EscapedQuote,
+}
+
+impl fmt::Display for RecordError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            RecordErrorKind::FieldCount(found) => {
+                write!(f, "expected 4 or 5 fields, found {found}")
+            }
+            RecordErrorKind::Active(value) => {
+                write!(f, "active must be true or false, got '{value}'")
+            }
+            RecordErrorKind::SignInCount(value) => {
+                write!(f, "sign_in_count must be a number, got '{value}'")
+            }
+            RecordErrorKind::UnterminatedQuote => write!(f, "unterminated quoted field"),
+            RecordErrorKind::EscapedQuote => {
+                write!(f, "escaped quotes (\"\") can't be read without copying")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecordError<'_> {}
+
+// PARSING
+
+// The same columns as the registry's CSV files (username, email, active,
+// sign_in_count, and optionally password_hash), separated by `delimiter` -
+// ',' for CSV or '\t' for TSV. A header line and blank lines are skipped.
+pub fn parse_records(text: &str, delimiter: char) -> Records<'_> {
+    Records {
+        lines: text.lines().enumerate(),
+        delimiter,
+    }
+}
+
+// An iterator, so records are parsed one at a time as they're asked for -
+// nothing is collected unless the caller collects it.
+pub struct Records<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    delimiter: char,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<UserRef<'a>, RecordError<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, line) in self.lines.by_ref() {
+            let is_header = index == 0
+                && line
+                    .strip_prefix("username")
+                    .is_some_and(|rest| rest.starts_with(self.delimiter));
+            if line.trim().is_empty() || is_header {
+                continue;
+            }
+            return Some(
+                parse_line(line, self.delimiter).map_err(|kind| RecordError {
+                    line: index + 1,
+                    kind,
+                }),
+            );
+        }
+        None
+    }
+}
+
+const MAX_FIELDS: usize = 5;
+
+fn parse_line(line: &str, delimiter: char) -> Result<UserRef<'_>, RecordErrorKind<'_>> {
+    // a fixed-size array instead of a Vec - there are never more than five
+    // fields we care about, so there's nothing to allocate.
+    let mut fields = [""; MAX_FIELDS];
+    let mut count = 0;
+    let mut rest = Some(line);
+
+    while let Some(text) = rest {
+        let (field, remainder) = split_field(text, delimiter)?;
+        if count < MAX_FIELDS {
+            fields[count] = field;
+        }
+        count += 1;
+        rest = remainder;
+    }
+
+    if !(4..=MAX_FIELDS).contains(&count) {
+        return Err(RecordErrorKind::FieldCount(count));
+    }
+    let [username, email, active, sign_in_count, hash] = fields;
+    Ok(UserRef {
+        username,
+        email,
+        active: active
+            .parse()
+            .map_err(|_| RecordErrorKind::Active(active))?,
+        sign_in_count: sign_in_count
+            .parse()
+            .map_err(|_| RecordErrorKind::SignInCount(sign_in_count))?,
+        password_hash: Some(hash).filter(|hash| !hash.is_empty()),
+    })
+}
+
+// Takes the first field off the front of `text`, returning it and whatever
+// comes after the delimiter (None if it was the last field).
+fn split_field(text: &str, delimiter: char) -> Result<(&str, Option<&str>), RecordErrorKind<'_>> {
+    if let Some(quoted) = text.strip_prefix('"') {
+        let end = quoted.find('"').ok_or(RecordErrorKind::UnterminatedQuote)?;
+        let (field, after) = (&quoted[..end], &quoted[end + 1..]);
+        if after.starts_with('"') {
+            return Err(RecordErrorKind::EscapedQuote);
+        }
+        return match after.strip_prefix(delimiter) {
+            Some(remainder) => Ok((field, Some(remainder))),
+            None if after.is_empty() => Ok((field, None)),
+            // something other than a delimiter after the closing quote
+            // ("ann"x), so that quote didn't really end the field.
+            None => Err(RecordErrorKind::UnterminatedQuote),
+        };
+    }
+    Ok(match text.split_once(delimiter) {
+        Some((field, remainder)) => (field, Some(remainder)),
+        None => (text, None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHQ$aGFzaGhhc2hoYXNoaGFzaA";
+
+    fn ann() -> UserRef<'static> {
+        UserRef {
+            username: "ann",
+            email: "ann@example.com",
+            active: true,
+            sign_in_count: 3,
+            password_hash: None,
+        }
+    }
+
+    #[test]
+    fn split_field_takes_one_field_at_a_time() {
+        assert_eq!(split_field("a,b,c", ','), Ok(("a", Some("b,c"))));
+        assert_eq!(split_field("c", ','), Ok(("c", None)));
+        assert_eq!(split_field("", ','), Ok(("", None)));
+        assert_eq!(split_field(",", ','), Ok(("", Some(""))));
+        assert_eq!(split_field("a\tb,c", '\t'), Ok(("a", Some("b,c"))));
+    }
+
+    #[test]
+    fn split_field_reads_quotes() {
+        assert_eq!(split_field(r#""a,b",c"#, ','), Ok(("a,b", Some("c"))));
+        assert_eq!(split_field(r#""a b""#, ','), Ok(("a b", None)));
+        assert_eq!(split_field(r#""",c"#, ','), Ok(("", Some("c"))));
+        assert_eq!(
+            split_field(r#""a,b"#, ','),
+            Err(RecordErrorKind::UnterminatedQuote)
+        );
+        assert_eq!(
+            split_field(r#""ann"x,c"#, ','),
+            Err(RecordErrorKind::UnterminatedQuote)
+        );
+        assert_eq!(
+            split_field(r#""say ""hi""",c"#, ','),
+            Err(RecordErrorKind::EscapedQuote)
+        );
+        // a quote that isn't at the start is just a character.
+        assert_eq!(split_field(r#"a"b,c"#, ','), Ok((r#"a"b"#, Some("c"))));
+    }
+
+    #[test]
+    fn parse_line_reads_every_column() {
+        assert_eq!(parse_line("ann,ann@example.com,true,3", ','), Ok(ann()));
+        assert_eq!(parse_line("ann,ann@example.com,true,3,", ','), Ok(ann()));
+        // (hashes have commas in them, so in a CSV file they're quoted.)
+        assert_eq!(
+            parse_line(&format!(r#"ann,ann@example.com,false,3,"{HASH}""#), ','),
+            Ok(UserRef {
+                active: false,
+                password_hash: Some(HASH),
+                ..ann()
+            })
+        );
+        assert_eq!(
+            parse_line(r#""ann","ann@example.com",true,"3""#, ','),
+            Ok(ann())
+        );
+        assert_eq!(parse_line("ann\tann@example.com\ttrue\t3", '\t'), Ok(ann()));
+    }
+
+    #[test]
+    fn parse_line_rejects_bad_records() {
+        assert_eq!(
+            parse_line("ann,ann@example.com,true", ','),
+            Err(RecordErrorKind::FieldCount(3))
+        );
+        assert_eq!(
+            parse_line("ann,ann@example.com,true,3,h,extra,more", ','),
+            Err(RecordErrorKind::FieldCount(7))
+        );
+        // with the wrong delimiter it's all one field.
+        assert_eq!(
+            parse_line("ann,ann@example.com,true,3", '\t'),
+            Err(RecordErrorKind::FieldCount(1))
+        );
+        assert_eq!(
+            parse_line("ann,ann@example.com,yes,3", ','),
+            Err(RecordErrorKind::Active("yes"))
+        );
+        assert_eq!(
+            parse_line("ann,ann@example.com,true,-1", ','),
+            Err(RecordErrorKind::SignInCount("-1"))
+        );
+        assert_eq!(
+            parse_line(r#""a ""b"" c",ann@example.com,true,3"#, ','),
+            Err(RecordErrorKind::EscapedQuote)
+        );
+    }
+
+    #[test]
+    fn records_skip_the_header_and_blank_lines() {
+        let text = "username,email,active,sign_in_count\n\nann,ann@example.com,true,3\n   \nbob,bob@example.com,false,0\n";
+        let users: Vec<_> = parse_records(text, ',').collect::<Result<_, _>>().unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0], ann());
+        assert_eq!(users[1].username, "bob");
+
+        let tsv = "username\temail\tactive\tsign_in_count\nann\tann@example.com\ttrue\t3";
+        assert_eq!(parse_records(tsv, '\t').collect::<Vec<_>>(), [Ok(ann())]);
+    }
+
+    #[test]
+    fn only_the_first_line_can_be_a_header() {
+        // a header line anywhere else is read as a record (and fails), and
+        // "usernames,..." isn't a header at all.
+        let text = "ann,ann@example.com,true,3\nusername,email,active,sign_in_count";
+        let errors: Vec<_> = parse_records(text, ',').filter_map(Result::err).collect();
+        assert_eq!(
+            errors,
+            [RecordError {
+                line: 2,
+                kind: RecordErrorKind::Active("active")
+            }]
+        );
+        let text = "usernames,email,active,sign_in_count";
+        assert_eq!(
+            parse_records(text, ',').next(),
+            Some(Err(RecordError {
+                line: 1,
+                kind: RecordErrorKind::Active("active")
+            }))
+        );
+    }
+
+    #[test]
+    fn errors_say_which_line() {
+        let text = "ann,ann@example.com,true,3\n\nbob,bob@example.com,maybe,0";
+        let error = parse_records(text, ',').nth(1).unwrap().unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(
+            error.to_string(),
+            "line 3: active must be true or false, got 'maybe'"
+        );
+    }
+
+    #[test]
+    fn to_user_and_back() {
+        let record = UserRef {
+            password_hash: Some(HASH),
+            ..ann()
+        };
+        let user = record.to_user().unwrap();
+        assert_eq!(user.username, "ann");
+        assert_eq!(
+            user.credentials.as_ref().map(Credentials::as_str),
+            Some(HASH)
+        );
+        assert_eq!(UserRef::from(&user), record);
+
+        let bad = UserRef {
+            password_hash: Some("not a hash"),
+            ..ann()
+        };
+        assert!(User::try_from(bad).is_err());
+    }
+}
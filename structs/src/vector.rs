@@ -0,0 +1,579 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+// 3D POINTS AND VECTORS
+
+// `struct Point(i32, i32, i32)` can't be added, scaled or measured. The
+// types here can, and they keep the lesson of Color vs Point: a Point3 is a
+// position and a Vec3 is a direction-and-distance, and the compiler only
+// lets you combine them in ways that mean something:
+//
+//   Point3 - Point3 = Vec3     (how to get from one place to another)
+//   Point3 + Vec3   = Point3   (move a place by an offset)
+//   Vec3 + Vec3     = Vec3     (chain two offsets)
+//   Vec3 * number   = Vec3     (stretch an offset)
+//   Point3 + Point3 = error!   (adding two places means nothing)
+//
+// Both are generic over the number type, so Point3<i32> works for grid
+// positions and Point3<f64> for anything that needs square roots or angles.
+
+// NUMBER TYPES
+
+// What a coordinate needs to be able to do. Implemented below for the
+// usual signed integers and floats.
+pub trait Scalar:
+    Copy
+    + PartialOrd
+    + fmt::Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn abs(self) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+// Floats get square roots and trig on top.
+pub trait Real: Scalar {
+    fn sqrt(self) -> Self;
+    fn sin_cos(self) -> (Self, Self);
+    fn epsilon() -> Self;
+}
+
+macro_rules! scalar {
+    ($($t:ty),*) => {$(
+        impl Scalar for $t {
+            fn zero() -> Self { 0 as $t }
+            fn one() -> Self { 1 as $t }
+            fn abs(self) -> Self { <$t>::abs(self) }
+            fn to_f64(self) -> f64 { self as f64 }
+        }
+    )*};
+}
+
+macro_rules! real {
+    ($($t:ident),*) => {$(
+        impl Real for $t {
+            fn sqrt(self) -> Self { $t::sqrt(self) }
+            fn sin_cos(self) -> (Self, Self) { $t::sin_cos(self) }
+            fn epsilon() -> Self { $t::EPSILON }
+        }
+    )*};
+}
+
+scalar!(i8, i16, i32, i64, i128, f32, f64);
+real!(f32, f64);
+
+// THE TYPES
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Point3<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Vec3<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T: Scalar> Point3<T> {
+    pub fn new(x: T, y: T, z: T) -> Point3<T> {
+        Point3 { x, y, z }
+    }
+
+    pub fn origin() -> Point3<T> {
+        Point3::new(T::zero(), T::zero(), T::zero())
+    }
+
+    // The offset from the origin to this point.
+    pub fn to_vec(self) -> Vec3<T> {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    // DISTANCES
+
+    // Straight-line distance ("as the crow flies"). The square root means
+    // this is always an f64, whatever the coordinates are.
+    pub fn distance(self, other: Point3<T>) -> f64 {
+        (other - self).length()
+    }
+
+    // Distance along the grid, like walking city blocks: |dx| + |dy| + |dz|.
+    pub fn manhattan(self, other: Point3<T>) -> T {
+        let d = other - self;
+        d.x.abs() + d.y.abs() + d.z.abs()
+    }
+
+    // The biggest single-axis difference - how many moves a chess king
+    // needs (in 3D).
+    pub fn chebyshev(self, other: Point3<T>) -> T {
+        let d = other - self;
+        max(max(d.x.abs(), d.y.abs()), d.z.abs())
+    }
+}
+
+fn max<T: PartialOrd>(a: T, b: T) -> T {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+impl<T: Scalar> Vec3<T> {
+    pub fn new(x: T, y: T, z: T) -> Vec3<T> {
+        Vec3 { x, y, z }
+    }
+
+    pub fn zero() -> Vec3<T> {
+        Vec3::new(T::zero(), T::zero(), T::zero())
+    }
+
+    // Big when the vectors point the same way, zero when they're at right
+    // angles, negative when they point apart.
+    pub fn dot(self, other: Vec3<T>) -> T {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    // A vector at right angles to both (right-hand rule), as long as the
+    // area of the parallelogram they make.
+    pub fn cross(self, other: Vec3<T>) -> Vec3<T> {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    // Squared length - no square root, so it stays exact for integers and
+    // is enough for comparing which of two vectors is longer.
+    pub fn length_squared(self) -> T {
+        self.dot(self)
+    }
+
+    // Each part goes to f64 before it's squared: 50_000 is a fine i32, but
+    // 50_000 * 50_000 isn't.
+    pub fn length(self) -> f64 {
+        let [x, y, z] = [self.x, self.y, self.z].map(T::to_f64);
+        (x * x + y * y + z * z).sqrt()
+    }
+}
+
+impl<T: Real> Vec3<T> {
+    // The same direction with a length of 1, or None for the zero vector
+    // (which has no direction).
+    pub fn normalized(self) -> Option<Vec3<T>> {
+        let length = self.length_squared().sqrt();
+        if length <= T::epsilon() {
+            None
+        } else {
+            Some(self / length)
+        }
+    }
+}
+
+// OPERATORS
+
+impl<T: Scalar> Sub for Point3<T> {
+    type Output = Vec3<T>;
+    fn sub(self, other: Point3<T>) -> Vec3<T> {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl<T: Scalar> Add<Vec3<T>> for Point3<T> {
+    type Output = Point3<T>;
+    fn add(self, v: Vec3<T>) -> Point3<T> {
+        Point3::new(self.x + v.x, self.y + v.y, self.z + v.z)
+    }
+}
+
+impl<T: Scalar> Sub<Vec3<T>> for Point3<T> {
+    type Output = Point3<T>;
+    fn sub(self, v: Vec3<T>) -> Point3<T> {
+        Point3::new(self.x - v.x, self.y - v.y, self.z - v.z)
+    }
+}
+
+impl<T: Scalar> AddAssign<Vec3<T>> for Point3<T> {
+    fn add_assign(&mut self, v: Vec3<T>) {
+        *self = *self + v;
+    }
+}
+
+impl<T: Scalar> SubAssign<Vec3<T>> for Point3<T> {
+    fn sub_assign(&mut self, v: Vec3<T>) {
+        *self = *self - v;
+    }
+}
+
+impl<T: Scalar> Add for Vec3<T> {
+    type Output = Vec3<T>;
+    fn add(self, other: Vec3<T>) -> Vec3<T> {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl<T: Scalar> Sub for Vec3<T> {
+    type Output = Vec3<T>;
+    fn sub(self, other: Vec3<T>) -> Vec3<T> {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl<T: Scalar> AddAssign for Vec3<T> {
+    fn add_assign(&mut self, other: Vec3<T>) {
+        *self = *self + other;
+    }
+}
+
+impl<T: Scalar> SubAssign for Vec3<T> {
+    fn sub_assign(&mut self, other: Vec3<T>) {
+        *self = *self - other;
+    }
+}
+
+impl<T: Scalar> Neg for Vec3<T> {
+    type Output = Vec3<T>;
+    fn neg(self) -> Vec3<T> {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl<T: Scalar> Mul<T> for Vec3<T> {
+    type Output = Vec3<T>;
+    fn mul(self, k: T) -> Vec3<T> {
+        Vec3::new(self.x * k, self.y * k, self.z * k)
+    }
+}
+
+impl<T: Scalar> Div<T> for Vec3<T> {
+    type Output = Vec3<T>;
+    fn div(self, k: T) -> Vec3<T> {
+        Vec3::new(self.x / k, self.y / k, self.z / k)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Point3<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Vec3<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}, {}, {}>", self.x, self.y, self.z)
+    }
+}
+
+// TRANSFORMS
+
+// A 4x4 matrix can move, scale and rotate in one go. Points and vectors get
+// an implied fourth coordinate: 1 for points (so they're moved by
+// translations) and 0 for vectors (so they aren't - an offset is the same
+// offset wherever it starts). Only affine transforms are supported, so the
+// bottom row is always 0, 0, 0, 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4<T> {
+    pub rows: [[T; 4]; 4],
+}
+
+impl<T: Scalar> Matrix4<T> {
+    pub fn identity() -> Matrix4<T> {
+        let (o, i) = (T::zero(), T::one());
+        Matrix4 {
+            rows: [[i, o, o, o], [o, i, o, o], [o, o, i, o], [o, o, o, i]],
+        }
+    }
+
+    pub fn translation(by: Vec3<T>) -> Matrix4<T> {
+        let mut m = Matrix4::identity();
+        m.rows[0][3] = by.x;
+        m.rows[1][3] = by.y;
+        m.rows[2][3] = by.z;
+        m
+    }
+
+    pub fn scale(x: T, y: T, z: T) -> Matrix4<T> {
+        let mut m = Matrix4::identity();
+        m.rows[0][0] = x;
+        m.rows[1][1] = y;
+        m.rows[2][2] = z;
+        m
+    }
+
+    // `self` first, then `next` - so a.then(b) applied to p is b(a(p)).
+    pub fn then(self, next: Matrix4<T>) -> Matrix4<T> {
+        next * self
+    }
+
+    pub fn transform_point(&self, p: Point3<T>) -> Point3<T> {
+        let r = &self.rows;
+        Point3::new(
+            r[0][0] * p.x + r[0][1] * p.y + r[0][2] * p.z + r[0][3],
+            r[1][0] * p.x + r[1][1] * p.y + r[1][2] * p.z + r[1][3],
+            r[2][0] * p.x + r[2][1] * p.y + r[2][2] * p.z + r[2][3],
+        )
+    }
+
+    pub fn transform_vec(&self, v: Vec3<T>) -> Vec3<T> {
+        let r = &self.rows;
+        Vec3::new(
+            r[0][0] * v.x + r[0][1] * v.y + r[0][2] * v.z,
+            r[1][0] * v.x + r[1][1] * v.y + r[1][2] * v.z,
+            r[2][0] * v.x + r[2][1] * v.y + r[2][2] * v.z,
+        )
+    }
+}
+
+impl<T: Real> Matrix4<T> {
+    // Rotations by `angle` radians, counter-clockwise when looking down the
+    // axis towards the origin.
+    pub fn rotation_x(angle: T) -> Matrix4<T> {
+        let (sin, cos) = angle.sin_cos();
+        let mut m = Matrix4::identity();
+        m.rows[1][1] = cos;
+        m.rows[1][2] = -sin;
+        m.rows[2][1] = sin;
+        m.rows[2][2] = cos;
+        m
+    }
+
+    pub fn rotation_y(angle: T) -> Matrix4<T> {
+        let (sin, cos) = angle.sin_cos();
+        let mut m = Matrix4::identity();
+        m.rows[0][0] = cos;
+        m.rows[0][2] = sin;
+        m.rows[2][0] = -sin;
+        m.rows[2][2] = cos;
+        m
+    }
+
+    pub fn rotation_z(angle: T) -> Matrix4<T> {
+        let (sin, cos) = angle.sin_cos();
+        let mut m = Matrix4::identity();
+        m.rows[0][0] = cos;
+        m.rows[0][1] = -sin;
+        m.rows[1][0] = sin;
+        m.rows[1][1] = cos;
+        m
+    }
+}
+
+impl<T: Scalar> Mul for Matrix4<T> {
+    type Output = Matrix4<T>;
+    fn mul(self, other: Matrix4<T>) -> Matrix4<T> {
+        let mut rows = [[T::zero(); 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                for k in 0..4 {
+                    *cell = *cell + self.rows[i][k] * other.rows[k][j];
+                }
+            }
+        }
+        Matrix4 { rows }
+    }
+}
+
+impl<T: Scalar> Mul<Point3<T>> for Matrix4<T> {
+    type Output = Point3<T>;
+    fn mul(self, p: Point3<T>) -> Point3<T> {
+        self.transform_point(p)
+    }
+}
+
+impl<T: Scalar> Mul<Vec3<T>> for Matrix4<T> {
+    type Output = Vec3<T>;
+    fn mul(self, v: Vec3<T>) -> Vec3<T> {
+        self.transform_vec(v)
+    }
+}
+
+// APPROXIMATE EQUALITY
+
+// Float maths picks up tiny errors - rotating by 90 degrees gives
+// 6.1e-17 instead of 0 - so == is almost never what you want. approx_eq
+// treats values as equal when they're within `tolerance` of each other,
+// scaled up for big numbers (where the gaps between floats are bigger).
+pub trait ApproxEq {
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool;
+}
+
+pub const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+impl ApproxEq for f64 {
+    fn approx_eq(&self, other: &f64, tolerance: f64) -> bool {
+        let scale = self.abs().max(other.abs()).max(1.0);
+        (self - other).abs() <= tolerance * scale
+    }
+}
+
+impl ApproxEq for f32 {
+    fn approx_eq(&self, other: &f32, tolerance: f64) -> bool {
+        (*self as f64).approx_eq(&(*other as f64), tolerance)
+    }
+}
+
+impl<T: ApproxEq> ApproxEq for Point3<T> {
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        self.x.approx_eq(&other.x, tolerance)
+            && self.y.approx_eq(&other.y, tolerance)
+            && self.z.approx_eq(&other.z, tolerance)
+    }
+}
+
+impl<T: ApproxEq> ApproxEq for Vec3<T> {
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        self.x.approx_eq(&other.x, tolerance)
+            && self.y.approx_eq(&other.y, tolerance)
+            && self.z.approx_eq(&other.z, tolerance)
+    }
+}
+
+impl<T: ApproxEq> ApproxEq for Matrix4<T> {
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        self.rows
+            .iter()
+            .flatten()
+            .zip(other.rows.iter().flatten())
+            .all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn points_and_vectors_combine() {
+        let a = Point3::new(1, 2, 3);
+        let b = Point3::new(4, 6, 3);
+        let v = b - a;
+        assert_eq!(v, Vec3::new(3, 4, 0));
+        assert_eq!(a + v, b);
+        assert_eq!(b - v, a);
+        assert_eq!(v + v, v * 2);
+        assert_eq!(v - v, Vec3::zero());
+        assert_eq!(-v, Vec3::new(-3, -4, 0));
+        assert_eq!(v * 4 / 2, Vec3::new(6, 8, 0));
+        assert_eq!(Point3::origin() + a.to_vec(), a);
+
+        let mut p = a;
+        p += v;
+        assert_eq!(p, b);
+        p -= v;
+        assert_eq!(p, a);
+        let mut w = v;
+        w += v;
+        w -= Vec3::new(1, 1, 1);
+        assert_eq!(w, Vec3::new(5, 7, -1));
+
+        assert_eq!(a.to_string(), "(1, 2, 3)");
+        assert_eq!(v.to_string(), "<3, 4, 0>");
+    }
+
+    #[test]
+    fn distances() {
+        let a = Point3::new(1, 2, 3);
+        let b = Point3::new(4, 6, 3);
+        assert_eq!(a.distance(b), 5.0);
+        assert_eq!(a.manhattan(b), 7);
+        assert_eq!(a.chebyshev(b), 4);
+        assert_eq!(b.chebyshev(a), 4);
+        assert_eq!(
+            Point3::new(0.0, 0.0, 0.0).distance(Point3::new(1.0, 2.0, 2.0)),
+            3.0
+        );
+    }
+
+    #[test]
+    fn large_integer_coordinates_do_not_overflow() {
+        let far = Point3::new(50_000, 0, 0);
+        assert_eq!(Point3::origin().distance(far), 50_000.0);
+        let diagonal = Vec3::new(i32::MAX, i32::MAX, 0).length();
+        assert!(diagonal.approx_eq(&(i32::MAX as f64 * 2f64.sqrt()), DEFAULT_TOLERANCE));
+        // 30_000 and 20_000 fit in an i16; their squares don't.
+        assert_eq!(Vec3::new(-30_000i16, 0, 20_000).length(), 1.3e9f64.sqrt());
+    }
+
+    #[test]
+    fn dot_and_cross() {
+        let x = Vec3::new(1, 0, 0);
+        let y = Vec3::new(0, 1, 0);
+        assert_eq!(x.dot(y), 0);
+        assert_eq!(x.dot(x * 3), 3);
+        assert_eq!(x.dot(-x), -1);
+        assert_eq!(x.cross(y), Vec3::new(0, 0, 1));
+        assert_eq!(y.cross(x), Vec3::new(0, 0, -1));
+        assert_eq!(Vec3::new(2, 3, 4).length_squared(), 29);
+    }
+
+    #[test]
+    fn normalizing() {
+        let v = Vec3::new(3.0, 0.0, 4.0).normalized().unwrap();
+        assert!(v.approx_eq(&Vec3::new(0.6, 0.0, 0.8), DEFAULT_TOLERANCE));
+        assert!(v.length().approx_eq(&1.0, DEFAULT_TOLERANCE));
+        assert_eq!(Vec3::<f64>::zero().normalized(), None);
+        assert_eq!(Vec3::new(0.0f32, 1e-9, 0.0).normalized(), None);
+    }
+
+    #[test]
+    fn affine_transforms() {
+        let p = Point3::new(1, 2, 3);
+        let v = Vec3::new(1, 2, 3);
+        assert_eq!(Matrix4::identity() * p, p);
+
+        let moved = Matrix4::translation(Vec3::new(10, 20, 30));
+        assert_eq!(moved * p, Point3::new(11, 22, 33));
+        // offsets don't move.
+        assert_eq!(moved * v, v);
+
+        let scaled = Matrix4::scale(2, 3, 4);
+        assert_eq!(scaled * p, Point3::new(2, 6, 12));
+        assert_eq!(scaled * v, Vec3::new(2, 6, 12));
+
+        // scale first, then move - and the other way round is different.
+        assert_eq!(scaled.then(moved) * p, Point3::new(12, 26, 42));
+        assert_eq!(moved.then(scaled) * p, Point3::new(22, 66, 132));
+        assert_eq!(scaled.then(moved), moved * scaled);
+    }
+
+    #[test]
+    fn rotations() {
+        let x = Point3::new(1.0, 0.0, 0.0);
+        let y = Point3::new(0.0, 1.0, 0.0);
+        let z = Point3::new(0.0, 0.0, 1.0);
+        let quarter = FRAC_PI_2;
+        assert!((Matrix4::rotation_z(quarter) * x).approx_eq(&y, DEFAULT_TOLERANCE));
+        assert!((Matrix4::rotation_x(quarter) * y).approx_eq(&z, DEFAULT_TOLERANCE));
+        assert!((Matrix4::rotation_y(quarter) * z).approx_eq(&x, DEFAULT_TOLERANCE));
+        // not exactly, though.
+        assert_ne!(Matrix4::rotation_z(quarter) * x, y);
+
+        let full_turn = (0..4).fold(Matrix4::identity(), |m, _| {
+            m.then(Matrix4::rotation_z(quarter))
+        });
+        assert!(full_turn.approx_eq(&Matrix4::identity(), DEFAULT_TOLERANCE));
+        assert!(!full_turn.approx_eq(&Matrix4::rotation_z(quarter), DEFAULT_TOLERANCE));
+    }
+
+    #[test]
+    fn approx_eq_scales_with_size() {
+        assert!(0.1f64.approx_eq(&(0.3 - 0.2), DEFAULT_TOLERANCE));
+        assert!(!0.1f64.approx_eq(&0.1001, DEFAULT_TOLERANCE));
+        // near 1e12 the tolerance grows with the numbers.
+        assert!(1e12f64.approx_eq(&(1e12 + 100.0), DEFAULT_TOLERANCE));
+        assert!(!1e12f64.approx_eq(&(1e12 + 1e4), DEFAULT_TOLERANCE));
+        assert!(1.0f32.approx_eq(&1.000_000_1, 1e-6));
+        assert!(!f64::NAN.approx_eq(&f64::NAN, DEFAULT_TOLERANCE));
+    }
+}
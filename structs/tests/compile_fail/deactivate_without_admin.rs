@@ -0,0 +1,7 @@
+use structs::account::{Account, Verified};
+
+fn deactivate(by: &Account<Verified>, other: &mut Account<Verified>) {
+    by.deactivate(other);
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use structs::account::{Account, Admin, Verified};
+
+fn promote(account: Account<Verified>, by: &Account<Verified>) -> Account<Admin> {
+    account.promote(by)
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use structs::account::Account;
+use structs::session::SessionManager;
+use structs::user::build_user;
+
+fn main() {
+    let mut sessions = SessionManager::new();
+    let mut account = Account::new(build_user(
+        String::from("zed@example.com"),
+        String::from("zed"),
+    ));
+    let _ = account.sign_in(&mut sessions);
+}
@@ -0,0 +1,13 @@
+use structs::account::{Account, EmailVerifier};
+use structs::user::build_user;
+
+fn main() {
+    let mut verifier = EmailVerifier::new();
+    let account = Account::new(build_user(
+        String::from("zed@example.com"),
+        String::from("zed"),
+    ));
+    let code = verifier.issue(&account);
+    let _ = verifier.verify(account, &code);
+    let _ = verifier.verify(account, &code);
+}
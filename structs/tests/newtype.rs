@@ -0,0 +1,23 @@
+// newtype! is exported, so a crate that isn't this one (which is what each
+// file in tests/ is) can use it with only the macro imported.
+
+use structs::newtype;
+
+newtype! {
+    pub struct Celsius(i32);
+    validate: |c: &i32| structs::newtype::in_range(*c, -273, i32::MAX)
+        => structs::newtype::OutOfRange<i32>;
+}
+
+newtype! {
+    pub struct Tag(String);
+    normalize: |tag: String| tag.to_lowercase();
+}
+
+#[test]
+fn newtypes_work_outside_the_crate() {
+    assert_eq!(Celsius::new(20).unwrap().to_string(), "20");
+    assert!(Celsius::new(-300).is_err());
+    assert!("-300".parse::<Celsius>().is_err());
+    assert_eq!(Tag::new("Rust").unwrap().get(), "rust");
+}
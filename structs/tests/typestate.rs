@@ -0,0 +1,10 @@
+// account.rs promises that some mistakes don't compile at all. These check
+// that they really don't: each file in compile_fail/ must fail to build,
+// with the error saved next to it in a .stderr file. (To update the saved
+// errors after a compiler upgrade, run with TRYBUILD=overwrite.)
+
+#[test]
+fn typestate_mistakes_dont_compile() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/compile_fail/*.rs");
+}